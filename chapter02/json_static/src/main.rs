@@ -1,26 +1,4 @@
-use serde_derive::{Deserialize, Serialize};
-
-#[derive(Deserialize, Serialize, Debug)]
-struct Product {
-    id: u32,
-    category: String,
-    name: String,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-struct Sale {
-    id: String,
-    product_id: u32,
-    date: i64,
-    quantity: f64,
-    unit: String,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-struct SalesAndProducts {
-    products: Vec<Product>,
-    sales: Vec<Sale>,
-}
+use sales::SalesAndProducts;
 
 fn main() -> Result<(), std::io::Error> {
     let input_path = std::env::args().nth(1).unwrap();
@@ -0,0 +1,127 @@
+use std::io::{Read, Write};
+
+use crate::error::{Error, Result};
+use crate::format::Format;
+use crate::location::{Location, Locations};
+use crate::model::{Product, Sale, SalesAndProducts};
+
+/// The products and the sales share a table, told apart by the first column,
+/// leaving empty the columns of the other record type.
+const HEADER: [&str; 8] = [
+    "record",
+    "id",
+    "category",
+    "name",
+    "product_id",
+    "date",
+    "quantity",
+    "unit",
+];
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        let line = e.position().map_or(0, |p| p.line());
+        let message = e.to_string();
+        match e.into_kind() {
+            csv::ErrorKind::Io(e) => Error::Io(e),
+            _ => Error::Csv { line, message },
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(record: &csv::StringRecord, column: usize) -> Result<T> {
+    let text = record.get(column).unwrap_or("");
+    text.parse::<T>().map_err(|_| Error::Csv {
+        line: record.position().map_or(0, |p| p.line()),
+        message: format!("invalid value of {}: \"{}\"", HEADER[column], text),
+    })
+}
+
+fn text(record: &csv::StringRecord, column: usize) -> String {
+    record.get(column).unwrap_or("").to_string()
+}
+
+pub fn read(reader: impl Read) -> Result<SalesAndProducts> {
+    read_located(reader).map(|(data, _)| data)
+}
+
+/// Reads a dataset, with the line of each record.
+pub fn read_located(reader: impl Read) -> Result<(SalesAndProducts, Locations)> {
+    let mut reader = csv::Reader::from_reader(reader);
+    if reader.headers()? != HEADER.as_ref() {
+        return Err(Error::Csv {
+            line: 1,
+            message: format!("the header must be: {}", HEADER.join(",")),
+        });
+    }
+    let mut data = SalesAndProducts::default();
+    let mut locations = Locations::new(Format::Csv);
+    for record in reader.records() {
+        let record = record?;
+        let location = Location::new(format!(
+            "line {}",
+            record.position().map_or(0, |p| p.line())
+        ));
+        match record.get(0) {
+            Some("product") => {
+                data.products.push(Product {
+                    id: parse(&record, 1)?,
+                    category: text(&record, 2),
+                    name: text(&record, 3),
+                });
+                locations.products.push(location);
+            }
+            Some("sale") => {
+                data.sales.push(Sale {
+                    id: text(&record, 1),
+                    product_id: parse(&record, 4)?,
+                    date: parse(&record, 5)?,
+                    quantity: parse(&record, 6)?,
+                    unit: text(&record, 7),
+                });
+                locations.sales.push(location);
+            }
+            other => {
+                return Err(Error::Csv {
+                    line: record.position().map_or(0, |p| p.line()),
+                    message: format!(
+                        "the record must be \"product\" or \"sale\", not \"{}\"",
+                        other.unwrap_or("")
+                    ),
+                })
+            }
+        }
+    }
+    Ok((data, locations))
+}
+
+pub fn write(writer: impl Write, data: &SalesAndProducts) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(HEADER)?;
+    for product in &data.products {
+        writer.write_record([
+            "product",
+            &product.id.to_string(),
+            &product.category,
+            &product.name,
+            "",
+            "",
+            "",
+            "",
+        ])?;
+    }
+    for sale in &data.sales {
+        writer.write_record([
+            "sale",
+            &sale.id,
+            "",
+            "",
+            &sale.product_id.to_string(),
+            &sale.date.to_string(),
+            &sale.quantity.to_string(),
+            &sale.unit,
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+/// The failure of reading or writing a dataset.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    TomlRead(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+    /// The line and the column are counted from 1.
+    Xml {
+        line: u64,
+        column: u64,
+        message: String,
+    },
+    Sqlite(rusqlite::Error),
+    /// The line is counted from 1.
+    Csv {
+        line: u64,
+        message: String,
+    },
+    /// An operation which the format cannot perform.
+    Unsupported(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Error::TomlRead(e) => write!(f, "Invalid TOML: {}", e),
+            Error::TomlWrite(e) => write!(f, "Cannot write TOML: {}", e),
+            Error::Xml {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Invalid XML at line {} column {}: {}",
+                line, column, message
+            ),
+            Error::Sqlite(e) => write!(f, "SQLite: {}", e),
+            Error::Csv { line, message } => write!(f, "Invalid CSV at line {}: {}", line, message),
+            Error::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    /// The failures of the stream are not about the JSON, as a closed pipe.
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            Error::Io(e.into())
+        } else {
+            Error::Json(e)
+        }
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::TomlRead(e)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Error::TomlWrite(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Sqlite(e)
+    }
+}
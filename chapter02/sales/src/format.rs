@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::location::Locations;
+use crate::model::SalesAndProducts;
+use crate::{csv_codec, json_codec, sqlite_codec, toml_codec, xml_codec};
+
+/// A file format of the datasets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+    Xml,
+    Sqlite,
+    Csv,
+}
+
+impl Format {
+    pub const ALL: [Format; 5] = [
+        Format::Json,
+        Format::Toml,
+        Format::Xml,
+        Format::Sqlite,
+        Format::Csv,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Toml => "toml",
+            Format::Xml => "xml",
+            Format::Sqlite => "sqlite",
+            Format::Csv => "csv",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The format of a file, by its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Format> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "db" | "sqlite3" => Some(Format::Sqlite),
+            extension => Format::from_name(extension),
+        }
+    }
+
+    /// Reads a dataset from a stream, which a database cannot be.
+    pub fn read(self, reader: impl Read) -> Result<SalesAndProducts> {
+        match self {
+            Format::Json => json_codec::read(reader),
+            Format::Toml => toml_codec::read(reader),
+            Format::Xml => xml_codec::read(reader),
+            Format::Csv => csv_codec::read(reader),
+            Format::Sqlite => Err(Error::Unsupported(
+                "A SQLite database can only be read from a file".to_string(),
+            )),
+        }
+    }
+
+    /// Writes a dataset to a stream, which a database cannot be.
+    /// Only JSON, TOML and XML have a compact form, if not `pretty`.
+    pub fn write(self, writer: impl Write, data: &SalesAndProducts, pretty: bool) -> Result<()> {
+        match self {
+            Format::Json => json_codec::write(writer, data, pretty),
+            Format::Toml => toml_codec::write(writer, data, pretty),
+            Format::Xml => xml_codec::write(writer, data, pretty),
+            Format::Csv => csv_codec::write(writer, data),
+            Format::Sqlite => Err(Error::Unsupported(
+                "A SQLite database can only be written to a file".to_string(),
+            )),
+        }
+    }
+
+    /// Reads a dataset from a stream, with the location of each record.
+    pub fn read_located(self, reader: impl Read) -> Result<(SalesAndProducts, Locations)> {
+        match self {
+            Format::Xml => xml_codec::read_located(reader),
+            Format::Csv => csv_codec::read_located(reader),
+            format => {
+                let data = format.read(reader)?;
+                let locations = Locations::by_index(format, &data);
+                Ok((data, locations))
+            }
+        }
+    }
+
+    pub fn read_file_located(
+        self,
+        path: impl AsRef<Path>,
+    ) -> Result<(SalesAndProducts, Locations)> {
+        match self {
+            Format::Sqlite => sqlite_codec::read_file_located(path),
+            format => format.read_located(BufReader::new(File::open(path)?)),
+        }
+    }
+
+    pub fn read_file(self, path: impl AsRef<Path>) -> Result<SalesAndProducts> {
+        match self {
+            Format::Sqlite => sqlite_codec::read_file(path),
+            format => format.read(BufReader::new(File::open(path)?)),
+        }
+    }
+
+    /// Writes a dataset to a file, replacing it.
+    pub fn write_file(
+        self,
+        path: impl AsRef<Path>,
+        data: &SalesAndProducts,
+        pretty: bool,
+    ) -> Result<()> {
+        match self {
+            Format::Sqlite => sqlite_codec::write_file(path, data),
+            format => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                format.write(&mut writer, data, pretty)?;
+                writer.flush()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        Format::from_name(name).ok_or_else(|| {
+            let names = Format::ALL.iter().map(|f| f.name()).collect::<Vec<_>>();
+            format!("unknown format \"{}\", expected {}", name, names.join(", "))
+        })
+    }
+}
@@ -0,0 +1,19 @@
+use std::io::{Read, Write};
+
+use crate::error::Result;
+use crate::model::SalesAndProducts;
+
+pub fn read(reader: impl Read) -> Result<SalesAndProducts> {
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Writes indented JSON if `pretty`, else a single line.
+pub fn write(mut writer: impl Write, data: &SalesAndProducts, pretty: bool) -> Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, data)?;
+    } else {
+        serde_json::to_writer(&mut writer, data)?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
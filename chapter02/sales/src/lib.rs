@@ -0,0 +1,31 @@
+//! The products and the sales used by the examples of this chapter,
+//! with their reading and writing in each of the supported formats:
+//! JSON, TOML, XML, SQLite and CSV. The `report` module totals the sales
+//! of a SQLite database.
+//!
+//! A dataset read in one format can be written in any other:
+//!
+//! ```no_run
+//! use sales::Format;
+//!
+//! let data = Format::Json.read_file("../data/sales.json").unwrap();
+//! Format::Xml.write_file("sales.xml", &data, true).unwrap();
+//! ```
+
+pub mod csv_codec;
+mod error;
+mod format;
+pub mod json_codec;
+mod location;
+mod model;
+pub mod report;
+pub mod sqlite_codec;
+pub mod toml_codec;
+mod validation;
+pub mod xml_codec;
+
+pub use error::{Error, Result};
+pub use format::Format;
+pub use location::{Location, Locations};
+pub use model::{Product, Sale, SaleWithProduct, SalesAndProducts};
+pub use validation::{validate, Violation, KNOWN_UNITS};
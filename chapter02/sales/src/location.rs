@@ -0,0 +1,73 @@
+use crate::format::Format;
+use crate::model::SalesAndProducts;
+
+/// Where a record is in its source: a JSON pointer, a TOML key, an XML path,
+/// a CSV line or a table row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub path: String,
+    /// Counted from 1, if known.
+    pub line: Option<u64>,
+}
+
+/// The locations of the records of a dataset, in their order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locations {
+    pub format: Format,
+    pub products: Vec<Location>,
+    pub sales: Vec<Location>,
+}
+
+impl Location {
+    pub fn new(path: String) -> Self {
+        Location { path, line: None }
+    }
+}
+
+impl Locations {
+    pub fn new(format: Format) -> Self {
+        Locations {
+            format,
+            products: Vec::new(),
+            sales: Vec::new(),
+        }
+    }
+
+    /// The locations of the records of JSON or TOML, which keep
+    /// the arrays in their order.
+    pub fn by_index(format: Format, data: &SalesAndProducts) -> Self {
+        let path = |array: &str, index: usize| match format {
+            Format::Toml => format!("{}[{}]", array, index),
+            _ => format!("/{}/{}", array, index),
+        };
+        Locations {
+            format,
+            products: (0..data.products.len())
+                .map(|i| Location::new(path("products", i)))
+                .collect(),
+            sales: (0..data.sales.len())
+                .map(|i| Location::new(path("sales", i)))
+                .collect(),
+        }
+    }
+
+    /// Where a field of a record is, named as in the `Product` and
+    /// the `Sale` structs.
+    pub fn field(&self, record: &Location, field: &str) -> String {
+        let located = match self.format {
+            Format::Json => format!("{}/{}", record.path, field),
+            Format::Toml => format!("{}.{}", record.path, field),
+            Format::Xml => format!("{}/{}", record.path, field.replace('_', "-")),
+            Format::Csv => format!("{}, column {}", record.path, field),
+            Format::Sqlite => format!(
+                "{}, column {}",
+                record.path,
+                if field == "date" { "sale_date" } else { field }
+            ),
+        };
+        match record.line {
+            Some(line) => format!("{} (line {})", located, line),
+            None => located,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Product {
+    pub id: u32,
+    pub category: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Sale {
+    pub id: String,
+    pub product_id: u32,
+    /// Seconds since the Unix epoch.
+    pub date: i64,
+    pub quantity: f64,
+    pub unit: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SalesAndProducts {
+    #[serde(default)]
+    pub products: Vec<Product>,
+    #[serde(default)]
+    pub sales: Vec<Sale>,
+}
+
+/// A sale joined with its product, as queried from a database.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct SaleWithProduct {
+    pub category: String,
+    pub name: String,
+    pub quantity: f64,
+    pub unit: String,
+    pub date: i64,
+}
+
+impl SalesAndProducts {
+    pub fn product(&self, id: u32) -> Option<&Product> {
+        self.products.iter().find(|p| p.id == id)
+    }
+
+    /// The sales joined with their products. As in `report::sales_with_products`,
+    /// a sale of an unknown product is kept, named as "unknown product 9".
+    pub fn sales_with_products(&self) -> Vec<SaleWithProduct> {
+        self.sales
+            .iter()
+            .map(|sale| {
+                let (category, name) = match self.product(sale.product_id) {
+                    Some(product) => (product.category.clone(), product.name.clone()),
+                    None => (
+                        "unknown category".to_string(),
+                        format!("unknown product {}", sale.product_id),
+                    ),
+                };
+                SaleWithProduct {
+                    category,
+                    name,
+                    quantity: sale.quantity,
+                    unit: sale.unit.clone(),
+                    date: sale.date,
+                }
+            })
+            .collect()
+    }
+}
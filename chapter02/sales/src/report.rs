@@ -0,0 +1,276 @@
+use rusqlite::{params, Connection};
+use serde_json::{Map, Value};
+use std::io::Write;
+
+use crate::error::Result;
+use crate::model::SaleWithProduct;
+
+/// How the sales are grouped in a report. The quantities are also
+/// grouped by unit, as those of different units cannot be added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grouping {
+    Product,
+    Category,
+    Unit,
+    /// The day in UTC of `sale_date`, as "2009-02-13".
+    Day,
+    /// The month in UTC of `sale_date`, as "2009-02".
+    Month,
+}
+
+/// How a report is written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    /// Aligned columns, for reading.
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportRow {
+    /// The product, the category, the unit, the day or the month.
+    pub key: String,
+    pub unit: String,
+    /// How many sales there are.
+    pub sales: u64,
+    pub quantity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub grouping: Grouping,
+    pub rows: Vec<ReportRow>,
+}
+
+impl Grouping {
+    pub const ALL: [Grouping; 5] = [
+        Grouping::Product,
+        Grouping::Category,
+        Grouping::Unit,
+        Grouping::Day,
+        Grouping::Month,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Grouping::Product => "product",
+            Grouping::Category => "category",
+            Grouping::Unit => "unit",
+            Grouping::Day => "day",
+            Grouping::Month => "month",
+        }
+    }
+
+    /// The SQL expression of the key, for a query of `Sales s` joined with
+    /// `Products p`; the sales of unknown products are kept apart.
+    fn key_expression(self) -> &'static str {
+        match self {
+            Grouping::Product => "COALESCE(p.name, 'unknown product ' || s.product_id)",
+            Grouping::Category => "COALESCE(p.category, 'unknown category')",
+            Grouping::Unit => "s.unit",
+            Grouping::Day => "strftime('%Y-%m-%d', s.sale_date, 'unixepoch')",
+            Grouping::Month => "strftime('%Y-%m', s.sale_date, 'unixepoch')",
+        }
+    }
+}
+
+impl std::str::FromStr for Grouping {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        Grouping::ALL
+            .iter()
+            .copied()
+            .find(|grouping| grouping.name() == name)
+            .ok_or_else(|| {
+                let names = Grouping::ALL.iter().map(|g| g.name()).collect::<Vec<_>>();
+                format!(
+                    "unknown grouping \"{}\", expected {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "table" => Ok(ReportFormat::Table),
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!(
+                "unknown report format \"{}\", expected table, csv, json",
+                name
+            )),
+        }
+    }
+}
+
+/// The sales joined with their products, by date. As in `totals`, a sale
+/// of an unknown product is kept, named as "unknown product 9".
+pub fn sales_with_products(conn: &Connection) -> Result<Vec<SaleWithProduct>> {
+    let mut command = conn.prepare(&format!(
+        "SELECT {}, {}, s.quantity, s.unit, s.sale_date
+        FROM Sales s
+        LEFT JOIN Products p
+        ON p.id = s.product_id
+        ORDER BY s.sale_date, s.rowid",
+        Grouping::Category.key_expression(),
+        Grouping::Product.key_expression()
+    ))?;
+    let sales = command
+        .query_map(params![], |row| {
+            Ok(SaleWithProduct {
+                category: row.get(0)?,
+                name: row.get(1)?,
+                quantity: row.get(2)?,
+                unit: row.get(3)?,
+                date: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(sales)
+}
+
+/// The number of sales and the total quantity of each group and unit,
+/// ordered by group and unit.
+pub fn totals(conn: &Connection, grouping: Grouping) -> Result<Report> {
+    let mut command = conn.prepare(&format!(
+        "SELECT {} AS key, s.unit, COUNT(*), SUM(s.quantity)
+        FROM Sales s
+        LEFT JOIN Products p
+        ON p.id = s.product_id
+        GROUP BY key, s.unit
+        ORDER BY key, s.unit",
+        grouping.key_expression()
+    ))?;
+    let rows = command
+        .query_map(params![], |row| {
+            Ok(ReportRow {
+                key: row.get(0)?,
+                unit: row.get(1)?,
+                sales: row.get::<_, i64>(2)? as u64,
+                quantity: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Report { grouping, rows })
+}
+
+impl Report {
+    /// The names of the columns, without the key of a report by unit,
+    /// which would repeat the unit.
+    pub fn columns(&self) -> Vec<&'static str> {
+        let mut columns = vec![self.grouping.name(), "unit", "sales", "quantity"];
+        if self.grouping == Grouping::Unit {
+            columns.remove(0);
+        }
+        columns
+    }
+
+    fn cells(&self, row: &ReportRow, quantity: String) -> Vec<String> {
+        let mut cells = vec![
+            row.key.clone(),
+            row.unit.clone(),
+            row.sales.to_string(),
+            quantity,
+        ];
+        if self.grouping == Grouping::Unit {
+            cells.remove(0);
+        }
+        cells
+    }
+
+    pub fn write(&self, writer: impl Write, format: ReportFormat) -> Result<()> {
+        match format {
+            ReportFormat::Table => self.write_table(writer),
+            ReportFormat::Csv => self.write_csv(writer),
+            ReportFormat::Json => self.write_json(writer),
+        }
+    }
+
+    /// Writes the texts aligned to the left and the numbers to the right,
+    /// with the quantities rounded to thousandths.
+    fn write_table(&self, mut writer: impl Write) -> Result<()> {
+        let columns = self.columns();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| self.cells(row, format!("{:.3}", row.quantity)))
+            .collect::<Vec<_>>();
+        let widths = (0..columns.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|cells| cells[i].chars().count())
+                    .chain(std::iter::once(columns[i].len()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        // The last two columns are the numbers.
+        let is_number = |i: usize| i + 2 >= columns.len();
+        let write_line = |writer: &mut dyn Write, cells: &[&str]| -> std::io::Result<()> {
+            let line = cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    if is_number(i) {
+                        format!("{:>width$}", cell, width = widths[i])
+                    } else {
+                        format!("{:<width$}", cell, width = widths[i])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(writer, "{}", line.trim_end())
+        };
+        write_line(&mut writer, &columns)?;
+        let rule = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>();
+        write_line(
+            &mut writer,
+            &rule.iter().map(String::as_str).collect::<Vec<_>>(),
+        )?;
+        for cells in &rows {
+            write_line(
+                &mut writer,
+                &cells.iter().map(String::as_str).collect::<Vec<_>>(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_csv(&self, writer: impl Write) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(self.columns())?;
+        for row in &self.rows {
+            writer.write_record(self.cells(row, row.quantity.to_string()))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes an array of objects, having the columns as keys.
+    fn write_json(&self, mut writer: impl Write) -> Result<()> {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let mut object = Map::new();
+                if self.grouping != Grouping::Unit {
+                    object.insert(self.grouping.name().to_string(), row.key.clone().into());
+                }
+                object.insert("unit".to_string(), row.unit.clone().into());
+                object.insert("sales".to_string(), row.sales.into());
+                object.insert("quantity".to_string(), row.quantity.into());
+                Value::Object(object)
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_writer_pretty(&mut writer, &rows)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
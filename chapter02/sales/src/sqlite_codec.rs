@@ -0,0 +1,150 @@
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::Path;
+
+use crate::error::Result;
+use crate::format::Format;
+use crate::location::{Location, Locations};
+use crate::model::{Product, Sale, SalesAndProducts};
+
+/// Replaces the tables of a database with empty ones.
+pub fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS Sales;
+        DROP TABLE IF EXISTS Products;
+        CREATE TABLE Products (
+            id INTEGER PRIMARY KEY,
+            category TEXT NOT NULL,
+            name TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE Sales (
+            id TEXT PRIMARY KEY,
+            product_id INTEGER NOT NULL REFERENCES Products,
+            sale_date BIGINT NOT NULL,
+            quantity DOUBLE PRECISION NOT NULL,
+            unit TEXT NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+/// Inserts all the products and the sales, or none of them.
+pub fn insert(conn: &mut Connection, data: &SalesAndProducts) -> Result<()> {
+    let transaction = conn.transaction()?;
+    insert_rows(&transaction, data)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+fn insert_rows(conn: &Connection, data: &SalesAndProducts) -> Result<()> {
+    let mut command = conn.prepare(
+        "INSERT INTO Products (
+            id, category, name
+            ) VALUES ($1, $2, $3)",
+    )?;
+    for product in &data.products {
+        command.execute(params![product.id, product.category, product.name])?;
+    }
+    let mut command = conn.prepare(
+        "INSERT INTO Sales (
+            id, product_id, sale_date, quantity, unit
+            ) VALUES ($1, $2, $3, $4, $5)",
+    )?;
+    for sale in &data.sales {
+        command.execute(params![
+            sale.id,
+            sale.product_id,
+            sale.date,
+            sale.quantity,
+            sale.unit
+        ])?;
+    }
+    Ok(())
+}
+
+/// Reads the products by id, and the sales in their insertion order.
+/// The id of a product is its rowid, so SQLite cannot keep the products
+/// in their insertion order: a dataset read back from a database has them
+/// sorted by id, whatever their order when written.
+pub fn read(conn: &Connection) -> Result<SalesAndProducts> {
+    read_located(conn).map(|(data, _)| data)
+}
+
+/// Reads a dataset, with the rowid of each record.
+pub fn read_located(conn: &Connection) -> Result<(SalesAndProducts, Locations)> {
+    let mut locations = Locations::new(Format::Sqlite);
+    let mut command = conn.prepare("SELECT id, category, name, rowid FROM Products ORDER BY id")?;
+    let products = command
+        .query_map(params![], |row| {
+            Ok((
+                Product {
+                    id: row.get(0)?,
+                    category: row.get(1)?,
+                    name: row.get(2)?,
+                },
+                row.get::<_, i64>(3)?,
+            ))
+        })?
+        .map(|row| {
+            let (product, rowid) = row?;
+            locations
+                .products
+                .push(Location::new(format!("Products rowid {}", rowid)));
+            Ok(product)
+        })
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut command = conn.prepare(
+        "SELECT id, product_id, sale_date, quantity, unit, rowid
+        FROM Sales
+        ORDER BY rowid",
+    )?;
+    let sales = command
+        .query_map(params![], |row| {
+            Ok((
+                Sale {
+                    id: row.get(0)?,
+                    product_id: row.get(1)?,
+                    date: row.get(2)?,
+                    quantity: row.get(3)?,
+                    unit: row.get(4)?,
+                },
+                row.get::<_, i64>(5)?,
+            ))
+        })?
+        .map(|row| {
+            let (sale, rowid) = row?;
+            locations
+                .sales
+                .push(Location::new(format!("Sales rowid {}", rowid)));
+            Ok(sale)
+        })
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok((SalesAndProducts { products, sales }, locations))
+}
+
+/// Reads a database file, which is not created if missing.
+pub fn read_file(path: impl AsRef<Path>) -> Result<SalesAndProducts> {
+    read(&open_read_only(path)?)
+}
+
+/// Reads a database file with the rowid of each record.
+pub fn read_file_located(path: impl AsRef<Path>) -> Result<(SalesAndProducts, Locations)> {
+    read_located(&open_read_only(path)?)
+}
+
+fn open_read_only(path: impl AsRef<Path>) -> Result<Connection> {
+    Ok(Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )?)
+}
+
+/// Writes the data to a database file, replacing its tables if it exists.
+/// If any record cannot be written, the previous tables are kept.
+pub fn write_file(path: impl AsRef<Path>, data: &SalesAndProducts) -> Result<()> {
+    let mut conn = Connection::open(path)?;
+    let transaction = conn.transaction()?;
+    create_tables(&transaction)?;
+    insert_rows(&transaction, data)?;
+    transaction.commit()?;
+    Ok(())
+}
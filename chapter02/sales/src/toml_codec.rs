@@ -0,0 +1,24 @@
+use std::io::{Read, Write};
+
+use crate::error::Result;
+use crate::model::SalesAndProducts;
+
+/// Reads the products and the sales as arrays of tables,
+/// `[[products]]` and `[[sales]]`.
+pub fn read(mut reader: impl Read) -> Result<SalesAndProducts> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    Ok(toml::from_str(&text)?)
+}
+
+/// Writes the arrays and the strings of several lines over several lines
+/// if `pretty`.
+pub fn write(mut writer: impl Write, data: &SalesAndProducts, pretty: bool) -> Result<()> {
+    let text = if pretty {
+        toml::to_string_pretty(data)?
+    } else {
+        toml::to_string(data)?
+    };
+    writer.write_all(text.as_bytes())?;
+    Ok(())
+}
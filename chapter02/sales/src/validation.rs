@@ -0,0 +1,109 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::hash::Hash;
+
+use crate::location::{Location, Locations};
+use crate::model::SalesAndProducts;
+
+/// The units of measure accepted by default.
+pub const KNOWN_UNITS: [&str; 6] = ["u.", "Kg", "g", "l", "ml", "m"];
+
+/// A rule broken by a record, with the location of the offending field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub location: String,
+    pub message: String,
+}
+
+/// The location of the first record having a key, if it is not `location`.
+fn first_location<'a, K: Eq + Hash>(
+    seen: &mut HashMap<K, &'a Location>,
+    key: K,
+    location: &'a Location,
+) -> Option<&'a Location> {
+    match seen.entry(key) {
+        Entry::Occupied(entry) => Some(entry.get()),
+        Entry::Vacant(entry) => {
+            entry.insert(location);
+            None
+        }
+    }
+}
+
+/// Checks that the product ids and names, and the sale ids, are unique,
+/// that each sale refers to an existing product, and that its quantity is
+/// a non-negative number of a known unit. Every violation is reported,
+/// in the order of the records.
+pub fn validate(
+    data: &SalesAndProducts,
+    locations: &Locations,
+    known_units: &[&str],
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut report =
+        |location: String, message: String| violations.push(Violation { location, message });
+
+    let mut product_ids = HashMap::new();
+    let mut product_names = HashMap::new();
+    for (product, location) in data.products.iter().zip(&locations.products) {
+        if let Some(first) = first_location(&mut product_ids, product.id, location) {
+            report(
+                locations.field(location, "id"),
+                format!(
+                    "duplicate product id {}, also at {}",
+                    product.id,
+                    locations.field(first, "id")
+                ),
+            );
+        }
+        if let Some(first) = first_location(&mut product_names, &product.name, location) {
+            report(
+                locations.field(location, "name"),
+                format!(
+                    "duplicate product name \"{}\", also at {}",
+                    product.name,
+                    locations.field(first, "name")
+                ),
+            );
+        }
+    }
+
+    let mut sale_ids = HashMap::new();
+    for (sale, location) in data.sales.iter().zip(&locations.sales) {
+        if let Some(first) = first_location(&mut sale_ids, &sale.id, location) {
+            report(
+                locations.field(location, "id"),
+                format!(
+                    "duplicate sale id \"{}\", also at {}",
+                    sale.id,
+                    locations.field(first, "id")
+                ),
+            );
+        }
+        if !product_ids.contains_key(&sale.product_id) {
+            report(
+                locations.field(location, "product_id"),
+                format!("unknown product id {}", sale.product_id),
+            );
+        }
+        if !(sale.quantity >= 0. && sale.quantity.is_finite()) {
+            report(
+                locations.field(location, "quantity"),
+                format!(
+                    "the quantity must be a non-negative number, not {}",
+                    sale.quantity
+                ),
+            );
+        }
+        if !known_units.contains(&sale.unit.as_str()) {
+            report(
+                locations.field(location, "unit"),
+                format!(
+                    "unknown unit \"{}\", expected one of: {}",
+                    sale.unit,
+                    known_units.join(", ")
+                ),
+            );
+        }
+    }
+    violations
+}
@@ -0,0 +1,348 @@
+use std::io::{Read, Write};
+use xml::attribute::OwnedAttribute;
+use xml::common::{Position, TextPosition};
+use xml::reader::{EventReader, XmlEvent};
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent as WriterEvent};
+
+use crate::error::{Error, Result};
+use crate::format::Format;
+use crate::location::{Location, Locations};
+use crate::model::{Product, Sale, SalesAndProducts};
+
+/// How a field is stored in an XML element, or in an attribute of
+/// the element of its item.
+struct FieldMapping<T> {
+    name: &'static str,
+    get: fn(&T) -> String,
+    /// Fails with the reason why the text is not a valid value.
+    set: fn(&mut T, String) -> std::result::Result<(), String>,
+}
+
+/// How a product or a sale is stored in an XML element.
+struct ItemMapping<T: 'static> {
+    name: &'static str,
+    fields: &'static [FieldMapping<T>],
+}
+
+fn parse<T: std::str::FromStr>(text: &str) -> std::result::Result<T, String> {
+    text.trim()
+        .parse::<T>()
+        .map_err(|_| format!("invalid value \"{}\"", text))
+}
+
+const PRODUCT: ItemMapping<Product> = ItemMapping {
+    name: "product",
+    fields: &[
+        FieldMapping {
+            name: "id",
+            get: |p| p.id.to_string(),
+            set: |p, text| {
+                p.id = parse(&text)?;
+                Ok(())
+            },
+        },
+        FieldMapping {
+            name: "category",
+            get: |p| p.category.clone(),
+            set: |p, text| {
+                p.category = text;
+                Ok(())
+            },
+        },
+        FieldMapping {
+            name: "name",
+            get: |p| p.name.clone(),
+            set: |p, text| {
+                p.name = text;
+                Ok(())
+            },
+        },
+    ],
+};
+
+const SALE: ItemMapping<Sale> = ItemMapping {
+    name: "sale",
+    fields: &[
+        FieldMapping {
+            name: "id",
+            get: |s| s.id.clone(),
+            set: |s, text| {
+                s.id = text;
+                Ok(())
+            },
+        },
+        FieldMapping {
+            name: "product-id",
+            get: |s| s.product_id.to_string(),
+            set: |s, text| {
+                s.product_id = parse(&text)?;
+                Ok(())
+            },
+        },
+        FieldMapping {
+            name: "date",
+            get: |s| s.date.to_string(),
+            set: |s, text| {
+                s.date = parse(&text)?;
+                Ok(())
+            },
+        },
+        FieldMapping {
+            name: "quantity",
+            get: |s| s.quantity.to_string(),
+            set: |s, text| {
+                s.quantity = parse(&text)?;
+                Ok(())
+            },
+        },
+        FieldMapping {
+            name: "unit",
+            get: |s| s.unit.clone(),
+            set: |s, text| {
+                s.unit = text;
+                Ok(())
+            },
+        },
+    ],
+};
+
+fn error_at(position: TextPosition, message: String) -> Error {
+    Error::Xml {
+        line: position.row + 1,
+        column: position.column + 1,
+        message,
+    }
+}
+
+impl From<xml::reader::Error> for Error {
+    fn from(e: xml::reader::Error) -> Self {
+        error_at(e.position(), e.msg().to_string())
+    }
+}
+
+impl From<xml::writer::Error> for Error {
+    fn from(e: xml::writer::Error) -> Self {
+        match e {
+            xml::writer::Error::Io(e) => Error::Io(e),
+            e => Error::Unsupported(format!("Cannot write XML: {}", e)),
+        }
+    }
+}
+
+/// Reads the events of the document, remembering the position of each one.
+struct Events<R: Read> {
+    reader: EventReader<R>,
+    position: TextPosition,
+}
+
+impl<R: Read> Events<R> {
+    fn next(&mut self) -> Result<XmlEvent> {
+        let event = self.reader.next()?;
+        self.position = self.reader.position();
+        Ok(event)
+    }
+
+    /// Skips the contents of the element just started, and its end.
+    fn skip_element(&mut self) -> Result<()> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The text of the element just started, till its end.
+    fn read_text(&mut self, name: &str) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            match self.next()? {
+                XmlEvent::Characters(s) | XmlEvent::Whitespace(s) | XmlEvent::CData(s) => {
+                    text.push_str(&s)
+                }
+                XmlEvent::StartElement { name: inner, .. } => {
+                    let message =
+                        format!("unexpected element <{}> in <{}>", inner.local_name, name);
+                    return Err(error_at(self.position, message));
+                }
+                XmlEvent::EndElement { .. } => return Ok(text),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads an item from the element just started, with its attributes.
+    /// The unknown attributes and elements are skipped, while every field
+    /// must be given, maybe empty.
+    fn read_item<T: Default>(
+        &mut self,
+        mapping: &ItemMapping<T>,
+        attributes: Vec<OwnedAttribute>,
+    ) -> Result<T> {
+        let mut item = T::default();
+        let mut seen = vec![false; mapping.fields.len()];
+        let start = self.position;
+        for attribute in attributes {
+            if let Some(index) = find_field(mapping, &attribute.name.local_name) {
+                let field = &mapping.fields[index];
+                seen[index] = true;
+                (field.set)(&mut item, attribute.value).map_err(|message| {
+                    error_at(
+                        start,
+                        format!("{}@{}: {}", mapping.name, field.name, message),
+                    )
+                })?;
+            }
+        }
+        loop {
+            match self.next()? {
+                XmlEvent::StartElement { name, .. } => {
+                    match find_field(mapping, &name.local_name) {
+                        Some(index) => {
+                            let field = &mapping.fields[index];
+                            seen[index] = true;
+                            let position = self.position;
+                            let text = self.read_text(&name.local_name)?;
+                            (field.set)(&mut item, text).map_err(|message| {
+                                error_at(
+                                    position,
+                                    format!("{}/{}: {}", mapping.name, field.name, message),
+                                )
+                            })?;
+                        }
+                        None => self.skip_element()?,
+                    }
+                }
+                XmlEvent::EndElement { .. } => break,
+                _ => {}
+            }
+        }
+        let missing: Vec<&str> = mapping
+            .fields
+            .iter()
+            .zip(seen)
+            .filter(|(_, seen)| !seen)
+            .map(|(field, _)| field.name)
+            .collect();
+        if missing.is_empty() {
+            Ok(item)
+        } else {
+            let message = format!("{}: missing {}", mapping.name, missing.join(", "));
+            Err(error_at(start, message))
+        }
+    }
+}
+
+/// The index of the field named `name`, if any.
+fn find_field<T>(mapping: &ItemMapping<T>, name: &str) -> Option<usize> {
+    mapping.fields.iter().position(|field| field.name == name)
+}
+
+/// Reads the `<product>` and `<sale>` elements, wherever they are,
+/// each having an element per field, as `<product-id>`, or an attribute,
+/// as `<sale id="2020-3987">`. The elements are matched by their local
+/// names, whatever their namespaces, and the unknown ones are skipped;
+/// a missing field is an error, at the element of its item.
+pub fn read(reader: impl Read) -> Result<SalesAndProducts> {
+    read_located(reader).map(|(data, _)| data)
+}
+
+/// An element containing products or sales, counting them to tell their paths.
+struct Parent {
+    path: String,
+    products: usize,
+    sales: usize,
+}
+
+/// Reads a dataset, with the path and the line of each record,
+/// as "/sales-and-products/sale[2]".
+pub fn read_located(reader: impl Read) -> Result<(SalesAndProducts, Locations)> {
+    let mut data = SalesAndProducts::default();
+    let mut locations = Locations::new(Format::Xml);
+    let mut events = Events {
+        reader: EventReader::new(reader),
+        position: TextPosition::new(),
+    };
+    let mut parents = vec![Parent {
+        path: String::new(),
+        products: 0,
+        sales: 0,
+    }];
+    loop {
+        let event = events.next()?;
+        let parent = parents.last_mut().unwrap();
+        match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let line = Some(events.position.row + 1);
+                // With its prefix, but without its namespace.
+                let name_repr = name.borrow().to_repr();
+                if name.local_name == PRODUCT.name {
+                    parent.products += 1;
+                    let path = format!("{}/{}[{}]", parent.path, name_repr, parent.products);
+                    data.products.push(events.read_item(&PRODUCT, attributes)?);
+                    locations.products.push(Location { path, line });
+                } else if name.local_name == SALE.name {
+                    parent.sales += 1;
+                    let path = format!("{}/{}[{}]", parent.path, name_repr, parent.sales);
+                    data.sales.push(events.read_item(&SALE, attributes)?);
+                    locations.sales.push(Location { path, line });
+                } else {
+                    let path = format!("{}/{}", parent.path, name_repr);
+                    parents.push(Parent {
+                        path,
+                        products: 0,
+                        sales: 0,
+                    });
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                parents.pop();
+            }
+            XmlEvent::EndDocument => return Ok((data, locations)),
+            _ => {}
+        }
+    }
+}
+
+fn write_item<T>(
+    writer: &mut EventWriter<impl Write>,
+    mapping: &ItemMapping<T>,
+    item: &T,
+) -> Result<()> {
+    writer.write(WriterEvent::start_element(mapping.name))?;
+    for field in mapping.fields {
+        writer.write(WriterEvent::start_element(field.name))?;
+        writer.write(WriterEvent::characters(&(field.get)(item)))?;
+        writer.write(WriterEvent::end_element())?;
+    }
+    writer.write(WriterEvent::end_element())?;
+    Ok(())
+}
+
+/// Writes a `<sales-and-products>` document, with the schema read by `read`,
+/// indented if `pretty`.
+pub fn write(writer: impl Write, data: &SalesAndProducts, pretty: bool) -> Result<()> {
+    let mut writer = EmitterConfig::new()
+        .perform_indent(pretty)
+        .create_writer(writer);
+    writer.write(WriterEvent::StartDocument {
+        version: xml::common::XmlVersion::Version10,
+        encoding: Some("utf-8"),
+        standalone: None,
+    })?;
+    writer.write(WriterEvent::start_element("sales-and-products"))?;
+    for product in &data.products {
+        write_item(&mut writer, &PRODUCT, product)?;
+    }
+    for sale in &data.sales {
+        write_item(&mut writer, &SALE, sale)?;
+    }
+    writer.write(WriterEvent::end_element())?;
+    writeln!(writer.inner_mut())?;
+    Ok(())
+}
@@ -0,0 +1,28 @@
+-- A small database of sales, with the schema of `sqlite_codec`,
+-- spanning two months and three units.
+CREATE TABLE Products (
+    id INTEGER PRIMARY KEY,
+    category TEXT NOT NULL,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE Sales (
+    id TEXT PRIMARY KEY,
+    product_id INTEGER NOT NULL REFERENCES Products,
+    sale_date BIGINT NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    unit TEXT NOT NULL
+);
+INSERT INTO Products (id, category, name) VALUES
+    (1, 'fruit', 'pears'),
+    (2, 'fruit', 'apples'),
+    (3, 'drinks', 'milk'),
+    (4, 'tools', 'hammers');
+-- 1234567890 is 2009-02-13 23:31:30 UTC.
+INSERT INTO Sales (id, product_id, sale_date, quantity, unit) VALUES
+    ('2020-103', 1, 1234567890, 7.5, 'Kg'),
+    ('2020-104', 2, 1234567900, 2.25, 'Kg'),
+    ('2020-105', 1, 1234600000, 500, 'g'),
+    ('2020-106', 3, 1234600000, 1.5, 'l'),
+    ('2020-107', 3, 1236000000, 2, 'l'),
+    ('2020-108', 4, 1236000000, 3, 'u.'),
+    ('2020-109', 1, 1236100000, 1.25, 'Kg');
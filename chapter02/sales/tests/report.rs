@@ -0,0 +1,234 @@
+use rusqlite::{params, Connection};
+use sales::report::{self, Grouping, Report, ReportFormat};
+use sales::{Product, Sale, SalesAndProducts};
+
+fn fixture() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(include_str!("fixtures/sales.sql"))
+        .unwrap();
+    conn
+}
+
+/// The rows of a report, as (key, unit, sales, quantity).
+fn rows(report: &Report) -> Vec<(&str, &str, u64, f64)> {
+    report
+        .rows
+        .iter()
+        .map(|row| (row.key.as_str(), row.unit.as_str(), row.sales, row.quantity))
+        .collect()
+}
+
+fn written(report: &Report, format: ReportFormat) -> String {
+    let mut output = Vec::new();
+    report.write(&mut output, format).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn sales_with_products_maps_every_column() {
+    let sales = report::sales_with_products(&fixture()).unwrap();
+    assert_eq!(sales.len(), 7);
+    let first = &sales[0];
+    assert_eq!(first.category, "fruit");
+    assert_eq!(first.name, "pears");
+    assert_eq!(first.quantity, 7.5);
+    assert_eq!(first.unit, "Kg");
+    assert_eq!(first.date, 1_234_567_890);
+    let dates = sales.iter().map(|sale| sale.date).collect::<Vec<_>>();
+    let mut sorted = dates.clone();
+    sorted.sort_unstable();
+    assert_eq!(dates, sorted);
+}
+
+#[test]
+fn totals_per_product() {
+    let report = report::totals(&fixture(), Grouping::Product).unwrap();
+    assert_eq!(
+        rows(&report),
+        vec![
+            ("apples", "Kg", 1, 2.25),
+            ("hammers", "u.", 1, 3.),
+            ("milk", "l", 2, 3.5),
+            ("pears", "Kg", 2, 8.75),
+            ("pears", "g", 1, 500.),
+        ]
+    );
+}
+
+#[test]
+fn totals_per_category() {
+    let report = report::totals(&fixture(), Grouping::Category).unwrap();
+    assert_eq!(
+        rows(&report),
+        vec![
+            ("drinks", "l", 2, 3.5),
+            ("fruit", "Kg", 3, 11.),
+            ("fruit", "g", 1, 500.),
+            ("tools", "u.", 1, 3.),
+        ]
+    );
+}
+
+#[test]
+fn totals_per_unit() {
+    let report = report::totals(&fixture(), Grouping::Unit).unwrap();
+    assert_eq!(
+        rows(&report),
+        vec![
+            ("Kg", "Kg", 3, 11.),
+            ("g", "g", 1, 500.),
+            ("l", "l", 2, 3.5),
+            ("u.", "u.", 1, 3.),
+        ]
+    );
+}
+
+#[test]
+fn totals_per_day_and_month_in_utc() {
+    let conn = fixture();
+    let report = report::totals(&conn, Grouping::Day).unwrap();
+    assert_eq!(
+        rows(&report),
+        vec![
+            ("2009-02-13", "Kg", 2, 9.75),
+            ("2009-02-14", "g", 1, 500.),
+            ("2009-02-14", "l", 1, 1.5),
+            ("2009-03-02", "l", 1, 2.),
+            ("2009-03-02", "u.", 1, 3.),
+            ("2009-03-03", "Kg", 1, 1.25),
+        ]
+    );
+    let report = report::totals(&conn, Grouping::Month).unwrap();
+    assert_eq!(
+        rows(&report),
+        vec![
+            ("2009-02", "Kg", 2, 9.75),
+            ("2009-02", "g", 1, 500.),
+            ("2009-02", "l", 1, 1.5),
+            ("2009-03", "Kg", 1, 1.25),
+            ("2009-03", "l", 1, 2.),
+            ("2009-03", "u.", 1, 3.),
+        ]
+    );
+}
+
+#[test]
+fn sales_of_unknown_products_are_kept_apart() {
+    let conn = fixture();
+    conn.execute(
+        "INSERT INTO Sales (id, product_id, sale_date, quantity, unit)
+        VALUES ('2020-110', 9, 1236100000, 4, 'Kg')",
+        params![],
+    )
+    .unwrap();
+    let report = report::totals(&conn, Grouping::Product).unwrap();
+    assert!(rows(&report).contains(&("unknown product 9", "Kg", 1, 4.)));
+    let report = report::totals(&conn, Grouping::Category).unwrap();
+    assert!(rows(&report).contains(&("unknown category", "Kg", 1, 4.)));
+    let sales = report::sales_with_products(&conn).unwrap();
+    assert_eq!(sales.len(), 8);
+    assert!(sales.iter().any(|sale| {
+        (
+            sale.name.as_str(),
+            sale.category.as_str(),
+            sale.quantity,
+            sale.date,
+        ) == ("unknown product 9", "unknown category", 4., 1_236_100_000)
+    }));
+}
+
+#[test]
+fn sales_of_unknown_products_are_kept_in_memory_too() {
+    let data = SalesAndProducts {
+        products: vec![Product {
+            id: 1,
+            category: "fruit".to_string(),
+            name: "pears".to_string(),
+        }],
+        sales: vec![
+            Sale {
+                id: "2020-101".to_string(),
+                product_id: 1,
+                date: 1_234_567_890,
+                quantity: 7.5,
+                unit: "Kg".to_string(),
+            },
+            Sale {
+                id: "2020-110".to_string(),
+                product_id: 9,
+                date: 1_236_100_000,
+                quantity: 4.,
+                unit: "Kg".to_string(),
+            },
+        ],
+    };
+    let sales = data.sales_with_products();
+    let names = sales
+        .iter()
+        .map(|sale| (sale.name.as_str(), sale.category.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            ("pears", "fruit"),
+            ("unknown product 9", "unknown category")
+        ]
+    );
+}
+
+#[test]
+fn table_is_aligned() {
+    let report = report::totals(&fixture(), Grouping::Category).unwrap();
+    assert_eq!(
+        written(&report, ReportFormat::Table),
+        "\
+category  unit  sales  quantity
+--------  ----  -----  --------
+drinks    l         2     3.500
+fruit     Kg        3    11.000
+fruit     g         1   500.000
+tools     u.        1     3.000
+"
+    );
+}
+
+#[test]
+fn csv_has_a_header() {
+    let report = report::totals(&fixture(), Grouping::Month).unwrap();
+    let csv = written(&report, ReportFormat::Csv);
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("month,unit,sales,quantity"));
+    assert_eq!(lines.next(), Some("2009-02,Kg,2,9.75"));
+    assert_eq!(lines.count(), 5);
+
+    let report = report::totals(&fixture(), Grouping::Unit).unwrap();
+    let csv = written(&report, ReportFormat::Csv);
+    assert_eq!(csv.lines().next(), Some("unit,sales,quantity"));
+    assert_eq!(csv.lines().nth(1), Some("Kg,3,11"));
+}
+
+#[test]
+fn json_is_an_array_of_objects() {
+    let report = report::totals(&fixture(), Grouping::Product).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&written(&report, ReportFormat::Json)).unwrap();
+    let rows = json.as_array().unwrap();
+    assert_eq!(rows.len(), 5);
+    assert_eq!(
+        rows[3],
+        serde_json::json!({"product": "pears", "unit": "Kg", "sales": 2, "quantity": 8.75})
+    );
+}
+
+#[test]
+fn groupings_and_formats_are_parsed_by_name() {
+    for grouping in &Grouping::ALL {
+        assert_eq!(grouping.name().parse::<Grouping>(), Ok(*grouping));
+    }
+    assert_eq!(
+        "week".parse::<Grouping>(),
+        Err("unknown grouping \"week\", expected product, category, unit, day, month".to_string())
+    );
+    assert_eq!("csv".parse::<ReportFormat>(), Ok(ReportFormat::Csv));
+    assert!("html".parse::<ReportFormat>().is_err());
+}
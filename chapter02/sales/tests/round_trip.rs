@@ -0,0 +1,168 @@
+use sales::{Format, Product, Sale, SalesAndProducts};
+use std::path::PathBuf;
+
+fn sample() -> SalesAndProducts {
+    SalesAndProducts {
+        products: vec![
+            Product {
+                id: 591,
+                category: "fruit".to_string(),
+                name: "orange".to_string(),
+            },
+            Product {
+                id: 190,
+                category: "furniture & <fittings>".to_string(),
+                name: " chair \"deluxe\" ".to_string(),
+            },
+            Product {
+                id: 7,
+                category: "".to_string(),
+                name: "crème brûlée".to_string(),
+            },
+        ],
+        sales: vec![
+            Sale {
+                id: "2020-7110".to_string(),
+                product_id: 190,
+                date: 1_234_527_890,
+                quantity: 2.0,
+                unit: "u.".to_string(),
+            },
+            Sale {
+                id: "2020-2871".to_string(),
+                product_id: 591,
+                date: -86_400,
+                quantity: 0.1 + 0.2,
+                unit: "Kg".to_string(),
+            },
+            Sale {
+                id: "2020-0001".to_string(),
+                product_id: 7,
+                date: 0,
+                quantity: 1e-7,
+                unit: "g".to_string(),
+            },
+        ],
+    }
+}
+
+/// SQLite returns the products by id.
+fn sorted(mut data: SalesAndProducts) -> SalesAndProducts {
+    data.products.sort_by_key(|p| p.id);
+    data
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sales-{}-{}", std::process::id(), name))
+}
+
+fn through_stream(format: Format, data: &SalesAndProducts) -> SalesAndProducts {
+    let mut bytes = Vec::new();
+    format.write(&mut bytes, data, true).unwrap();
+    format.read(bytes.as_slice()).unwrap()
+}
+
+#[test]
+fn json_xml_sqlite_json_preserves_every_record() {
+    let original = sample();
+    let from_xml = through_stream(Format::Xml, &through_stream(Format::Json, &original));
+    assert_eq!(from_xml, original);
+
+    let db_path = temp_path("round_trip.db");
+    Format::Sqlite
+        .write_file(&db_path, &from_xml, true)
+        .unwrap();
+    let from_sqlite = Format::Sqlite.read_file(&db_path).unwrap();
+    std::fs::remove_file(&db_path).unwrap();
+
+    assert_eq!(through_stream(Format::Json, &from_sqlite), sorted(original));
+}
+
+#[test]
+fn every_format_preserves_every_record() {
+    let original = sample();
+    for &format in &Format::ALL {
+        let path = temp_path(&format!("every.{}", format.name()));
+        for &pretty in &[true, false] {
+            format.write_file(&path, &original, pretty).unwrap();
+            let read = format.read_file(&path).unwrap();
+            assert_eq!(sorted(read), sorted(original.clone()), "{}", format.name());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn keeps_the_sqlite_tables_when_a_record_cannot_be_written() {
+    let path = temp_path("failed_write.db");
+    Format::Sqlite.write_file(&path, &sample(), true).unwrap();
+    let mut duplicated = sample();
+    duplicated.products[1].name = duplicated.products[0].name.clone();
+    assert!(Format::Sqlite.write_file(&path, &duplicated, true).is_err());
+    let read = Format::Sqlite.read_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read, sorted(sample()));
+}
+
+/// A pipe whose reader has quit.
+struct ClosedPipe;
+
+impl std::io::Write for ClosedPipe {
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn reports_the_failures_of_the_stream_as_io_errors() {
+    for &format in &[Format::Json, Format::Xml, Format::Toml, Format::Csv] {
+        match format.write(ClosedPipe, &sample(), true) {
+            Err(sales::Error::Io(e)) => {
+                assert_eq!(
+                    e.kind(),
+                    std::io::ErrorKind::BrokenPipe,
+                    "{}",
+                    format.name()
+                )
+            }
+            other => panic!("{}: unexpected result: {:?}", format.name(), other),
+        }
+    }
+}
+
+#[test]
+fn reads_the_chapter_data_files() {
+    let data_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../data");
+    let from_json = Format::Json.read_file(data_dir.join("sales.json")).unwrap();
+    assert_eq!(from_json.products.len(), 2);
+    assert_eq!(from_json.sales[1].quantity, 2.14);
+
+    let from_xml = Format::Xml.read_file(data_dir.join("sales.xml")).unwrap();
+    assert_eq!(from_xml.products[0].name, "cherry");
+    assert_eq!(from_xml.sales[1].product_id, 236);
+    assert_eq!(from_xml.sales[1].quantity, 1.0);
+}
+
+#[test]
+fn reports_the_position_of_invalid_xml_values() {
+    let text = "<sales-and-products>\n  <sale>\n    <quantity>many</quantity>\n  </sale>\n</sales-and-products>";
+    match Format::Xml.read(text.as_bytes()) {
+        Err(sales::Error::Xml { line, .. }) => assert_eq!(line, 3),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn reports_the_line_of_invalid_csv_values() {
+    let text = "record,id,category,name,product_id,date,quantity,unit\n\
+                product,1,fruit,pears,,,,\n\
+                sale,2020-1,,,1,0,some,Kg\n";
+    match Format::Csv.read(text.as_bytes()) {
+        Err(sales::Error::Csv { line, .. }) => assert_eq!(line, 3),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
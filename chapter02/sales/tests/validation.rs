@@ -0,0 +1,106 @@
+use sales::{validate, Format, Violation, KNOWN_UNITS};
+
+fn violations(format: Format, text: &str) -> Vec<Violation> {
+    let (data, locations) = format.read_located(text.as_bytes()).unwrap();
+    validate(&data, &locations, &KNOWN_UNITS)
+}
+
+fn located_at(violations: &[Violation]) -> Vec<&str> {
+    violations.iter().map(|v| v.location.as_str()).collect()
+}
+
+const INVALID_JSON: &str = r#"{
+    "products": [
+        { "id": 1, "category": "fruit", "name": "pears" },
+        { "id": 1, "category": "fruit", "name": "apples" },
+        { "id": 2, "category": "fruit", "name": "pears" }
+    ],
+    "sales": [
+        { "id": "s1", "product_id": 1, "date": 0, "quantity": 1.5, "unit": "Kg" },
+        { "id": "s1", "product_id": 3, "date": 0, "quantity": -2, "unit": "lb" }
+    ]
+}"#;
+
+#[test]
+fn reports_every_violation_with_a_json_pointer() {
+    let violations = violations(Format::Json, INVALID_JSON);
+    assert_eq!(
+        located_at(&violations),
+        vec![
+            "/products/1/id",
+            "/products/2/name",
+            "/sales/1/id",
+            "/sales/1/product_id",
+            "/sales/1/quantity",
+            "/sales/1/unit",
+        ]
+    );
+    assert_eq!(
+        violations[0].message,
+        "duplicate product id 1, also at /products/0/id"
+    );
+    assert_eq!(violations[3].message, "unknown product id 3");
+}
+
+#[test]
+fn accepts_the_chapter_data_files() {
+    let data_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../data/");
+    for file in &["sales.json", "sales.xml"] {
+        let path = format!("{}{}", data_dir, file);
+        let format = Format::from_path(&path).unwrap();
+        let (data, locations) = format.read_file_located(&path).unwrap();
+        assert_eq!(
+            validate(&data, &locations, &KNOWN_UNITS),
+            vec![],
+            "{}",
+            file
+        );
+    }
+}
+
+#[test]
+fn locates_xml_records_by_path_and_line() {
+    let text = r#"<sales-and-products>
+    <product><id>1</id><category>fruit</category><name>pears</name></product>
+    <batch>
+        <sale><id>s1</id><product-id>1</product-id><date>0</date><quantity>1</quantity><unit>Kg</unit></sale>
+        <sale><id>s2</id><product-id>9</product-id><date>0</date><quantity>1</quantity><unit>Kg</unit></sale>
+    </batch>
+</sales-and-products>"#;
+    assert_eq!(
+        located_at(&violations(Format::Xml, text)),
+        vec!["/sales-and-products/batch/sale[2]/product-id (line 5)"]
+    );
+}
+
+#[test]
+fn locates_csv_records_by_line() {
+    let text = "record,id,category,name,product_id,date,quantity,unit\n\
+                sale,s1,,,1,0,1,Kg\n\
+                product,1,fruit,pears,,,,\n\
+                sale,s2,,,1,0,1,pounds\n";
+    assert_eq!(
+        located_at(&violations(Format::Csv, text)),
+        vec!["line 4, column unit"]
+    );
+}
+
+#[test]
+fn locates_sqlite_records_by_rowid() {
+    let (mut data, _) = Format::Json.read_located(INVALID_JSON.as_bytes()).unwrap();
+    // The database enforces unique ids and product names.
+    data.products.truncate(1);
+    data.sales[1].id = "s2".to_string();
+    let path = std::env::temp_dir().join(format!("sales-{}-validation.db", std::process::id()));
+    Format::Sqlite.write_file(&path, &data, true).unwrap();
+    let (data, locations) = Format::Sqlite.read_file_located(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        located_at(&validate(&data, &locations, &KNOWN_UNITS)),
+        vec![
+            "Sales rowid 2, column product_id",
+            "Sales rowid 2, column quantity",
+            "Sales rowid 2, column unit",
+        ]
+    );
+}
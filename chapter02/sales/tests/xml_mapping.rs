@@ -0,0 +1,117 @@
+use sales::{xml_codec, Error, Product, Sale};
+
+#[test]
+fn reads_attributes_cdata_and_namespaces() {
+    let text = r#"<?xml version="1.0"?>
+<s:sales-and-products xmlns:s="urn:example:sales">
+    <s:product id="862">
+        <s:category><![CDATA[fruit & <berries>]]></s:category>
+        <s:name>cherry</s:name>
+    </s:product>
+    <sale id="2020-3987" unit="Kg">
+        <product-id>862</product-id>
+        <date>1238563890</date>
+        <quantity> 0.753 </quantity>
+    </sale>
+</s:sales-and-products>"#;
+    let data = xml_codec::read(text.as_bytes()).unwrap();
+    assert_eq!(
+        data.products,
+        vec![Product {
+            id: 862,
+            category: "fruit & <berries>".to_string(),
+            name: "cherry".to_string(),
+        }]
+    );
+    assert_eq!(
+        data.sales,
+        vec![Sale {
+            id: "2020-3987".to_string(),
+            product_id: 862,
+            date: 1_238_563_890,
+            quantity: 0.753,
+            unit: "Kg".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn skips_unknown_elements_and_attributes() {
+    let text = r#"<sales-and-products>
+    <product color="red">
+        <id>1</id>
+        <category/>
+        <notes><note>ripe</note><name>not the name</name></notes>
+        <name>pears</name>
+    </product>
+    <supplier><name>ACME</name></supplier>
+</sales-and-products>"#;
+    let data = xml_codec::read(text.as_bytes()).unwrap();
+    assert_eq!(data.products.len(), 1);
+    assert_eq!(data.products[0].name, "pears");
+    assert!(data.sales.is_empty());
+}
+
+#[test]
+fn reports_the_line_and_column_of_invalid_values() {
+    let text = "<sales-and-products>\n  <sale>\n    <quantity>many</quantity>\n  </sale>\n</sales-and-products>";
+    match xml_codec::read(text.as_bytes()) {
+        Err(Error::Xml {
+            line,
+            column,
+            message,
+        }) => {
+            assert_eq!((line, column), (3, 5));
+            assert_eq!(message, "sale/quantity: invalid value \"many\"");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let text = "<sales-and-products>\n<product id=\"x\"/>\n</sales-and-products>";
+    match xml_codec::read(text.as_bytes()) {
+        Err(Error::Xml { line, column, .. }) => assert_eq!((line, column), (2, 1)),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn reports_the_missing_fields_at_their_item() {
+    let text = "<sales-and-products>\n  <product><id>1</id><name>pears</name></product>\n  \
+                <sale unit=\"Kg\"><id>s1</id><quantity>1</quantity></sale>\n</sales-and-products>";
+    match xml_codec::read(text.as_bytes()) {
+        Err(Error::Xml {
+            line,
+            column,
+            message,
+        }) => {
+            assert_eq!((line, column), (2, 3));
+            assert_eq!(message, "product: missing category");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let text = text.replace("<id>1</id>", "<id>1</id><category/>");
+    match xml_codec::read(text.as_bytes()) {
+        Err(Error::Xml {
+            line,
+            column,
+            message,
+        }) => {
+            assert_eq!((line, column), (3, 3));
+            assert_eq!(message, "sale: missing product-id, date");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn writes_the_schema_it_reads() {
+    let text =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../data/sales.xml")).unwrap();
+    let data = xml_codec::read(text.as_bytes()).unwrap();
+    let mut written = Vec::new();
+    xml_codec::write(&mut written, &data, true).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    assert!(written.contains("<product-id>862</product-id>"));
+    assert_eq!(xml_codec::read(written.as_bytes()).unwrap(), data);
+}
@@ -0,0 +1,124 @@
+// Converts a dataset of products and sales between formats, as:
+// cargo run -- ../data/sales.json sales.xml
+// cargo run -- ../data/sales.xml sales.db
+// cargo run -- --from sqlite sales.db --to toml
+// cat ../data/sales.json | cargo run -- --from json --to csv --compact
+//
+// The formats are json, xml, toml, sqlite and csv. By default, they are told
+// by the extensions of the files. Without an input file, or with "-", the
+// dataset is read from the standard input; likewise, it is written to the
+// standard output, except a SQLite database, which needs a file.
+
+use sales::{Format, SalesAndProducts};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Converts the sales and the products between file formats")]
+struct Opt {
+    /// The format of the input: json, xml, toml, sqlite or csv.
+    #[structopt(short = "f", long = "from")]
+    from: Option<Format>,
+
+    /// The format of the output: json, xml, toml, sqlite or csv.
+    #[structopt(short = "t", long = "to")]
+    to: Option<Format>,
+
+    /// Writes JSON, XML and TOML without indentation.
+    #[structopt(short = "c", long = "compact")]
+    compact: bool,
+
+    /// By default, the standard input.
+    #[structopt(parse(from_os_str))]
+    input: Option<PathBuf>,
+
+    /// By default, the standard output.
+    #[structopt(parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+/// A file, or the standard stream if it is missing or "-".
+fn file_path(path: &Option<PathBuf>) -> Option<&PathBuf> {
+    path.as_ref().filter(|path| path.to_str() != Some("-"))
+}
+
+fn format_of(format: Option<Format>, path: Option<&PathBuf>, name: &str) -> Result<Format, String> {
+    match (format, path) {
+        (Some(format), _) => Ok(format),
+        (None, Some(path)) => Format::from_path(path).ok_or_else(|| {
+            format!(
+                "{}: unknown extension; choose the format with --{}",
+                path.display(),
+                name
+            )
+        }),
+        (None, None) => Err(format!(
+            "choose the format of the standard {} with --{}",
+            if name == "from" { "input" } else { "output" },
+            name
+        )),
+    }
+}
+
+fn read(format: Format, input: Option<&PathBuf>) -> Result<SalesAndProducts, String> {
+    match input {
+        Some(path) => format
+            .read_file(path)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        None => format
+            .read(std::io::stdin().lock())
+            .map_err(|e| format!("<stdin>: {}", e)),
+    }
+}
+
+fn write(
+    format: Format,
+    output: Option<&PathBuf>,
+    data: &SalesAndProducts,
+    pretty: bool,
+) -> Result<(), String> {
+    match output {
+        Some(path) => format
+            .write_file(path, data, pretty)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        None => {
+            let stdout = std::io::stdout();
+            let mut stdout = stdout.lock();
+            match format
+                .write(&mut stdout, data, pretty)
+                .and_then(|_| Ok(stdout.flush()?))
+            {
+                // The reader has quit, as `head`, so nothing more is wanted.
+                Err(sales::Error::Io(ref e)) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+                result => result.map_err(|e| format!("<stdout>: {}", e)),
+            }
+        }
+    }
+}
+
+fn run(opt: &Opt) -> Result<(), String> {
+    let input = file_path(&opt.input);
+    let output = file_path(&opt.output);
+    let from = format_of(opt.from, input, "from")?;
+    let to = format_of(opt.to, output, "to")?;
+    let data = read(from, input)?;
+    write(to, output, &data, !opt.compact)?;
+    if output.is_some() {
+        eprintln!(
+            "Converted {} products and {} sales from {} to {}",
+            data.products.len(),
+            data.sales.len(),
+            from.name(),
+            to.name()
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(message) = run(&Opt::from_args()) {
+        eprintln!("salesconv: {}", message);
+        std::process::exit(1);
+    }
+}
@@ -1,86 +1,51 @@
-use rusqlite::{params, Connection, Result};
+// Prints the sales of a database and their totals, as:
+// cargo run -- sales.db
+// or prints only the totals of a grouping, in a format, as:
+// cargo run -- sales.db month
+// cargo run -- sales.db category csv
+// cargo run -- sales.db day json
+//
+// The database is only read; create it with salesconv, as:
+// cargo run --manifest-path ../salesconv/Cargo.toml -- ../data/sales.json sales.db
 
-fn main() -> Result<()> {
-    let conn = create_db()?;
-    populate_db(&conn)?;
-    print_db(&conn)?;
-    Ok(())
-}
-
-struct SaleWithProduct {
-    category: String,
-    name: String,
-    quantity: f64,
-    unit: String,
-    date: i64,
-}
+use rusqlite::{Connection, OpenFlags};
+use sales::report::{self, Grouping, ReportFormat};
+use sales::Result;
 
-fn create_db() -> Result<Connection> {
-    let database_file = "sales.db";
-    let conn = Connection::open(database_file)?;
-    let _ = conn.execute("DROP TABLE Sales", params![]);
-    let _ = conn.execute("DROP TABLE Products", params![]);
-    conn.execute(
-        "CREATE TABLE Products (
-            id INTEGER PRIMARY KEY,
-            category TEXT NOT NULL,
-            name TEXT NOT NULL UNIQUE
-        )",
-        params![],
-    )?;
-    conn.execute(
-        "CREATE TABLE Sales (
-            id TEXT PRIMARY KEY,
-            product_id INTEGER NOT NULL REFERENCES Products,
-            sale_date BIGINT NOT NULL,
-            quantity DOUBLE PRECISION NOT NULL,
-            unit TEXT NOT NULL
-        )",
-        params![],
-    )?;
-    Ok(conn)
+fn parse_arg<T: std::str::FromStr<Err = String>>(index: usize) -> Option<T> {
+    let arg = std::env::args().nth(index)?;
+    Some(arg.parse::<T>().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }))
 }
 
-fn populate_db(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "INSERT INTO Products (
-            id, category, name
-            ) VALUES ($1, $2, $3)",
-        params![1, "fruit", "pears"],
-    )?;
-    conn.execute(
-        "INSERT INTO Sales (
-            id, product_id, sale_date, quantity, unit
-            ) VALUES ($1, $2, $3, $4, $5)",
-        params!["2020-103", 1, 1_234_567_890_i64, 7.545, "Kg"],
-    )?;
+fn main() -> Result<()> {
+    let database_file = std::env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("usage: sqlite_example <database file> [grouping [format]]");
+        std::process::exit(1);
+    });
+    let grouping = parse_arg::<Grouping>(2);
+    let format = parse_arg::<ReportFormat>(3).unwrap_or(ReportFormat::Table);
+    // Unlike `Connection::open`, this does not create a missing file.
+    let conn = Connection::open_with_flags(database_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    match grouping {
+        Some(grouping) => report::totals(&conn, grouping)?.write(std::io::stdout(), format)?,
+        None => print_db(&conn)?,
+    }
     Ok(())
 }
 
 fn print_db(conn: &Connection) -> Result<()> {
-    let mut command = conn.prepare(
-        "SELECT p.name, s.unit, s.quantity, s.sale_date
-        FROM Sales s
-        LEFT JOIN Products p
-        ON p.id = s.product_id
-        ORDER BY s.sale_date",
-    )?;
-
-    for sale_with_product in command.query_map(params![], |row| {
-        Ok(SaleWithProduct {
-            category: "".to_string(),
-            name: row.get(0)?,
-            quantity: row.get(1)?,
-            unit: row.get(2)?,
-            date: row.get(3)?,
-        })
-    })? {
-        if let Ok(item) = sale_with_product {
-            println!(
-                "At instant {}, {} {} of {} were sold.",
-                item.date, item.quantity, item.unit, item.name
-            );
-        }
+    for item in report::sales_with_products(conn)? {
+        println!(
+            "At instant {}, {} {} of {} ({}) were sold.",
+            item.date, item.quantity, item.unit, item.name, item.category
+        );
+    }
+    for grouping in &Grouping::ALL {
+        println!("\nTotals per {}:", grouping.name());
+        report::totals(conn, *grouping)?.write(std::io::stdout(), ReportFormat::Table)?;
     }
     Ok(())
 }
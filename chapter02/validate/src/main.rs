@@ -0,0 +1,85 @@
+// Checks datasets of products and sales, as:
+// cargo run -- ../data/sales.json ../data/sales.xml
+// cat ../data/sales.json | cargo run -- --format json
+// cargo run -- --unit lb --unit oz sales.db
+//
+// Every violation is printed with its location: a JSON pointer, a TOML key,
+// an XML path, a CSV line or a SQLite row. The exit code is 0 if the files
+// are valid, 1 if any violation is found, and 2 if a file cannot be read.
+
+use sales::{validate, Format, Locations, SalesAndProducts, KNOWN_UNITS};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Checks the consistency of files of sales and products")]
+struct Opt {
+    /// The format of the files, by default told by their extensions:
+    /// json, xml, toml, sqlite or csv.
+    #[structopt(short = "f", long = "format")]
+    format: Option<Format>,
+
+    /// A unit of measure accepted besides the default ones.
+    #[structopt(short = "u", long = "unit")]
+    units: Vec<String>,
+
+    /// By default, or with "-", the standard input.
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+fn read(format: Option<Format>, file: &PathBuf) -> Result<(SalesAndProducts, Locations), String> {
+    if file.to_str() == Some("-") {
+        let format = format.ok_or("choose the format of the standard input with --format")?;
+        return format
+            .read_located(std::io::stdin().lock())
+            .map_err(|e| e.to_string());
+    }
+    let format = format
+        .or_else(|| Format::from_path(file))
+        .ok_or("unknown extension; choose the format with --format")?;
+    format.read_file_located(file).map_err(|e| e.to_string())
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    let mut units = KNOWN_UNITS.to_vec();
+    units.extend(opt.units.iter().map(String::as_str));
+    let files = if opt.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        opt.files
+    };
+
+    let mut exit_code = 0;
+    for file in &files {
+        let name = match file.to_str() {
+            Some("-") => "<stdin>".to_string(),
+            _ => file.display().to_string(),
+        };
+        match read(opt.format, file) {
+            Ok((data, locations)) => {
+                let violations = validate(&data, &locations, &units);
+                for violation in &violations {
+                    println!("{}: {}: {}", name, violation.location, violation.message);
+                }
+                if violations.is_empty() {
+                    eprintln!(
+                        "{}: valid ({} products, {} sales)",
+                        name,
+                        data.products.len(),
+                        data.sales.len()
+                    );
+                } else {
+                    eprintln!("{}: {} violations", name, violations.len());
+                    exit_code = exit_code.max(1);
+                }
+            }
+            Err(message) => {
+                eprintln!("{}: {}", name, message);
+                exit_code = 2;
+            }
+        }
+    }
+    std::process::exit(exit_code);
+}
@@ -1,200 +1,29 @@
-use xml::reader::{EventReader, XmlEvent};
+// Reads the products and the sales of an XML file, and prints them;
+// with a second argument, writes them back to that file, as:
+// cargo run -- ../data/sales.xml copy.xml
 
-#[derive(Debug, Default)]
-struct S {}
-
-#[derive(Debug, Default)]
-struct Product {
-    id: u32,
-    category: String,
-    name: String,
-}
-
-#[derive(Debug, Default)]
-struct Sale {
-    id: String,
-    product_id: u32,
-    date: i64,
-    quantity: f64,
-    unit: String,
-}
-
-enum LocationItem {
-    Other,
-    InProduct,
-    InSale,
-}
-enum LocationProduct {
-    Other,
-    InId,
-    InCategory,
-    InName,
-}
-enum LocationSale {
-    Other,
-    InId,
-    InProductId,
-    InDate,
-    InQuantity,
-    InUnit,
-}
+use sales::xml_codec;
 
 fn main() {
-    let mut location_item = LocationItem::Other;
-    let mut location_product = LocationProduct::Other;
-    let mut location_sale = LocationSale::Other;
-
-    let mut product = Product::default();
-    let mut sale = Sale::default();
+    let input_path = std::env::args().nth(1).unwrap();
+    let file = std::fs::File::open(&input_path).unwrap();
+    let sales_and_products = match xml_codec::read(std::io::BufReader::new(file)) {
+        Ok(sales_and_products) => sales_and_products,
+        Err(e) => {
+            eprintln!("{}: {}", input_path, e);
+            std::process::exit(1);
+        }
+    };
 
-    let pathname = std::env::args().nth(1).unwrap();
-    let file = std::fs::File::open(pathname).unwrap();
-    let file = std::io::BufReader::new(file);
-    let parser = EventReader::new(file);
+    for product in &sales_and_products.products {
+        println!("Got product: {:?}", product);
+    }
+    for sale in &sales_and_products.sales {
+        println!("Got sale: {:?}", sale);
+    }
 
-    for event in parser {
-        match location_item {
-            LocationItem::Other => match event {
-                Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "product" => {
-                    location_item = LocationItem::InProduct;
-                    location_product = LocationProduct::Other;
-                    product = Default::default();
-                }
-                Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "sale" => {
-                    location_item = LocationItem::InSale;
-                    location_sale = LocationSale::Other;
-                    sale = Default::default();
-                }
-                _ => {}
-            },
-            LocationItem::InProduct => match &location_product {
-                LocationProduct::Other => match event {
-                    Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "id" => {
-                        location_product = LocationProduct::InId;
-                    }
-                    Ok(XmlEvent::StartElement { ref name, .. })
-                        if name.local_name == "category" =>
-                    {
-                        location_product = LocationProduct::InCategory;
-                    }
-                    Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "name" => {
-                        location_product = LocationProduct::InName;
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_item = LocationItem::Other;
-                        println!("  Exit product: {:?}", product);
-                    }
-                    _ => {}
-                },
-                LocationProduct::InId => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        product.id = characters.parse::<u32>().unwrap();
-                        println!("Got product.id: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_product = LocationProduct::Other;
-                    }
-                    _ => {}
-                },
-                LocationProduct::InCategory => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        product.category = characters.clone();
-                        println!("Got product.category: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_product = LocationProduct::Other;
-                    }
-                    _ => {}
-                },
-                LocationProduct::InName => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        product.name = characters.clone();
-                        println!("Got product.name: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_product = LocationProduct::Other;
-                    }
-                    _ => {}
-                },
-            },
-            LocationItem::InSale => match &location_sale {
-                LocationSale::Other => match event {
-                    Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "id" => {
-                        location_sale = LocationSale::InId;
-                    }
-                    Ok(XmlEvent::StartElement { ref name, .. })
-                        if name.local_name == "product-id" =>
-                    {
-                        location_sale = LocationSale::InProductId;
-                    }
-                    Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "date" => {
-                        location_sale = LocationSale::InDate;
-                    }
-                    Ok(XmlEvent::StartElement { ref name, .. })
-                        if name.local_name == "quantity" =>
-                    {
-                        location_sale = LocationSale::InQuantity;
-                    }
-                    Ok(XmlEvent::StartElement { ref name, .. }) if name.local_name == "unit" => {
-                        location_sale = LocationSale::InUnit;
-                    }
-                    Ok(XmlEvent::EndElement { ref name, .. }) if name.local_name == "sale" => {
-                        location_item = LocationItem::Other;
-                        println!("  Exit sale: {:?}", sale);
-                    }
-                    _ => {}
-                },
-                LocationSale::InId => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        sale.id = characters.clone();
-                        println!("Got sale.id: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_sale = LocationSale::Other;
-                    }
-                    _ => {}
-                },
-                LocationSale::InProductId => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        sale.product_id = characters.parse::<u32>().unwrap();
-                        println!("Got sale.product-id: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_sale = LocationSale::Other;
-                    }
-                    _ => {}
-                },
-                LocationSale::InDate => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        sale.date = characters.parse::<i64>().unwrap();
-                        println!("Got sale.date: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_sale = LocationSale::Other;
-                    }
-                    _ => {}
-                },
-                LocationSale::InQuantity => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        sale.quantity = characters.parse::<f64>().unwrap();
-                        println!("Got sale.quantity: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_sale = LocationSale::Other;
-                    }
-                    _ => {}
-                },
-                LocationSale::InUnit => match event {
-                    Ok(XmlEvent::Characters(characters)) => {
-                        sale.unit = characters.clone();
-                        println!("Got sale.unit: {}.", characters);
-                    }
-                    Ok(XmlEvent::EndElement { .. }) => {
-                        location_sale = LocationSale::Other;
-                    }
-                    _ => {}
-                },
-            },
-        }
+    if let Some(output_path) = std::env::args().nth(2) {
+        let file = std::fs::File::create(output_path).unwrap();
+        xml_codec::write(file, &sales_and_products, true).unwrap();
     }
 }
@@ -0,0 +1,210 @@
+use actix_web::dev::Payload;
+use actix_web::{error, web, FromRequest, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::quota::Quota;
+use crate::storage::Storage;
+use crate::AppState;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// A user of the server, who can reach only the files in their home directory.
+pub struct Account {
+    pub username: String,
+    /// Used as the password of the Basic authentication, or as the Bearer token.
+    secret: String,
+    pub access: Access,
+    /// The most bytes the home directory may hold, if limited.
+    pub quota: Option<Quota>,
+    pub home: Storage,
+}
+
+pub struct Accounts {
+    accounts: Vec<Account>,
+}
+
+impl Accounts {
+    /// Loads the accounts from a file having a line per account, as
+    /// "username:secret:read-only" or "username:secret:read-write:quota",
+    /// with the quota in bytes; empty lines and lines starting with '#' are skipped.
+    /// The home directories are created under the root, named as the users.
+    pub fn load(users_file: &str, root: &Storage) -> io::Result<Self> {
+        let text = std::fs::read_to_string(users_file)?;
+        let mut accounts: Vec<Account> = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |msg: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", users_file, index + 1, msg),
+                )
+            };
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 3 || fields.len() > 4 {
+                return Err(invalid("expected username:secret:access[:quota]"));
+            }
+            let username = fields[0];
+            if username.is_empty()
+                || !username
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(invalid("invalid username"));
+            }
+            if accounts.iter().any(|a| a.username == username) {
+                return Err(invalid("duplicate username"));
+            }
+            let secret = fields[1];
+            if secret.is_empty() {
+                return Err(invalid("empty secret"));
+            }
+            // The secret alone identifies a Bearer token.
+            if accounts.iter().any(|a| a.secret == secret) {
+                return Err(invalid("secret used by another account"));
+            }
+            let access = match fields[2] {
+                "read-only" => Access::ReadOnly,
+                "read-write" => Access::ReadWrite,
+                _ => return Err(invalid("access must be read-only or read-write")),
+            };
+            let home = Storage::open(root.root().join(username))?;
+            let quota = match fields.get(3) {
+                Some(quota) => {
+                    let limit = quota.parse::<u64>().map_err(|_| invalid("invalid quota"))?;
+                    Some(Quota::new(limit, home.used_bytes()?))
+                }
+                None => None,
+            };
+            accounts.push(Account {
+                username: username.to_string(),
+                secret: secret.to_string(),
+                access,
+                quota,
+                home,
+            });
+        }
+        Ok(Self { accounts })
+    }
+
+    /// The index of the account whose credentials the request carries, if any.
+    fn authenticate(&self, req: &HttpRequest) -> Option<usize> {
+        if let Ok(auth) = BasicAuth::extract(req) {
+            let password = auth.password()?;
+            self.accounts
+                .iter()
+                .position(|a| a.username == **auth.user_id() && secrets_match(password, &a.secret))
+        } else if let Ok(auth) = BearerAuth::extract(req) {
+            self.accounts
+                .iter()
+                .position(|a| secrets_match(auth.token(), &a.secret))
+        } else {
+            None
+        }
+    }
+}
+
+/// Compares in a time independent from where the secrets differ.
+fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// An access required by a handler, as a type parameter of `Authorized`.
+pub trait RequiredAccess: 'static {
+    const WRITE: bool;
+}
+
+pub struct CanRead;
+pub struct CanWrite;
+
+impl RequiredAccess for CanRead {
+    const WRITE: bool = false;
+}
+impl RequiredAccess for CanWrite {
+    const WRITE: bool = true;
+}
+
+/// Extracts the storage the request may use: without accounts the whole root,
+/// otherwise the home directory of the authenticated account.
+/// Fails with 401 without valid credentials, or with 403 if `A` requires
+/// writing and the account is read-only.
+pub struct Authorized<A: RequiredAccess> {
+    state: web::Data<AppState>,
+    /// The index of the account, if there are accounts.
+    account: Option<usize>,
+    _access: PhantomData<A>,
+}
+
+impl<A: RequiredAccess> Authorized<A> {
+    pub fn account(&self) -> Option<&Account> {
+        match (&self.state.accounts, self.account) {
+            (Some(accounts), Some(index)) => accounts.accounts.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn storage(&self) -> &Storage {
+        match self.account() {
+            Some(account) => &account.home,
+            None => &self.state.storage,
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        self.account().map_or("anonymous", |a| a.username.as_str())
+    }
+}
+
+fn unauthorized() -> actix_web::Error {
+    error::InternalError::from_response(
+        "Authentication required",
+        HttpResponse::Unauthorized()
+            .header("www-authenticate", "Basic realm=\"file_transfer\"")
+            .content_type("text/plain")
+            .body("Authentication required"),
+    )
+    .into()
+}
+
+impl<A: RequiredAccess> FromRequest for Authorized<A> {
+    type Config = ();
+    type Error = actix_web::Error;
+    type Future = Result<Self, Self::Error>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let state = web::Data::<AppState>::from_request(req, payload)?;
+        let account = match &state.accounts {
+            Some(accounts) => {
+                let index = accounts.authenticate(req).ok_or_else(unauthorized)?;
+                if A::WRITE && accounts.accounts[index].access == Access::ReadOnly {
+                    println!(
+                        "Rejected request of read-only user: \"{}\"",
+                        accounts.accounts[index].username
+                    );
+                    return Err(error::ErrorForbidden("Read-only account"));
+                }
+                Some(index)
+            }
+            None => None,
+        };
+        Ok(Authorized {
+            state,
+            account,
+            _access: PhantomData,
+        })
+    }
+}
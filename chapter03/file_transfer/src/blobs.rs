@@ -0,0 +1,141 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::digest::{to_hex, Sha256Sum};
+use crate::storage::temp_sibling;
+
+/// A directory of files named by the SHA-256 of their contents. The stored
+/// files are hard links to them, so that identical uploads share their storage;
+/// hence it must be on the same file system as the storage root.
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    /// Uses the given directory, creating it if missing.
+    pub fn open(dir: &str) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: std::fs::canonicalize(dir)?,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn blob_path(&self, sha256: &Sha256Sum) -> PathBuf {
+        let hex = to_hex(sha256);
+        self.dir.join(&hex[..2]).join(hex)
+    }
+
+    /// Makes the file just written at `path` share its contents with the other
+    /// files having the same: it becomes the blob if there is none yet,
+    /// otherwise it is replaced by a link to the blob.
+    pub fn share(&self, path: &Path, sha256: &Sha256Sum) -> std::io::Result<()> {
+        let blob = self.blob_path(sha256);
+        if let Some(parent) = blob.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match std::fs::hard_link(path, &blob) {
+            Ok(_) => return Ok(()),
+            Err(ref error) if error.kind() == ErrorKind::AlreadyExists => {}
+            Err(error) => return Err(error),
+        }
+        // The link is made aside and renamed over the file,
+        // so that the name always has the complete contents.
+        let link = temp_sibling(path);
+        std::fs::hard_link(&blob, &link)?;
+        std::fs::rename(&link, path).map_err(|error| {
+            let _ = std::fs::remove_file(&link);
+            error
+        })
+    }
+
+    /// Removes the blobs no stored file links to anymore,
+    /// returning how many were removed.
+    #[cfg(unix)]
+    pub fn collect_garbage(&self) -> std::io::Result<usize> {
+        use std::os::unix::fs::MetadataExt;
+
+        let mut removed = 0;
+        for subdir in std::fs::read_dir(&self.dir)? {
+            let subdir = subdir?;
+            if !subdir.file_type()?.is_dir() {
+                continue;
+            }
+            for blob in std::fs::read_dir(subdir.path())? {
+                let blob = blob?;
+                let metadata = blob.metadata()?;
+                if metadata.is_file() && metadata.nlink() == 1 {
+                    std::fs::remove_file(blob.path())?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// The count of links is not available, so no blob is removed.
+    #[cfg(not(unix))]
+    pub fn collect_garbage(&self) -> std::io::Result<usize> {
+        Ok(0)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    fn sha256_of(contents: &str) -> Sha256Sum {
+        let mut sha256 = [0; 32];
+        sha256[..contents.len()].copy_from_slice(contents.as_bytes());
+        sha256
+    }
+
+    #[test]
+    fn shares_the_identical_files() {
+        let dir =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), "blobs"));
+        let _ = std::fs::remove_dir_all(&dir);
+        let files = dir.join("files");
+        std::fs::create_dir_all(&files).unwrap();
+        let blobs = BlobStore::open(dir.join("blobs").to_str().unwrap()).unwrap();
+        let inode = |name: &str| std::fs::metadata(files.join(name)).unwrap().ino();
+
+        for name in &["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(files.join(name), "Same.").unwrap();
+        }
+        std::fs::write(files.join("d.txt"), "Other.").unwrap();
+        blobs
+            .share(&files.join("a.txt"), &sha256_of("Same."))
+            .unwrap();
+        blobs
+            .share(&files.join("b.txt"), &sha256_of("Same."))
+            .unwrap();
+        blobs
+            .share(&files.join("d.txt"), &sha256_of("Other."))
+            .unwrap();
+        let blob = blobs.blob_path(&sha256_of("Same."));
+        assert!(blob.starts_with(blobs.dir()));
+        assert_eq!(std::fs::metadata(&blob).unwrap().ino(), inode("a.txt"));
+        assert_eq!(inode("a.txt"), inode("b.txt"));
+        assert_ne!(inode("a.txt"), inode("c.txt"));
+        assert_eq!(
+            std::fs::read_to_string(files.join("b.txt")).unwrap(),
+            "Same."
+        );
+        // No temporary link is left behind.
+        assert_eq!(std::fs::read_dir(&files).unwrap().count(), 4);
+
+        assert_eq!(blobs.collect_garbage().unwrap(), 0);
+        std::fs::remove_file(files.join("d.txt")).unwrap();
+        std::fs::remove_file(files.join("a.txt")).unwrap();
+        assert_eq!(blobs.collect_garbage().unwrap(), 1);
+        assert!(!blobs.blob_path(&sha256_of("Other.")).exists());
+        std::fs::remove_file(files.join("b.txt")).unwrap();
+        assert_eq!(blobs.collect_garbage().unwrap(), 1);
+        assert!(!blob.exists());
+    }
+}
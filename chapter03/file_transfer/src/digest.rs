@@ -0,0 +1,263 @@
+use actix_web::HttpRequest;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub type Sha256Sum = [u8; 32];
+
+/// The cache is emptied when it reaches this size,
+/// rather than keeping track of which digests are still used.
+const MAX_CACHED_DIGESTS: usize = 10_000;
+
+/// The digests a client declares for the body it uploads,
+/// by the `Content-MD5` header or by the `Digest` header.
+#[derive(Default)]
+pub struct ExpectedDigests {
+    md5: Option<Vec<u8>>,
+    sha256: Option<Vec<u8>>,
+}
+
+impl ExpectedDigests {
+    /// Reads the headers, as "Content-MD5: <base64>" and as
+    /// "Digest: SHA-256=<base64>, MD5=<base64>"; other algorithms are ignored.
+    pub fn from_request(req: &HttpRequest) -> Result<Self, String> {
+        let mut expected = Self::default();
+        if let Some(value) = req.headers().get("content-md5") {
+            expected.md5 = Some(decode_digest(
+                value.to_str().unwrap_or(""),
+                16,
+                "Content-MD5",
+            )?);
+        }
+        if let Some(value) = req.headers().get("digest") {
+            let value = value
+                .to_str()
+                .map_err(|_| "Invalid Digest header".to_string())?;
+            for instance in value.split(',') {
+                let (algorithm, encoded) = match instance.find('=') {
+                    Some(equal) => (instance[..equal].trim(), &instance[equal + 1..]),
+                    None => return Err(format!("Invalid Digest header: \"{}\"", value)),
+                };
+                if algorithm.eq_ignore_ascii_case("sha-256") {
+                    expected.sha256 = Some(decode_digest(encoded, 32, "SHA-256")?);
+                } else if algorithm.eq_ignore_ascii_case("md5") {
+                    expected.md5 = Some(decode_digest(encoded, 16, "MD5")?);
+                }
+            }
+        }
+        Ok(expected)
+    }
+}
+
+fn decode_digest(encoded: &str, len: usize, name: &str) -> Result<Vec<u8>, String> {
+    match base64::decode(encoded.trim()) {
+        Ok(digest) if digest.len() == len => Ok(digest),
+        _ => Err(format!("Invalid {} digest: \"{}\"", name, encoded.trim())),
+    }
+}
+
+/// Computes the digests of an uploaded body as its chunks arrive,
+/// the MD5 only if the client declared one.
+pub struct UploadHasher {
+    sha256: Sha256,
+    md5: Option<Md5>,
+    expected: ExpectedDigests,
+}
+
+impl UploadHasher {
+    pub fn new(expected: ExpectedDigests) -> Self {
+        Self {
+            sha256: Sha256::new(),
+            md5: expected.md5.as_ref().map(|_| Md5::new()),
+            expected,
+        }
+    }
+
+    pub fn input(&mut self, chunk: &[u8]) {
+        self.sha256.input(chunk);
+        if let Some(md5) = &mut self.md5 {
+            md5.input(chunk);
+        }
+    }
+
+    /// The SHA-256 of the body, or an error if it differs from a declared digest.
+    pub fn finish(self) -> Result<Sha256Sum, String> {
+        let mut sha256 = [0; 32];
+        sha256.copy_from_slice(&self.sha256.result());
+        if let Some(expected) = &self.expected.sha256 {
+            if expected[..] != sha256[..] {
+                return Err(format!(
+                    "SHA-256 mismatch: declared {}, received {}",
+                    base64::encode(expected),
+                    base64::encode(&sha256)
+                ));
+            }
+        }
+        if let (Some(expected), Some(md5)) = (&self.expected.md5, self.md5) {
+            let md5 = md5.result();
+            if expected[..] != md5[..] {
+                return Err(format!(
+                    "MD5 mismatch: declared {}, received {}",
+                    base64::encode(expected),
+                    base64::encode(&md5)
+                ));
+            }
+        }
+        Ok(sha256)
+    }
+}
+
+/// The value of the `Digest` header sent with a file.
+pub fn digest_header(sha256: &Sha256Sum) -> String {
+    format!("SHA-256={}", base64::encode(sha256))
+}
+
+pub fn to_hex(sha256: &Sha256Sum) -> String {
+    sha256.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn file_sha256(path: &Path) -> std::io::Result<Sha256Sum> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read_count = file.read(&mut buffer)?;
+        if read_count == 0 {
+            break;
+        }
+        hasher.input(&buffer[..read_count]);
+    }
+    let mut sha256 = [0; 32];
+    sha256.copy_from_slice(&hasher.result());
+    Ok(sha256)
+}
+
+/// The SHA-256 of the stored files, so that a file is read again only when
+/// it changes, as told by its entity tag.
+#[derive(Default)]
+pub struct DigestCache {
+    digests: Mutex<HashMap<PathBuf, (String, Sha256Sum)>>,
+}
+
+impl DigestCache {
+    pub fn insert(&self, path: &Path, etag: &str, sha256: Sha256Sum) {
+        let mut digests = self.digests.lock().unwrap();
+        if digests.len() >= MAX_CACHED_DIGESTS {
+            digests.clear();
+        }
+        digests.insert(path.to_path_buf(), (etag.to_string(), sha256));
+    }
+
+    /// The SHA-256 of the file if cached with this entity tag, without reading the file.
+    pub fn cached(&self, path: &Path, etag: &str) -> Option<Sha256Sum> {
+        match self.digests.lock().unwrap().get(path) {
+            Some((cached_etag, sha256)) if cached_etag == etag => Some(*sha256),
+            _ => None,
+        }
+    }
+
+    /// The SHA-256 of the file, read from the cache if its entity tag is unchanged.
+    pub fn get(&self, path: &Path, etag: &str) -> std::io::Result<Sha256Sum> {
+        if let Some(sha256) = self.cached(path, etag) {
+            return Ok(sha256);
+        }
+        let sha256 = file_sha256(path)?;
+        self.insert(path, etag, sha256);
+        Ok(sha256)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const CONTENTS: &str = "File contents.";
+    const MD5: &str = "A/F26yyieRb5nmQlF1LWiQ==";
+    const SHA256: &str = "eQsVD/oX4izB5MVGpqIiVPhGrju/dESdyRpxYs/erLM=";
+    const SHA256_HEX: &str = "790b150ffa17e22cc1e4c546a6a22254f846ae3bbf74449dc91a7162cfdeacb3";
+
+    fn expected(headers: &[(&str, &str)]) -> Result<ExpectedDigests, String> {
+        let mut request = TestRequest::default();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        ExpectedDigests::from_request(&request.to_http_request())
+    }
+
+    fn hash(expected: ExpectedDigests, chunks: &[&str]) -> Result<Sha256Sum, String> {
+        let mut hasher = UploadHasher::new(expected);
+        for chunk in chunks {
+            hasher.input(chunk.as_bytes());
+        }
+        hasher.finish()
+    }
+
+    #[test]
+    fn reads_the_declared_digests() {
+        let digests = expected(&[]).unwrap();
+        assert!(digests.md5.is_none() && digests.sha256.is_none());
+        let digests = expected(&[("content-md5", MD5)]).unwrap();
+        assert_eq!(digests.md5, base64::decode(MD5).ok());
+        let digest = format!("sha-256={}, UNIXsum=30637, MD5={}", SHA256, MD5);
+        let digests = expected(&[("digest", &digest)]).unwrap();
+        assert_eq!(digests.sha256, base64::decode(SHA256).ok());
+        assert_eq!(digests.md5, base64::decode(MD5).ok());
+
+        assert!(expected(&[("content-md5", "not base64")]).is_err());
+        // A SHA-256 is not an MD5.
+        assert!(expected(&[("content-md5", SHA256)]).is_err());
+        assert!(expected(&[("digest", &format!("SHA-256={}", MD5))]).is_err());
+        assert!(expected(&[("digest", "SHA-256")]).is_err());
+    }
+
+    #[test]
+    fn checks_the_body_against_the_declared_digests() {
+        let sha256 = hash(ExpectedDigests::default(), &["File ", "contents."]).unwrap();
+        assert_eq!(to_hex(&sha256), SHA256_HEX);
+        assert_eq!(digest_header(&sha256), format!("SHA-256={}", SHA256));
+
+        let digest = format!("SHA-256={},MD5={}", SHA256, MD5);
+        assert!(hash(expected(&[("digest", &digest)]).unwrap(), &[CONTENTS]).is_ok());
+        let md5 = expected(&[("content-md5", MD5)]).unwrap();
+        let error = hash(md5, &["Other contents."]).unwrap_err();
+        assert!(error.starts_with("MD5 mismatch"), "{}", error);
+        let sha256 = expected(&[("digest", &format!("SHA-256={}", SHA256))]).unwrap();
+        let error = hash(sha256, &["File contents"]).unwrap_err();
+        assert!(error.starts_with("SHA-256 mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn reads_a_file_again_only_when_it_changes() {
+        let dir = std::env::temp_dir().join(format!(
+            "file_transfer-{}-{}",
+            std::process::id(),
+            "digests"
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("datafile.txt");
+        std::fs::write(&path, CONTENTS).unwrap();
+        let cache = DigestCache::default();
+        assert_eq!(cache.cached(&path, "\"1\""), None);
+        let sha256 = cache.get(&path, "\"1\"").unwrap();
+        assert_eq!(to_hex(&sha256), SHA256_HEX);
+        assert_eq!(cache.cached(&path, "\"1\""), Some(sha256));
+
+        // Until the entity tag changes, the cached digest is kept.
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(cache.get(&path, "\"1\"").unwrap(), sha256);
+        assert_eq!(cache.cached(&path, "\"2\""), None);
+        let empty = cache.get(&path, "\"2\"").unwrap();
+        assert_eq!(
+            to_hex(&empty),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        cache.insert(&path, "\"3\"", sha256);
+        assert_eq!(cache.cached(&path, "\"3\""), Some(sha256));
+        assert!(cache.get(&dir.join("missing.txt"), "\"1\"").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use actix_web::http::header::HttpDate;
+use serde_derive::Serialize;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::storage::{is_temp_name, Storage};
+
+/// A file or a directory inside a listed directory.
+#[derive(Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    /// In bytes, 0 for a directory.
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+/// The entries of the directory at `dir_name`, relative to the storage root,
+/// the directories first, then by name. The symbolic links leading outside
+/// the root are left out.
+pub fn read_listing(storage: &Storage, dir_name: &str) -> std::io::Result<Vec<Entry>> {
+    let dir_path = if dir_name.is_empty() {
+        storage.root().to_path_buf()
+    } else {
+        storage
+            .resolve(dir_name)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+    };
+    let mut entries = vec![];
+    for dir_entry in std::fs::read_dir(&dir_path)? {
+        let dir_entry = dir_entry?;
+        let name = match dir_entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        // The uploads in progress are not shown.
+        if is_temp_name(&name) {
+            continue;
+        }
+        let path = match storage.resolve(&join_names(dir_name, &name)) {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+        // Follows the symbolic links, known to stay inside the root.
+        let metadata = std::fs::metadata(&path)?;
+        entries.push(Entry {
+            name,
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs()),
+        });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// Joins a name to a directory name, which is empty for the root.
+pub fn join_names(dir_name: &str, name: &str) -> String {
+    let dir_name = dir_name.trim_end_matches('/');
+    if dir_name.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir_name, name)
+    }
+}
+
+/// A page listing the entries of a directory, linking each of them.
+pub fn to_html(dir_name: &str, entries: &[Entry]) -> String {
+    let dir_name = dir_name.trim_end_matches('/');
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>/{0}</title></head>\n\
+         <body>\n<h1>/{0}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n",
+        escape_html(dir_name)
+    );
+    if !dir_name.is_empty() {
+        let parent = Path::new(dir_name)
+            .parent()
+            .and_then(|p| p.to_str())
+            .unwrap_or("");
+        html += &format!(
+            "<tr><td><a href=\"{}\">..</a></td><td></td><td></td></tr>\n",
+            href(parent, true)
+        );
+    }
+    for entry in entries {
+        let modified = HttpDate::from(UNIX_EPOCH + Duration::from_secs(entry.modified));
+        html += &format!(
+            "<tr><td><a href=\"{}\">{}{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            href(&join_names(dir_name, &entry.name), entry.is_dir),
+            escape_html(&entry.name),
+            if entry.is_dir { "/" } else { "" },
+            if entry.is_dir {
+                String::new()
+            } else {
+                entry.size.to_string()
+            },
+            modified
+        );
+    }
+    html += "</table>\n</body>\n</html>\n";
+    html
+}
+
+/// The absolute URL path of a name, with its segments percent-encoded.
+pub fn href(name: &str, is_dir: bool) -> String {
+    let mut href = String::new();
+    for segment in name.split('/').filter(|s| !s.is_empty()) {
+        href.push('/');
+        for byte in segment.bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                href.push(byte as char);
+            } else {
+                href += &format!("%{:02X}", byte);
+            }
+        }
+    }
+    if is_dir || href.is_empty() {
+        href.push('/');
+    }
+    href
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A storage in a new directory named after the test, holding "b/",
+    /// "a.txt", "c.txt" and an upload in progress.
+    fn temp_storage(name: &str) -> Storage {
+        let dir =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage = Storage::open(dir.join("root")).unwrap();
+        std::fs::create_dir(storage.root().join("b")).unwrap();
+        std::fs::write(storage.root().join("b/d.txt"), "Nested.").unwrap();
+        std::fs::write(storage.root().join("c.txt"), "C").unwrap();
+        std::fs::write(storage.root().join("a.txt"), "AAA").unwrap();
+        let upload = crate::storage::temp_sibling(&storage.root().join("e.txt"));
+        std::fs::write(upload, "In progress.").unwrap();
+        storage
+    }
+
+    fn names(entries: &[Entry]) -> Vec<(&str, bool, u64)> {
+        entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.is_dir, e.size))
+            .collect()
+    }
+
+    #[test]
+    fn lists_the_directories_first() {
+        let storage = temp_storage("listing");
+        let entries = read_listing(&storage, "").unwrap();
+        assert_eq!(
+            names(&entries),
+            vec![("b", true, 0), ("a.txt", false, 3), ("c.txt", false, 1)]
+        );
+        assert!(entries[1].modified > 0);
+        let entries = read_listing(&storage, "b/").unwrap();
+        assert_eq!(names(&entries), vec![("d.txt", false, 7)]);
+
+        assert!(read_listing(&storage, "missing").is_err());
+        assert!(read_listing(&storage, "../root").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn leaves_out_the_links_leading_outside() {
+        let storage = temp_storage("listing_links");
+        let outside = storage.root().parent().unwrap().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, storage.root().join("out")).unwrap();
+        std::os::unix::fs::symlink(storage.root().join("b"), storage.root().join("in")).unwrap();
+        let entries = read_listing(&storage, "").unwrap();
+        assert_eq!(
+            names(&entries),
+            vec![
+                ("b", true, 0),
+                ("in", true, 0),
+                ("a.txt", false, 3),
+                ("c.txt", false, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_the_links() {
+        assert_eq!(href("", true), "/");
+        assert_eq!(href("", false), "/");
+        assert_eq!(href("a b/c#d.txt", false), "/a%20b/c%23d.txt");
+        assert_eq!(href("caf\u{e9}/", true), "/caf%C3%A9/");
+        assert_eq!(join_names("", "a.txt"), "a.txt");
+        assert_eq!(join_names("b/", "a.txt"), "b/a.txt");
+    }
+
+    #[test]
+    fn escapes_the_names_in_html() {
+        let entries = vec![Entry {
+            name: "<a>&\"b\"".to_string(),
+            is_dir: false,
+            size: 12,
+            modified: 0,
+        }];
+        let html = to_html("d/", &entries);
+        assert!(html.contains("<h1>/d</h1>"), "{}", html);
+        assert!(html.contains("<a href=\"/\">..</a>"), "{}", html);
+        assert!(
+            html.contains("<a href=\"/d/%3Ca%3E%26%22b%22\">&lt;a&gt;&amp;&quot;b&quot;</a>"),
+            "{}",
+            html
+        );
+        assert!(html.contains("<td>12</td>"), "{}", html);
+        assert!(!to_html("", &[]).contains(">..<"));
+    }
+}
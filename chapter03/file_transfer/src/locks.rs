@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The paths being changed by a request, so that another request
+/// changing the same path is rejected instead of mixing with it.
+/// The paths are locked once resolved, so that a symbolic link and
+/// its target, or two ways to write a name, are the same path.
+#[derive(Clone, Default)]
+pub struct PathLocks {
+    locked: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl PathLocks {
+    /// Locks the path until the returned guard is dropped,
+    /// or returns None if it is already locked.
+    pub fn try_lock(&self, path: &Path) -> Option<PathLock> {
+        let path = lock_key(path);
+        if self.locked.lock().unwrap().insert(path.clone()) {
+            Some(PathLock {
+                locks: self.clone(),
+                path,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// The canonical path, or for a file yet to be created, the canonical path
+/// of its directory joined with its name. Not the inode, since the blob store
+/// links distinct names to the same file.
+fn lock_key(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map_or_else(|_| path.to_path_buf(), |parent| parent.join(name)),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub struct PathLock {
+    locks: PathLocks,
+    path: PathBuf,
+}
+
+impl Drop for PathLock {
+    fn drop(&mut self) {
+        self.locks.locked.lock().unwrap().remove(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_a_path_until_released() {
+        let dir =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), "locks"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "A").unwrap();
+        let locks = PathLocks::default();
+
+        let lock = locks.try_lock(&dir.join("a.txt")).unwrap();
+        assert!(locks.try_lock(&dir.join("a.txt")).is_none());
+        assert!(locks.try_lock(&dir.join("sub/../a.txt")).is_none());
+        let other = locks.try_lock(&dir.join("b.txt")).unwrap();
+        assert!(locks.try_lock(&dir.join("sub/../b.txt")).is_none());
+        drop(lock);
+        assert!(locks.try_lock(&dir.join("a.txt")).is_some());
+        drop(other);
+        assert!(locks.locked.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn locks_the_target_of_a_link() {
+        let dir = std::env::temp_dir().join(format!(
+            "file_transfer-{}-{}",
+            std::process::id(),
+            "locks_links"
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/a.txt"), "A").unwrap();
+        std::os::unix::fs::symlink(dir.join("sub/a.txt"), dir.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(dir.join("sub"), dir.join("linked")).unwrap();
+        let locks = PathLocks::default();
+
+        let _lock = locks.try_lock(&dir.join("link.txt")).unwrap();
+        assert!(locks.try_lock(&dir.join("sub/a.txt")).is_none());
+        assert!(locks.try_lock(&dir.join("linked/a.txt")).is_none());
+        let _new = locks.try_lock(&dir.join("linked/new.txt")).unwrap();
+        assert!(locks.try_lock(&dir.join("sub/new.txt")).is_none());
+    }
+}
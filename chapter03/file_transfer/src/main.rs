@@ -1,165 +1,1327 @@
-// test it with the following commands:
+// test it with the client in ../file_transfer_client, or with the following commands:
 // curl -X DELETE http://localhost:8080/datafile.txt
 // curl -X GET http://localhost:8080/datafile.txt
 // curl -X PUT http://localhost:8080/datafile.txt -d "File contents."
-// curl -X POST http://localhost:8080/data-d "File contents."
-// curl -X GET http://localhost:8080/a/b
+// curl -X POST http://localhost:8080/data.txt -d "File contents."
+// curl -X GET http://localhost:8080/
+// curl -X MKCOL http://localhost:8080/a
+// curl -X PUT http://localhost:8080/a/b -d "File contents."
+// curl -X GET http://localhost:8080/a/?format=html
+// curl -X DELETE http://localhost:8080/a?recursive=true
+// curl -X GET http://localhost:8080/..%2FCargo.toml
+// curl -X GET http://localhost:8080/datafile.txt -H "Range: bytes=0-99,-100"
+// curl -X GET http://localhost:8080/datafile.txt -H 'If-None-Match: "<etag>"'
+//
+// The settings are read from the TOML file given by --config or by the
+// FILE_TRANSFER_CONFIG variable, as ../../chapter05/server_config/server.toml,
+// and each one can be overridden by a FILE_TRANSFER_* variable or a flag;
+// --help lists them.
+//
+// The files are kept in the directory given as the first argument, or by
+// storage.root, or else in the current directory. Any file type is accepted,
+// up to the size in bytes given by storage.max_upload_size, or the
+// FILE_TRANSFER_MAX_UPLOAD_SIZE variable (1 GiB by default):
+// curl -X PUT http://localhost:8080/picture.png --data-binary @picture.png
+//
+// If the FILE_TRANSFER_USERS variable names a users file, every request must
+// be authenticated, and each user reaches only their home directory, named
+// as them inside the storage root. Each line of the file is an account, as
+// "username:secret:read-only" or "username:secret:read-write:quota",
+// with the quota in bytes, which is optional:
+// curl -u alice:secret -X PUT http://localhost:8080/datafile.txt -d "File contents."
+// curl -H "Authorization: Bearer secret" -X GET http://localhost:8080/datafile.txt
+//
+// The SHA-256 of a file is sent in the Digest header of the responses,
+// also to HEAD requests. An upload declaring its digest, by a Content-MD5 or
+// a Digest header, is rejected if the received contents do not match it:
+// curl -X PUT http://localhost:8080/datafile.txt -d "File contents." -H "Content-MD5: $(printf 'File contents.' | openssl md5 -binary | base64)"
+// curl -I http://localhost:8080/datafile.txt
+//
+// An upload is written to a hidden temporary file, renamed to the file once
+// complete, and a request changing a file already being changed is rejected.
+// A change can be made conditional on the current version of the file:
+// curl -X PUT http://localhost:8080/datafile.txt -d "New contents." -H 'If-Match: "<etag>"'
+// curl -X PUT http://localhost:8080/datafile.txt -d "File contents." -H "If-None-Match: *"
+//
+// If the FILE_TRANSFER_BLOB_DIR variable names a directory, outside the storage
+// root but on the same file system, the contents of every upload are kept there
+// once, named by their SHA-256, and the stored files are hard links to them.
 
-use actix_web::{web, web::Path, App, Error, HttpRequest, HttpResponse, HttpServer, Responder};
-use futures::{
-    future::{ok, Future},
-    Stream,
+mod auth;
+mod blobs;
+mod digest;
+mod listing;
+mod locks;
+mod media_type;
+mod quota;
+mod range;
+mod storage;
+mod streaming;
+mod validators;
+
+use actix_web::dev::SizedStream;
+use actix_web::http::{Method, StatusCode};
+use actix_web::{
+    middleware, web, web::Path, App, Error, HttpRequest, HttpResponse, HttpServer, Responder,
 };
+use futures::future::{err, ok, Either, Future};
 use rand::prelude::*;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
+use serde_derive::Deserialize;
+use server_config::Config;
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+
+use auth::{Accounts, Authorized, CanRead, CanWrite, RequiredAccess};
+use blobs::BlobStore;
+use digest::{digest_header, DigestCache, ExpectedDigests, UploadHasher};
+use listing::{href, read_listing, to_html};
+use locks::PathLocks;
+use quota::Reservation;
+use range::{multipart_segments, parse_range, RangeRequest};
+use storage::{size_of, temp_sibling, Storage};
+use streaming::{
+    check_content_length, declared_size, is_storage_full, write_payload, FileChunks, Segment,
+    UploadLimit, Written,
+};
+use uuid::Uuid;
+use validators::{is_change_allowed, Validators};
+
+/// The upload size limit when `storage.max_upload_size` is not configured.
+const DEFAULT_MAX_UPLOAD_SIZE: u64 = 1024 * 1024 * 1024;
+
+struct AppState {
+    storage: Storage,
+    /// In bytes.
+    max_upload_size: u64,
+    /// Without accounts, anyone can use the whole storage.
+    accounts: Option<Accounts>,
+    digests: DigestCache,
+    /// Only if the identical uploads share their storage.
+    blobs: Option<BlobStore>,
+    /// The files being uploaded or deleted.
+    locks: PathLocks,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteOptions {
+    /// Required to delete a directory which is not empty.
+    recursive: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ListingOptions {
+    /// "html" or "json"; without it the `Accept` header decides.
+    format: Option<String>,
+}
 
 fn flust_stdout() {
     std::io::stdout().flush().unwrap();
 }
 
-fn delete_file(info: Path<(String,)>) -> impl Responder {
+fn invalid_path(error_message: &str) -> HttpResponse {
+    println!("Rejected path: {}", error_message);
+    HttpResponse::BadRequest()
+        .content_type("text/plain")
+        .body(error_message.to_string())
+}
+
+/// The answer to a failed creation of the file or the directory `name`.
+fn create_failure(name: &str, error: &std::io::Error) -> HttpResponse {
+    println!("Failed to create: \"{}\" -> {}", name, error);
+    if error.kind() == ErrorKind::NotFound {
+        HttpResponse::Conflict()
+            .content_type("text/plain")
+            .body("The parent directory does not exist")
+    } else if is_storage_full(error) {
+        HttpResponse::InsufficientStorage()
+            .content_type("text/plain")
+            .body("Not enough storage space")
+    } else {
+        HttpResponse::InternalServerError().finish()
+    }
+}
+
+fn being_changed(name: &str) -> HttpResponse {
+    println!("Rejected: \"{}\" -> already being changed", name);
+    HttpResponse::Conflict()
+        .content_type("text/plain")
+        .body("Another request is changing this file")
+}
+
+fn precondition_failed(name: &str) -> HttpResponse {
+    println!("Rejected: \"{}\" -> precondition failed", name);
+    HttpResponse::PreconditionFailed()
+        .content_type("text/plain")
+        .body("The file is not in the version the request expects")
+}
+
+/// The validators of the file at `path`, if there is one.
+fn current_validators(path: &std::path::Path) -> Option<Validators> {
+    std::fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| Validators::of(&metadata))
+}
+
+fn delete_file(
+    req: HttpRequest,
+    info: Path<(String,)>,
+    query: web::Query<DeleteOptions>,
+    auth: Authorized<CanWrite>,
+    state: web::Data<AppState>,
+) -> impl Responder {
     let filename = &info.0;
-    print!("Deleting file: \"{}\" ... ", filename);
+    print!(
+        "Deleting file: \"{}\" by {} ... ",
+        filename,
+        auth.username()
+    );
     flust_stdout();
 
-    match std::fs::remove_file(&filename) {
+    let path = match auth.storage().resolve(filename) {
+        Ok(path) => path,
+        Err(msg) => return invalid_path(&msg),
+    };
+    let _lock = match state.locks.try_lock(&path) {
+        Some(lock) => lock,
+        None => return being_changed(filename),
+    };
+    if !is_change_allowed(&req, current_validators(&path).as_ref()) {
+        return precondition_failed(filename);
+    }
+    // With a quota, what the deletion frees is counted, even if it fails halfway.
+    let quota = auth.account().and_then(|account| account.quota.as_ref());
+    let size_before = quota.map_or(0, |_| size_of(&path));
+    // A symbolic link is removed, never what it points to.
+    let is_dir = path.symlink_metadata().map_or(false, |m| m.is_dir());
+    let result = if !is_dir {
+        std::fs::remove_file(&path)
+    } else if query.recursive == Some(true) {
+        std::fs::remove_dir_all(&path)
+    } else {
+        std::fs::remove_dir(&path)
+    };
+    if let Some(quota) = quota {
+        quota.release(size_before.saturating_sub(size_of(&path)));
+    }
+    match result {
         Ok(_) => {
-            println!("Deleted file: \"{}\"", filename);
-            HttpResponse::Ok()
+            println!("Deleted: \"{}\"", filename);
+            HttpResponse::Ok().finish()
         }
         Err(error) => {
-            println!("Failed to delete file: \"{}\" -> {}", filename, error);
-            HttpResponse::NotFound()
+            println!("Failed to delete: \"{}\" -> {}", filename, error);
+            if error.kind() == ErrorKind::NotFound {
+                HttpResponse::NotFound().finish()
+            } else if is_dir && query.recursive != Some(true) && has_entries(&path) {
+                HttpResponse::Conflict()
+                    .content_type("text/plain")
+                    .body("The directory is not empty: add ?recursive=true to delete its contents")
+            } else {
+                HttpResponse::InternalServerError().finish()
+            }
         }
     }
 }
 
-fn download_file(info: Path<(String,)>) -> impl Responder {
-    let filename = &info.0;
-    print!("Downloading file: \"{}\" ... ", filename);
+/// Whether the directory at `path` holds anything.
+fn has_entries(path: &std::path::Path) -> bool {
+    std::fs::read_dir(path).map_or(false, |mut entries| entries.next().is_some())
+}
+
+fn make_directory(info: Path<(String,)>, auth: Authorized<CanWrite>) -> impl Responder {
+    let dirname = info.0.trim_end_matches('/');
+    print!(
+        "Creating directory: \"{}\" by {} ... ",
+        dirname,
+        auth.username()
+    );
     flust_stdout();
 
-    let read_file_contents = |filename: &str| -> std::io::Result<String> {
-        use std::io::Read;
-        let mut contents = String::new();
-        File::open(filename)?.read_to_string(&mut contents)?;
-        Ok(contents)
+    let path = match auth.storage().resolve(dirname) {
+        Ok(path) => path,
+        Err(msg) => return invalid_path(&msg),
     };
+    // As WebDAV MKCOL, the parent directory must exist.
+    match std::fs::create_dir(&path) {
+        Ok(_) => {
+            println!("Created directory: \"{}\"", dirname);
+            HttpResponse::Created().finish()
+        }
+        Err(ref error) if error.kind() == ErrorKind::AlreadyExists => {
+            println!("Already existing: \"{}\"", dirname);
+            HttpResponse::MethodNotAllowed()
+                .content_type("text/plain")
+                .body("A file or a directory with this name already exists")
+        }
+        Err(error) => create_failure(dirname, &error),
+    }
+}
 
-    match read_file_contents(&filename) {
-        Ok(contents) => {
-            println!("Downloaded file: \"{}\"", filename);
-            HttpResponse::Ok().content_type("text/plain").body(contents)
+/// Whether a directory listing is wanted as HTML rather than as JSON.
+fn wants_html(req: &HttpRequest, format: Option<&str>) -> bool {
+    match format {
+        Some(format) => format == "html",
+        None => req
+            .headers()
+            .get("accept")
+            .and_then(|v| v.to_str().ok())
+            .map_or(false, |accept| accept.contains("text/html")),
+    }
+}
+
+fn list_directory(
+    req: &HttpRequest,
+    format: Option<&str>,
+    dirname: &str,
+    storage: &Storage,
+) -> HttpResponse {
+    match read_listing(storage, dirname) {
+        Ok(entries) => {
+            println!(
+                "Listed directory: \"{}\" ({} entries)",
+                dirname,
+                entries.len()
+            );
+            if wants_html(req, format) {
+                HttpResponse::Ok()
+                    .content_type("text/html; charset=utf-8")
+                    .body(to_html(dirname, &entries))
+            } else {
+                HttpResponse::Ok().json(entries)
+            }
         }
         Err(error) => {
-            println!("Failed to read file: \"{}\" -> {}", filename, error);
+            println!("Failed to list directory: \"{}\" -> {}", dirname, error);
             HttpResponse::NotFound().finish()
         }
     }
 }
 
+fn download_file(
+    req: HttpRequest,
+    info: Path<(String,)>,
+    query: web::Query<ListingOptions>,
+    auth: Authorized<CanRead>,
+    state: web::Data<AppState>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let filename = &info.0;
+    print!(
+        "Downloading file: \"{}\" by {} ... ",
+        filename,
+        auth.username()
+    );
+    flust_stdout();
+
+    // A directory, including the root, is answered with the list of its entries.
+    let format = query.format.as_ref().map(String::as_str);
+    if filename.trim_end_matches('/').is_empty() {
+        return Either::A(ok(list_directory(&req, format, "", auth.storage())));
+    }
+    let path = match auth.storage().resolve(filename) {
+        Ok(path) => path,
+        Err(msg) => return Either::A(ok(invalid_path(&msg))),
+    };
+    if path.is_dir() {
+        return Either::A(ok(list_directory(&req, format, filename, auth.storage())));
+    }
+    // Only the first bytes are read here, to recognize the type of the contents.
+    let open_file = |path: &std::path::Path| -> std::io::Result<(File, Metadata, &'static str)> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let mut head = vec![0; media_type::SNIFF_LEN];
+        let head_len = file.read(&mut head)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok((file, metadata, media_type::detect(path, &head[..head_len])))
+    };
+    let (file, metadata, content_type) = match open_file(&path) {
+        Ok(opened) => opened,
+        Err(error) => {
+            println!("Failed to read file: \"{}\" -> {}", filename, error);
+            return Either::A(ok(HttpResponse::NotFound().finish()));
+        }
+    };
+
+    let len = metadata.len();
+    let validators = Validators::of(&metadata);
+    if validators.is_not_modified(&req) {
+        println!("Not modified: \"{}\"", filename);
+        return Either::A(ok(HttpResponse::NotModified()
+            .header("etag", validators.etag.clone())
+            .header("last-modified", validators.last_modified_header())
+            .finish()));
+    }
+    let range_request = match req.headers().get("range").and_then(|v| v.to_str().ok()) {
+        Some(range) if validators.is_range_allowed(&req) => parse_range(range, len),
+        _ => RangeRequest::Full,
+    };
+    let (status, content_type, content_range, chunks) = match range_request {
+        RangeRequest::Full => (
+            StatusCode::OK,
+            content_type.to_string(),
+            None,
+            FileChunks::new(file, len),
+        ),
+        RangeRequest::Partial(ranges) if ranges.len() == 1 => (
+            StatusCode::PARTIAL_CONTENT,
+            content_type.to_string(),
+            Some(ranges[0].content_range(len)),
+            FileChunks::with_segments(
+                file,
+                vec![Segment::File {
+                    start: ranges[0].first,
+                    len: ranges[0].size(),
+                }],
+            ),
+        ),
+        RangeRequest::Partial(ranges) => {
+            let boundary: String = rand::thread_rng()
+                .sample_iter(&rand::distributions::Alphanumeric)
+                .take(24)
+                .collect();
+            (
+                StatusCode::PARTIAL_CONTENT,
+                format!("multipart/byteranges; boundary={}", boundary),
+                None,
+                FileChunks::with_segments(
+                    file,
+                    multipart_segments(&ranges, len, content_type, &boundary),
+                ),
+            )
+        }
+        RangeRequest::Unsatisfiable => {
+            println!("Unsatisfiable range for file: \"{}\"", filename);
+            return Either::A(ok(HttpResponse::RangeNotSatisfiable()
+                .header("content-range", format!("bytes */{}", len))
+                .finish()));
+        }
+    };
+
+    println!("Downloaded file: \"{}\" ({})", filename, status);
+    let mut response = HttpResponse::build(status);
+    response
+        .content_type(content_type)
+        .header("accept-ranges", "bytes")
+        .header("etag", validators.etag.clone())
+        .header("last-modified", validators.last_modified_header());
+    if let Some(content_range) = content_range {
+        response.header("content-range", content_range);
+    }
+    let body = SizedStream::new(chunks.body_len() as _, chunks);
+    // The digest is of the whole file, even when only a range is sent.
+    if let Some(sha256) = state.digests.cached(&path, &validators.etag) {
+        response.header("digest", digest_header(&sha256));
+        return Either::A(ok(response.body(body)));
+    }
+    // Hashing reads the whole file, so it is done on the thread pool.
+    let filename = filename.clone();
+    let etag = validators.etag.clone();
+    Either::B(web::block(move || state.digests.get(&path, &etag)).then(
+        move |result| -> Result<_, Error> {
+            match result {
+                Ok(sha256) => {
+                    response.header("digest", digest_header(&sha256));
+                }
+                Err(error) => println!("Failed to hash file: \"{}\" -> {}", filename, error),
+            }
+            Ok(response.body(body))
+        },
+    ))
+}
+
+/// The most bytes an upload may write: the size limit, or the space left in
+/// the quota of the account, counting as free the bytes of the replaced file.
+/// With a quota, the space is reserved until the returned reservation is
+/// dropped: as much as the declared size of the body, or else the size limit.
+fn upload_limit<A: RequiredAccess>(
+    req: &HttpRequest,
+    auth: &Authorized<A>,
+    max_upload_size: u64,
+    replaced_len: u64,
+) -> (UploadLimit, Option<Reservation>) {
+    let size_limit = UploadLimit::Size(max_upload_size);
+    match auth.account().and_then(|account| account.quota.as_ref()) {
+        Some(quota) => {
+            let wanted =
+                declared_size(req).map_or(max_upload_size, |size| size.min(max_upload_size));
+            let reservation = quota.reserve(wanted, replaced_len);
+            let limit = size_limit.min(UploadLimit::Quota(reservation.bytes()));
+            (limit, Some(reservation))
+        }
+        None => (size_limit, None),
+    }
+}
+
+/// The size of the complete upload at `temp_path`, checked again against
+/// the space reserved for it, since only the received bytes were counted.
+fn written_size(
+    temp_path: &std::path::Path,
+    reservation: Option<&Reservation>,
+) -> Result<u64, Error> {
+    let len = std::fs::metadata(temp_path)?.len();
+    match reservation {
+        Some(reservation) if len > reservation.bytes() => {
+            Err(UploadLimit::Quota(reservation.bytes()).exceeded())
+        }
+        _ => Ok(len),
+    }
+}
+
+/// The hasher of an upload, checking the digests the client declared,
+/// or a 400 Bad Request response if they are invalid.
+fn upload_hasher(req: &HttpRequest, filename: &str) -> Result<UploadHasher, HttpResponse> {
+    match ExpectedDigests::from_request(req) {
+        Ok(expected) => Ok(UploadHasher::new(expected)),
+        Err(msg) => {
+            println!("Rejected file: \"{}\" -> {}", filename, msg);
+            Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body(msg))
+        }
+    }
+}
+
+/// Makes an uploaded file share its contents with the identical ones,
+/// if deduplicating, and remembers its digest.
+/// Returns the entity tag of the stored file.
+fn record_upload(state: &AppState, path: &std::path::Path, written: &Written) -> Option<String> {
+    if let Some(blobs) = &state.blobs {
+        if let Err(error) = blobs.share(path, &written.sha256) {
+            println!("Failed to deduplicate: \"{}\" -> {}", path.display(), error);
+        }
+    }
+    let etag = Validators::of(&std::fs::metadata(path).ok()?).etag;
+    state.digests.insert(path, &etag, written.sha256);
+    Some(etag)
+}
+
+/// Creates the hidden file an upload to `path` is written to.
+fn create_temp_file(path: &std::path::Path) -> std::io::Result<(File, std::path::PathBuf)> {
+    let temp_path = temp_sibling(path);
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)?;
+    Ok((file, temp_path))
+}
+
 fn upload_specified_file(
+    req: HttpRequest,
     payload: web::Payload,
     info: Path<(String,)>,
+    auth: Authorized<CanWrite>,
+    state: web::Data<AppState>,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     let filename = info.0.clone();
-    print!("Uploading file: \"{}\" ... ", filename);
+    print!(
+        "Uploading file: \"{}\" by {} ... ",
+        filename,
+        auth.username()
+    );
     flust_stdout();
 
-    // get the content async-ly from the client
-    payload
-        .map_err(Error::from)
-        .fold(web::BytesMut::new(), move |mut body, chunk| {
-            body.extend_from_slice(&chunk);
-            Ok::<_, Error>(body)
-        })
-        .and_then(move |contents| {
-            let f = File::create(&filename);
-            if f.is_err() {
-                println!("Failed to create file: \"{}\"", filename);
-                return ok(HttpResponse::NotFound().into());
-            }
+    // An invalid name or a too large body is rejected before writing anything.
+    let path = match auth.storage().resolve(&filename) {
+        Ok(path) => path,
+        Err(msg) => return Either::A(ok(invalid_path(&msg))),
+    };
+    if path.is_dir() {
+        println!("Rejected file: \"{}\" -> it is a directory", filename);
+        return Either::A(ok(HttpResponse::Conflict()
+            .content_type("text/plain")
+            .body("A directory with this name already exists")));
+    }
+    // Held until the upload ends, however it ends.
+    let lock = match state.locks.try_lock(&path) {
+        Some(lock) => lock,
+        None => return Either::A(ok(being_changed(&filename))),
+    };
+    if !is_change_allowed(&req, current_validators(&path).as_ref()) {
+        return Either::A(ok(precondition_failed(&filename)));
+    }
+    let replaced_len = path.metadata().map_or(0, |m| m.len());
+    let (limit, reservation) = upload_limit(&req, &auth, state.max_upload_size, replaced_len);
+    if let Err(error) = check_content_length(&req, limit) {
+        println!("Rejected file: \"{}\" -> {}", filename, error);
+        return Either::A(err(error));
+    }
+    let hasher = match upload_hasher(&req, &filename) {
+        Ok(hasher) => hasher,
+        Err(response) => return Either::A(ok(response)),
+    };
+    // The file is replaced only once the upload is complete, so it always has
+    // either its previous contents or its new ones; this also keeps intact
+    // the blob it may share.
+    let (file, temp_path) = match create_temp_file(&path) {
+        Ok(created) => created,
+        Err(error) => return Either::A(ok(create_failure(&filename, &error))),
+    };
 
-            if f.unwrap().write_all(&contents).is_err() {
-                println!("Failed to create file: \"{}\"", filename);
-                return ok(HttpResponse::NotFound().into());
-            }
-            println!("Uploaded file: \"{}\"", filename);
-            ok(HttpResponse::Ok().finish())
-        })
+    // the content is written as it arrives from the client
+    Either::B(
+        write_payload(payload, file, temp_path.clone(), limit, hasher).and_then(
+            move |written| -> Result<_, Error> {
+                let stored_len = match written_size(&temp_path, reservation.as_ref()) {
+                    Ok(len) => len,
+                    Err(error) => {
+                        println!("Rejected file: \"{}\" -> {}", filename, error);
+                        let _ = std::fs::remove_file(&temp_path);
+                        return Err(error);
+                    }
+                };
+                if let Err(error) = std::fs::rename(&temp_path, &path) {
+                    println!("Failed to replace file: \"{}\" -> {}", filename, error);
+                    let _ = std::fs::remove_file(&temp_path);
+                    return Err(error.into());
+                }
+                if let Some(reservation) = reservation {
+                    reservation.commit(stored_len);
+                }
+                println!("Uploaded file: \"{}\" ({} bytes)", filename, written.len);
+                let etag = record_upload(&state, &path, &written);
+                drop(lock);
+                let mut response = HttpResponse::Ok();
+                response.header("digest", digest_header(&written.sha256));
+                if let Some(etag) = etag {
+                    response.header("etag", etag);
+                }
+                Ok(response.finish())
+            },
+        ),
+    )
+}
+
+/// The name of a new file posted to `requested`: a unique id is appended to
+/// the last segment, before its extension, so that "reports/data.csv"
+/// becomes like "reports/data-67e55044-10b1-426f-9247-bb680e5fe0c8.csv".
+fn new_file_name(requested: &str) -> String {
+    let (dir, last) = match requested.rfind('/') {
+        Some(slash) => requested.split_at(slash + 1),
+        None => ("", requested),
+    };
+    let is_extension = |e: &str| e.len() <= 16 && e.chars().all(|c| c.is_ascii_alphanumeric());
+    let (stem, extension) = match last.rfind('.') {
+        Some(dot) if dot > 0 && dot + 1 < last.len() && is_extension(&last[dot + 1..]) => {
+            last.split_at(dot)
+        }
+        _ => (last, ""),
+    };
+    let separator = if stem.is_empty() { "" } else { "-" };
+    format!(
+        "{}{}{}{}{}",
+        dir,
+        stem,
+        separator,
+        Uuid::new_v4(),
+        extension
+    )
 }
 
 fn upload_new_file(
+    req: HttpRequest,
     payload: web::Payload,
     info: Path<(String,)>,
+    auth: Authorized<CanWrite>,
+    state: web::Data<AppState>,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
-    let filename_prefix = info.0.clone();
-    print!("Uploading file: \"{}\" ... ", filename_prefix);
+    let requested_name = info.0.clone();
+    print!(
+        "Uploading new file: \"{}\" by {} ... ",
+        requested_name,
+        auth.username()
+    );
     flust_stdout();
 
-    // get the content async-ly from the client
-    payload
-        .map_err(Error::from)
-        .fold(web::BytesMut::new(), move |mut body, chunk| {
-            body.extend_from_slice(&chunk);
-            Ok::<_, Error>(body)
-        })
-        .and_then(move |contents| {
-            let mut rng = rand::thread_rng();
-            let mut attemps = 0;
-            let mut file;
-            let mut filename;
-            const MAX_ATTEMPS: u32 = 100;
-
-            loop {
-                attemps += 1;
-                if attemps > MAX_ATTEMPS {
-                    println!(
-                        "failed to create new file with prefix: \"{}\" after {} attemps.",
-                        filename_prefix, MAX_ATTEMPS
-                    );
-                    return ok(HttpResponse::NotFound().into());
-                }
+    // A too large body is rejected before writing anything.
+    let (limit, reservation) = upload_limit(&req, &auth, state.max_upload_size, 0);
+    if let Err(error) = check_content_length(&req, limit) {
+        println!("Rejected file: \"{}\" -> {}", requested_name, error);
+        return Either::A(err(error));
+    }
+    let hasher = match upload_hasher(&req, &requested_name) {
+        Ok(hasher) => hasher,
+        Err(response) => return Either::A(ok(response)),
+    };
 
-                filename = format!("{}{:03}.txt", filename_prefix, rng.gen_range(0, 1000));
-                file = OpenOptions::new()
-                    .write(true)
-                    .create_new(true)
-                    .open(&filename);
-                if file.is_ok() {
-                    break;
-                }
-            }
+    // The contents are written aside, and given a name only once complete.
+    let (file, temp_path) = match auth
+        .storage()
+        .resolve(&new_file_name(&requested_name))
+        .map(|path| create_temp_file(&path))
+    {
+        Ok(Ok(created)) => created,
+        Ok(Err(error)) => return Either::A(ok(create_failure(&requested_name, &error))),
+        Err(msg) => return Either::A(ok(invalid_path(&msg))),
+    };
 
-            if file.unwrap().write_all(&contents).is_err() {
-                println!("Failed to write file: \"{}\"", filename);
-                return ok(HttpResponse::NotFound().into());
-            }
+    // the content is written as it arrives from the client
+    Either::B(
+        write_payload(payload, file, temp_path.clone(), limit, hasher).and_then(
+            move |written| -> Result<_, Error> {
+                let stored_len = match written_size(&temp_path, reservation.as_ref()) {
+                    Ok(len) => len,
+                    Err(error) => {
+                        println!("Rejected file: \"{}\" -> {}", requested_name, error);
+                        let _ = std::fs::remove_file(&temp_path);
+                        return Err(error);
+                    }
+                };
+                let published = publish_new_file(&temp_path, &requested_name, auth.storage());
+                let _ = std::fs::remove_file(&temp_path);
+                let (filename, path) = match published {
+                    Ok(published) => published,
+                    Err(response) => return Ok(response),
+                };
+                if let Some(reservation) = reservation {
+                    reservation.commit(stored_len);
+                }
+                println!("Uploaded file: \"{}\" ({} bytes)", filename, written.len);
+                let etag = record_upload(&state, &path, &written);
+                let mut response = HttpResponse::Created();
+                response
+                    .header("location", href(&filename, false))
+                    .header("digest", digest_header(&written.sha256));
+                if let Some(etag) = etag {
+                    response.header("etag", etag);
+                }
+                Ok(response.content_type("text/plain").body(filename))
+            },
+        ),
+    )
+}
 
-            println!("Uploaded file: \"{}\"", filename);
-            ok(HttpResponse::Ok().content_type("text/plain").body(filename))
-        })
+/// Gives the complete temporary file of a new upload a new name,
+/// never replacing an existing file. Returns the name and the path.
+fn publish_new_file(
+    temp_path: &std::path::Path,
+    requested_name: &str,
+    storage: &Storage,
+) -> Result<(String, std::path::PathBuf), HttpResponse> {
+    // A random UUID is practically never repeated,
+    // so a few attempts are enough however many files there are.
+    const MAX_ATTEMPTS: u32 = 3;
+    for _ in 0..MAX_ATTEMPTS {
+        let filename = new_file_name(requested_name);
+        let path = storage
+            .resolve(&filename)
+            .map_err(|msg| invalid_path(&msg))?;
+        // Unlike a rename, a hard link fails if the name is taken.
+        match std::fs::hard_link(temp_path, &path) {
+            Ok(_) => return Ok((filename, path)),
+            Err(ref error) if error.kind() == ErrorKind::AlreadyExists => {}
+            Err(error) => return Err(create_failure(&filename, &error)),
+        }
+    }
+    println!(
+        "Failed to create new file: \"{}\" after {} attempts",
+        requested_name, MAX_ATTEMPTS
+    );
+    Err(HttpResponse::Conflict()
+        .content_type("text/plain")
+        .body("Cannot find a free name for the new file"))
 }
 
 fn invalid_resource(req: HttpRequest) -> impl Responder {
     println!("Invalid URI: \"{}\"", req.uri());
     HttpResponse::NotFound()
 }
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/{path:.*}")
+            .route(web::delete().to(delete_file))
+            .route(web::get().to_async(download_file))
+            .route(web::head().to_async(download_file))
+            .route(web::put().to_async(upload_specified_file))
+            .route(web::post().to_async(upload_new_file))
+            .route(web::method(Method::from_bytes(b"MKCOL").unwrap()).to(make_directory)),
+    );
+}
+
 fn main() -> std::io::Result<()> {
-    let server_addr = "127.0.0.1:8080";
-    println!("Listening at addres: {} ...", server_addr);
-    HttpServer::new(|| {
+    let config = Config::load("FILE_TRANSFER")?;
+    config.init_logger();
+    // The first argument is kept as a shorthand of the storage root.
+    let storage_root = config
+        .args
+        .first()
+        .or_else(|| config.storage.root.as_ref())
+        .map_or(".", String::as_str);
+    let max_upload_size = config
+        .storage
+        .max_upload_size
+        .unwrap_or(DEFAULT_MAX_UPLOAD_SIZE);
+    let storage = Storage::open(storage_root)?;
+    // Before the usage of the quotas is counted.
+    let removed = storage.remove_temp_files()?;
+    if removed > 0 {
+        println!("Removed {} interrupted uploads", removed);
+    }
+    let accounts = match &config.storage.users_file {
+        Some(users_file) => {
+            println!("Authenticating the users of: {}", users_file);
+            Some(Accounts::load(users_file, &storage)?)
+        }
+        None => None,
+    };
+    let blobs = match &config.storage.blob_dir {
+        Some(blob_dir) => {
+            let blobs = BlobStore::open(blob_dir)?;
+            // Else the blobs could be reached, and changed, as stored files.
+            if blobs.dir().starts_with(storage.root()) {
+                return Err(config
+                    .invalid("storage.blob_dir", "must be outside the storage root")
+                    .into());
+            }
+            let removed = blobs.collect_garbage()?;
+            println!(
+                "Sharing identical uploads in: {} ({} unused blobs removed)",
+                blobs.dir().display(),
+                removed
+            );
+            Some(blobs)
+        }
+        None => None,
+    };
+    let state = web::Data::new(AppState {
+        storage,
+        max_upload_size,
+        accounts,
+        digests: DigestCache::default(),
+        blobs,
+        locks: PathLocks::default(),
+    });
+    println!("Storing files in: {}", state.storage.root().display());
+    println!("Accepting uploads up to {} bytes", max_upload_size);
+
+    let server_addr = config.server.bind_address.clone();
+    let mut server = HttpServer::new(move || {
         App::new()
-            .service(
-                web::resource("/{filename}")
-                    .route(web::delete().to(delete_file))
-                    .route(web::get().to(download_file))
-                    .route(web::put().to_async(upload_specified_file))
-                    .route(web::post().to_async(upload_new_file)),
-            )
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
+            .register_data(state.clone())
+            .configure(routes)
             .default_service(web::route().to(invalid_resource))
-    })
-    .bind(server_addr)?
-    .run()
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&server_addr, acceptor)?,
+        None => server.bind(&server_addr)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&server_addr)?;
+    println!(
+        "Listening at address: {}://{} ...",
+        config.scheme(),
+        server_addr
+    );
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self, TestRequest};
+
+    /// A storage in a new directory named after the test, holding
+    /// "datafile.txt", with "secret.txt" next to it outside the root.
+    fn test_storage(name: &str) -> Storage {
+        let dir =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("secret.txt"), "Outside the root.").unwrap();
+        let storage = Storage::open(dir.join("root")).unwrap();
+        std::fs::write(storage.root().join("datafile.txt"), "File contents.").unwrap();
+        storage
+    }
+
+    fn state_of(
+        storage: Storage,
+        max_upload_size: u64,
+        accounts: Option<Accounts>,
+    ) -> web::Data<AppState> {
+        web::Data::new(AppState {
+            storage,
+            max_upload_size,
+            accounts,
+            digests: DigestCache::default(),
+            blobs: None,
+            locks: PathLocks::default(),
+        })
+    }
+
+    /// The state of a server without accounts, storing the files in
+    /// a new directory named after the test.
+    fn test_state(name: &str) -> web::Data<AppState> {
+        state_of(test_storage(name), DEFAULT_MAX_UPLOAD_SIZE, None)
+    }
+
+    /// The names in a directory, sorted.
+    fn names_in(dir: &std::path::Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn status_of(state: &web::Data<AppState>, request: TestRequest) -> StatusCode {
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        test::call_service(&mut app, request.to_request()).status()
+    }
+
+    #[test]
+    fn rejects_paths_leading_outside_the_root() {
+        let state = test_state("handler_paths");
+        assert_eq!(
+            status_of(&state, TestRequest::get().uri("/datafile.txt")),
+            StatusCode::OK
+        );
+        for uri in &[
+            "/../secret.txt",
+            "/%2e%2e/secret.txt",
+            "/a/%2E%2E/%2e%2e/secret.txt",
+        ] {
+            assert_eq!(
+                status_of(&state, TestRequest::get().uri(uri)),
+                StatusCode::BAD_REQUEST,
+                "{}",
+                uri
+            );
+            assert_eq!(
+                status_of(&state, TestRequest::put().uri(uri).set_payload("Changed.")),
+                StatusCode::BAD_REQUEST,
+                "{}",
+                uri
+            );
+        }
+        let secret = state.storage.root().parent().unwrap().join("secret.txt");
+        assert_eq!(
+            std::fs::read_to_string(secret).unwrap(),
+            "Outside the root."
+        );
+    }
+
+    #[test]
+    fn answers_ranges_of_the_file() {
+        let state = test_state("handler_ranges");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let request = |range: &str| {
+            TestRequest::get()
+                .uri("/datafile.txt")
+                .header("range", range)
+                .to_request()
+        };
+
+        let response = test::call_service(&mut app, request("bytes=5-"));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get("content-range").unwrap(),
+            "bytes 5-13/14"
+        );
+        assert_eq!(
+            test::read_response(&mut app, request("bytes=-9")),
+            "contents."
+        );
+
+        let response = test::call_service(&mut app, request("bytes=14-"));
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers().get("content-range").unwrap(),
+            "bytes */14"
+        );
+    }
+
+    #[test]
+    fn rejects_uploads_beyond_the_size_limit() {
+        let state = state_of(test_storage("handler_size_limit"), 10, None);
+        let put = |contents: &'static str| TestRequest::put().uri("/a.txt").set_payload(contents);
+        assert_eq!(
+            status_of(&state, put("Eleven byte")),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        // A declared size beyond the limit is rejected before reading the body.
+        assert_eq!(
+            status_of(&state, put("Ten bytes.").header("content-length", "11")),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(names_in(state.storage.root()), vec!["datafile.txt"]);
+
+        assert_eq!(status_of(&state, put("Ten bytes.")), StatusCode::OK);
+        let uploaded = std::fs::read_to_string(state.storage.root().join("a.txt")).unwrap();
+        assert_eq!(uploaded, "Ten bytes.");
+    }
+
+    #[test]
+    fn answers_not_modified_to_a_current_copy() {
+        let state = test_state("handler_not_modified");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let request = |headers: &[(&str, &str)]| {
+            headers
+                .iter()
+                .fold(
+                    TestRequest::get().uri("/datafile.txt"),
+                    |req, (name, value)| req.header(*name, *value),
+                )
+                .to_request()
+        };
+        let response = test::call_service(&mut app, request(&[]));
+        let header = |name: &str| response.headers().get(name).unwrap().to_str().unwrap();
+        let (etag, last_modified) = (
+            header("etag").to_string(),
+            header("last-modified").to_string(),
+        );
+        assert!(etag.starts_with("\"e-"), "{}", etag);
+
+        let response = test::call_service(&mut app, request(&[("if-none-match", &etag)]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get("etag").unwrap(), etag.as_str());
+        let body = test::read_response(&mut app, request(&[("if-none-match", &etag)]));
+        assert!(body.is_empty());
+        let response = test::call_service(&mut app, request(&[("if-none-match", "\"other\"")]));
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let since = [("if-modified-since", last_modified.as_str())];
+        let response = test::call_service(&mut app, request(&since));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let since = [("if-modified-since", "Thu, 01 Jan 1970 00:00:00 GMT")];
+        let response = test::call_service(&mut app, request(&since));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn answers_several_ranges_as_multipart() {
+        let state = test_state("handler_multipart");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let request = || {
+            TestRequest::get()
+                .uri("/datafile.txt")
+                .header("range", "bytes=0-3,5-12")
+                .to_request()
+        };
+        let response = test::call_service(&mut app, request());
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response.headers().get("content-type").unwrap();
+        assert!(content_type
+            .to_str()
+            .unwrap()
+            .starts_with("multipart/byteranges; boundary="));
+
+        // Each response has its own boundary.
+        let body = test::read_response(&mut app, request());
+        let body = std::str::from_utf8(&body).unwrap();
+        let boundary = &body[4..body.find("\r\nContent-Type").unwrap()];
+        let part = |range: &str, contents: &str| {
+            format!(
+                "\r\n--{}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+                 Content-Range: bytes {}/14\r\n\r\n{}",
+                boundary, range, contents
+            )
+        };
+        let expected = part("0-3", "File") + &part("5-12", "contents");
+        assert_eq!(body, expected + "\r\n--" + boundary + "--\r\n");
+    }
+
+    #[test]
+    fn sends_the_whole_file_for_another_version() {
+        let state = test_state("handler_if_range");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let request = |if_range: &str| {
+            TestRequest::get()
+                .uri("/datafile.txt")
+                .header("range", "bytes=0-3")
+                .header("if-range", if_range)
+                .to_request()
+        };
+        let datafile = state.storage.root().join("datafile.txt");
+        let etag = Validators::of(&std::fs::metadata(datafile).unwrap()).etag;
+
+        let response = test::call_service(&mut app, request(&etag));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(test::read_response(&mut app, request(&etag)), "File");
+        for other in &["\"other\"", "Thu, 01 Jan 1970 00:00:00 GMT"] {
+            let response = test::call_service(&mut app, request(other));
+            assert_eq!(response.status(), StatusCode::OK, "{}", other);
+            assert_eq!(
+                test::read_response(&mut app, request(other)),
+                "File contents."
+            );
+        }
+    }
+
+    #[test]
+    fn makes_lists_and_deletes_directories() {
+        let state = test_state("handler_directories");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let mkcol = |uri: &str| {
+            TestRequest::with_uri(uri)
+                .method(Method::from_bytes(b"MKCOL").unwrap())
+                .to_request()
+        };
+        assert_eq!(
+            test::call_service(&mut app, mkcol("/dir")).status(),
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            test::call_service(&mut app, mkcol("/dir/")).status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(
+            test::call_service(&mut app, mkcol("/missing/dir")).status(),
+            StatusCode::CONFLICT
+        );
+        let request = TestRequest::put()
+            .uri("/dir/a%20b.txt")
+            .set_payload("Nested.")
+            .to_request();
+        assert_eq!(
+            test::call_service(&mut app, request).status(),
+            StatusCode::OK
+        );
+
+        let request = TestRequest::get().uri("/dir/").to_request();
+        let body = test::read_response(&mut app, request);
+        let json = std::str::from_utf8(&body).unwrap();
+        assert!(
+            json.starts_with("[{\"name\":\"a b.txt\",\"is_dir\":false,\"size\":7,"),
+            "{}",
+            json
+        );
+        let request = TestRequest::get()
+            .uri("/")
+            .header("accept", "text/html")
+            .to_request();
+        let body = test::read_response(&mut app, request);
+        let html = std::str::from_utf8(&body).unwrap();
+        assert!(html.contains("<a href=\"/dir/\">dir/</a>"), "{}", html);
+        let request = TestRequest::get().uri("/dir?format=html").to_request();
+        let body = test::read_response(&mut app, request);
+        let html = std::str::from_utf8(&body).unwrap();
+        assert!(
+            html.contains("<a href=\"/dir/a%20b.txt\">a b.txt</a>"),
+            "{}",
+            html
+        );
+
+        let dir = state.storage.root().join("dir");
+        let request = TestRequest::delete().uri("/dir").to_request();
+        assert_eq!(
+            test::call_service(&mut app, request).status(),
+            StatusCode::CONFLICT
+        );
+        assert!(dir.join("a b.txt").exists());
+        let request = TestRequest::delete()
+            .uri("/dir?recursive=true")
+            .to_request();
+        assert_eq!(
+            test::call_service(&mut app, request).status(),
+            StatusCode::OK
+        );
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn names_the_new_files_uniquely() {
+        let uuid_len = Uuid::nil().to_string().len();
+        let named = |requested: &str, prefix: &str, suffix: &str| {
+            let name = new_file_name(requested);
+            assert!(name.starts_with(prefix), "{} -> {}", requested, name);
+            assert!(name.ends_with(suffix), "{} -> {}", requested, name);
+            let id = &name[prefix.len()..name.len() - suffix.len()];
+            assert!(Uuid::parse_str(id).is_ok(), "{} -> {}", requested, name);
+            assert_eq!(id.len(), uuid_len);
+            name
+        };
+        assert_ne!(
+            named("data.csv", "data-", ".csv"),
+            named("data.csv", "data-", ".csv")
+        );
+        named("reports/data.csv", "reports/data-", ".csv");
+        named("reports/", "reports/", "");
+        named("", "", "");
+        named(".profile", ".profile-", "");
+        named("archive.tar.gz", "archive.tar-", ".gz");
+        named("notes.", "notes.-", "");
+        named("a.b c", "a.b c-", "");
+        named("v1.0/data", "v1.0/data-", "");
+    }
+
+    #[test]
+    fn posts_a_new_file_under_a_unique_name() {
+        let state = test_state("handler_post");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let request = TestRequest::post()
+            .uri("/data.txt")
+            .set_payload("New contents.")
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let location = response.headers().get("location").unwrap().clone();
+        let body = test::read_body(response);
+        let filename = std::str::from_utf8(&body).unwrap();
+        assert!(filename.starts_with("data-") && filename.ends_with(".txt"));
+        assert_eq!(location, href(filename, false).as_str());
+        assert_eq!(
+            std::fs::read_to_string(state.storage.root().join(filename)).unwrap(),
+            "New contents."
+        );
+        assert!(!state.storage.root().join("data.txt").exists());
+    }
+
+    #[test]
+    fn answers_not_found_to_the_deletion_of_a_missing_file() {
+        let state = test_state("handler_delete");
+        assert_eq!(
+            status_of(&state, TestRequest::delete().uri("/missing.txt")),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status_of(&state, TestRequest::delete().uri("/datafile.txt")),
+            StatusCode::OK
+        );
+        assert!(!state.storage.root().join("datafile.txt").exists());
+    }
+
+    #[test]
+    fn keeps_the_uploads_within_the_quota() {
+        let storage = test_storage("handler_quota");
+        let users_file = storage.root().parent().unwrap().join("users.txt");
+        std::fs::write(
+            &users_file,
+            "alice:secret:read-write:10\nbob:other:read-only\n",
+        )
+        .unwrap();
+        let accounts = Accounts::load(users_file.to_str().unwrap(), &storage).unwrap();
+        let state = state_of(storage, DEFAULT_MAX_UPLOAD_SIZE, Some(accounts));
+        let put = |credentials: &str, uri: &str| {
+            TestRequest::put()
+                .uri(uri)
+                .header(
+                    "authorization",
+                    format!("Basic {}", base64::encode(credentials)),
+                )
+                .set_payload("Six b.")
+        };
+        let home = state.storage.root().join("alice");
+
+        assert_eq!(
+            status_of(
+                &state,
+                TestRequest::put().uri("/a.txt").set_payload("Six b.")
+            ),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status_of(&state, put("bob:other", "/a.txt")),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status_of(&state, put("alice:secret", "/a.txt")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status_of(&state, put("alice:secret", "/b.txt")),
+            StatusCode::INSUFFICIENT_STORAGE
+        );
+        assert_eq!(names_in(&home), vec!["a.txt"]);
+        // Replacing a file counts its size as free.
+        assert_eq!(
+            status_of(&state, put("alice:secret", "/a.txt")),
+            StatusCode::OK
+        );
+
+        let delete = TestRequest::delete().uri("/a.txt").header(
+            "authorization",
+            format!("Basic {}", base64::encode("alice:secret")),
+        );
+        assert_eq!(status_of(&state, delete), StatusCode::OK);
+        assert_eq!(
+            status_of(&state, put("alice:secret", "/b.txt")),
+            StatusCode::OK
+        );
+        assert_eq!(names_in(&home), vec!["b.txt"]);
+    }
+
+    #[test]
+    fn checks_and_sends_the_digests() {
+        let state = test_state("handler_digests");
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+        let put = |md5: &str| {
+            TestRequest::put()
+                .uri("/new.txt")
+                .header("content-md5", md5)
+                .set_payload("File contents.")
+                .to_request()
+        };
+        let sha256 = "SHA-256=eQsVD/oX4izB5MVGpqIiVPhGrju/dESdyRpxYs/erLM=";
+        let path = state.storage.root().join("new.txt");
+
+        let response = test::call_service(&mut app, put("1B2M2Y8AsgTpgAmY7PhCfg=="));
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!path.exists());
+        assert_eq!(names_in(state.storage.root()), vec!["datafile.txt"]);
+        let response = test::call_service(&mut app, put("A/F26yyieRb5nmQlF1LWiQ=="));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("digest").unwrap(), sha256);
+
+        // Sent from the cache filled by the upload.
+        let request = TestRequest::get().uri("/new.txt").to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.headers().get("digest").unwrap(), sha256);
+        // Computed from the file, even for a range.
+        let request = TestRequest::get()
+            .uri("/datafile.txt")
+            .header("range", "bytes=0-3")
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers().get("digest").unwrap(), sha256);
+    }
+
+    #[test]
+    fn rejects_the_conflicting_changes() {
+        let state = test_state("handler_conflicts");
+        let datafile = state.storage.root().join("datafile.txt");
+        let put = || {
+            TestRequest::put()
+                .uri("/datafile.txt")
+                .set_payload("Changed.")
+        };
+        let delete = || TestRequest::delete().uri("/datafile.txt");
+
+        let lock = state.locks.try_lock(&datafile).unwrap();
+        assert_eq!(status_of(&state, put()), StatusCode::CONFLICT);
+        assert_eq!(status_of(&state, delete()), StatusCode::CONFLICT);
+        drop(lock);
+
+        for request in vec![put(), delete()] {
+            assert_eq!(
+                status_of(&state, request.header("if-match", "\"other\"")),
+                StatusCode::PRECONDITION_FAILED
+            );
+        }
+        assert_eq!(
+            status_of(&state, put().header("if-none-match", "*")),
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(
+            std::fs::read_to_string(&datafile).unwrap(),
+            "File contents."
+        );
+
+        let etag = Validators::of(&std::fs::metadata(&datafile).unwrap()).etag;
+        assert_eq!(
+            status_of(&state, put().header("if-match", etag.as_str())),
+            StatusCode::OK
+        );
+        assert_eq!(std::fs::read_to_string(&datafile).unwrap(), "Changed.");
+        let request = TestRequest::put()
+            .uri("/new.txt")
+            .header("if-none-match", "*")
+            .set_payload("New.");
+        assert_eq!(status_of(&state, request), StatusCode::OK);
+    }
 }
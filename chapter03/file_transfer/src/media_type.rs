@@ -0,0 +1,132 @@
+use std::path::Path;
+
+/// How many bytes at the start of a file are enough to recognize its type.
+pub const SNIFF_LEN: usize = 512;
+
+const BY_EXTENSION: [(&str, &str); 22] = [
+    ("txt", "text/plain; charset=utf-8"),
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "application/javascript"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("wasm", "application/wasm"),
+];
+
+const BY_MAGIC: [(&[u8], &str); 8] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF8", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"ID3", "audio/mpeg"),
+    (b"\0asm", "application/wasm"),
+];
+
+/// The media type of a file, from the extension of its name if known,
+/// otherwise from its first bytes.
+pub fn detect(path: &Path, head: &[u8]) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    if let Some(extension) = extension {
+        if let Some((_, media_type)) = BY_EXTENSION.iter().find(|(e, _)| *e == extension) {
+            return media_type;
+        }
+    }
+    if let Some((_, media_type)) = BY_MAGIC.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return media_type;
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    if is_text(head) {
+        "text/plain; charset=utf-8"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Whether the bytes look like UTF-8 text, allowing for a character
+/// cut at the end of the sample.
+fn is_text(head: &[u8]) -> bool {
+    let valid = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+    !valid
+        .chars()
+        .any(|c| c.is_control() && !c.is_ascii_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn detects_by_extension_first() {
+        assert_eq!(detect(Path::new("a/picture.PNG"), b""), "image/png");
+        assert_eq!(
+            detect(Path::new("notes.txt"), PNG),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            detect(Path::new("page.htm"), b""),
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn detects_by_contents_without_a_known_extension() {
+        assert_eq!(detect(Path::new("picture"), PNG), "image/png");
+        assert_eq!(
+            detect(Path::new("data.xyz"), b"%PDF-1.4\n"),
+            "application/pdf"
+        );
+        assert_eq!(
+            detect(Path::new("picture"), b"RIFF\x10\0\0\0WEBPVP8 "),
+            "image/webp"
+        );
+        assert_eq!(
+            detect(Path::new("readme"), "Café\r\n\tmenu".as_bytes()),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            detect(Path::new("data"), b"\0\x01\x02\x03"),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            detect(Path::new("data"), b"a\xffb"),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn accepts_a_character_cut_by_the_sample() {
+        let text = "Café".as_bytes();
+        assert!(is_text(&text[..text.len() - 1]));
+        assert!(!is_text(b"Caf\xc3("));
+    }
+}
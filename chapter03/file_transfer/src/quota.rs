@@ -0,0 +1,107 @@
+use std::sync::{Arc, Mutex};
+
+/// The most bytes a home directory may hold, with the bytes it holds kept
+/// in memory, so that an upload does not walk the directory: they are
+/// counted once at start, then updated by every change.
+pub struct Quota {
+    limit: u64,
+    usage: Arc<Mutex<Usage>>,
+}
+
+struct Usage {
+    stored: u64,
+    /// By the uploads in progress.
+    reserved: u64,
+}
+
+impl Quota {
+    pub fn new(limit: u64, stored: u64) -> Self {
+        Self {
+            limit,
+            usage: Arc::new(Mutex::new(Usage {
+                stored,
+                reserved: 0,
+            })),
+        }
+    }
+
+    /// Reserves for an upload up to `wanted` bytes of the space left, counting
+    /// as free the `replaced_len` bytes of the file it replaces. The size of
+    /// the reservation, maybe 0, is the most the upload may write.
+    pub fn reserve(&self, wanted: u64, replaced_len: u64) -> Reservation {
+        let mut usage = self.usage.lock().unwrap();
+        let left = (self.limit + replaced_len).saturating_sub(usage.stored + usage.reserved);
+        let bytes = wanted.min(left);
+        usage.reserved += bytes;
+        Reservation {
+            usage: self.usage.clone(),
+            bytes,
+            replaced_len,
+        }
+    }
+
+    /// Counts the bytes freed by a deletion.
+    pub fn release(&self, freed: u64) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.stored = usage.stored.saturating_sub(freed);
+    }
+}
+
+/// The space held for an upload in progress, given back when dropped.
+pub struct Reservation {
+    usage: Arc<Mutex<Usage>>,
+    bytes: u64,
+    replaced_len: u64,
+}
+
+impl Reservation {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Counts as stored the `len` bytes of the complete upload,
+    /// in place of those of the replaced file.
+    pub fn commit(self, len: u64) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.stored = usage.stored.saturating_sub(self.replaced_len) + len;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.usage.lock().unwrap().reserved -= self.bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserves_only_the_space_left() {
+        let quota = Quota::new(100, 40);
+        let first = quota.reserve(50, 0);
+        assert_eq!(first.bytes(), 50);
+        // The reserved bytes are not free until the upload ends.
+        let second = quota.reserve(50, 0);
+        assert_eq!(second.bytes(), 10);
+        drop(second);
+        assert_eq!(quota.reserve(50, 0).bytes(), 10);
+        // The replaced file is counted as free.
+        assert_eq!(quota.reserve(50, 20).bytes(), 30);
+
+        first.commit(30);
+        assert_eq!(quota.reserve(100, 0).bytes(), 30);
+        quota.release(60);
+        assert_eq!(quota.reserve(100, 0).bytes(), 90);
+    }
+
+    #[test]
+    fn counts_the_replaced_file_once_committed() {
+        let quota = Quota::new(100, 80);
+        let reservation = quota.reserve(100, 30);
+        assert_eq!(reservation.bytes(), 50);
+        reservation.commit(50);
+        assert_eq!(quota.reserve(100, 0).bytes(), 0);
+    }
+}
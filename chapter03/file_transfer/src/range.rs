@@ -0,0 +1,252 @@
+use actix_web::web;
+
+use crate::streaming::Segment;
+
+/// More ranges in a request are served as the whole file, since they can only
+/// come from a client trying to make the server work for nothing.
+const MAX_RANGES: usize = 32;
+
+/// A range of bytes of a file, including both ends.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ByteRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+impl ByteRange {
+    pub fn size(self) -> u64 {
+        self.last - self.first + 1
+    }
+
+    /// The value of the `Content-Range` header of this part.
+    pub fn content_range(self, file_len: u64) -> String {
+        format!("bytes {}-{}/{}", self.first, self.last, file_len)
+    }
+}
+
+/// What a `Range` header asks of a file.
+#[derive(PartialEq, Debug)]
+pub enum RangeRequest {
+    /// No usable range, so the whole file is sent.
+    Full,
+    /// The ranges to send, sorted and without overlaps.
+    Partial(Vec<ByteRange>),
+    /// No range overlaps the file, to be answered with 416.
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header such as "bytes=0-99,200-,-50" for a file of `file_len` bytes.
+/// An invalid header is ignored, as the HTTP specification requires.
+pub fn parse_range(header: &str, file_len: u64) -> RangeRequest {
+    let specs = match header.trim().strip_prefix("bytes=") {
+        Some(specs) => specs,
+        None => return RangeRequest::Full,
+    };
+    let mut ranges = vec![];
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (first, last) = match spec.find('-') {
+            Some(dash) => (spec[..dash].trim(), spec[dash + 1..].trim()),
+            None => return RangeRequest::Full,
+        };
+        let range = if first.is_empty() {
+            // The last bytes of the file.
+            match last.parse::<u64>() {
+                Ok(0) => None,
+                Ok(suffix_len) if file_len > 0 => Some(ByteRange {
+                    first: file_len.saturating_sub(suffix_len),
+                    last: file_len - 1,
+                }),
+                Ok(_) => None,
+                Err(_) => return RangeRequest::Full,
+            }
+        } else {
+            let first = match first.parse::<u64>() {
+                Ok(first) => first,
+                Err(_) => return RangeRequest::Full,
+            };
+            let last = if last.is_empty() {
+                u64::MAX
+            } else {
+                match last.parse::<u64>() {
+                    Ok(last) if last >= first => last,
+                    _ => return RangeRequest::Full,
+                }
+            };
+            if first < file_len {
+                Some(ByteRange {
+                    first,
+                    last: last.min(file_len - 1),
+                })
+            } else {
+                None
+            }
+        };
+        ranges.extend(range);
+    }
+    if specs.trim().is_empty() {
+        return RangeRequest::Full;
+    }
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+    if ranges.len() > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+    RangeRequest::Partial(coalesce(ranges))
+}
+
+/// Sorts the ranges, merging the overlapping or adjacent ones.
+fn coalesce(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.sort_by_key(|r| r.first);
+    let mut merged: Vec<ByteRange> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(previous) if range.first <= previous.last.saturating_add(1) => {
+                previous.last = previous.last.max(range.last);
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// The segments of a `multipart/byteranges` body sending the given ranges,
+/// each as a part with its own headers.
+pub fn multipart_segments(
+    ranges: &[ByteRange],
+    file_len: u64,
+    content_type: &str,
+    boundary: &str,
+) -> Vec<Segment> {
+    let mut segments = vec![];
+    for range in ranges {
+        segments.push(Segment::Bytes(web::Bytes::from(format!(
+            "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            boundary,
+            content_type,
+            range.content_range(file_len)
+        ))));
+        segments.push(Segment::File {
+            start: range.first,
+            len: range.size(),
+        });
+    }
+    segments.push(Segment::Bytes(web::Bytes::from(format!(
+        "\r\n--{}--\r\n",
+        boundary
+    ))));
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::FileChunks;
+    use futures::Stream;
+
+    fn partial(ranges: &[(u64, u64)]) -> RangeRequest {
+        RangeRequest::Partial(
+            ranges
+                .iter()
+                .map(|&(first, last)| ByteRange { first, last })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parses_open_ended_ranges() {
+        assert_eq!(parse_range("bytes=0-", 100), partial(&[(0, 99)]));
+        assert_eq!(parse_range("bytes=90-", 100), partial(&[(90, 99)]));
+        assert_eq!(parse_range("bytes=10-1000", 100), partial(&[(10, 99)]));
+        assert_eq!(parse_range(" bytes= 5 - 9 ", 100), partial(&[(5, 9)]));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range("bytes=-10", 100), partial(&[(90, 99)]));
+        assert_eq!(parse_range("bytes=-1000", 100), partial(&[(0, 99)]));
+        assert_eq!(parse_range("bytes=-0", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-10", 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn finds_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=100-", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(
+            parse_range("bytes=100-199,300-", 100),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(parse_range("bytes=0-", 0), RangeRequest::Unsatisfiable);
+        // The ranges which overlap the file are still sent.
+        assert_eq!(parse_range("bytes=100-199,0-9", 100), partial(&[(0, 9)]));
+    }
+
+    #[test]
+    fn ignores_invalid_headers() {
+        for header in &[
+            "",
+            "bytes=",
+            "items=0-9",
+            "bytes=9-0",
+            "bytes=a-9",
+            "bytes=0-9,x",
+            "bytes=0-9,-",
+        ] {
+            assert_eq!(parse_range(header, 100), RangeRequest::Full, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn merges_multiple_ranges() {
+        assert_eq!(
+            parse_range("bytes=50-59,0-9,-10", 100),
+            partial(&[(0, 9), (50, 59), (90, 99)])
+        );
+        assert_eq!(
+            parse_range("bytes=0-9,10-19,15-29", 100),
+            partial(&[(0, 29)])
+        );
+    }
+
+    #[test]
+    fn rejects_too_many_ranges() {
+        let ranges = |count: u64| {
+            let specs: Vec<String> = (0..count).map(|i| format!("{}-{}", i * 2, i * 2)).collect();
+            format!("bytes={}", specs.join(","))
+        };
+        match parse_range(&ranges(MAX_RANGES as u64), 1000) {
+            RangeRequest::Partial(parts) => assert_eq!(parts.len(), MAX_RANGES),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            parse_range(&ranges(MAX_RANGES as u64 + 1), 1000),
+            RangeRequest::Full
+        );
+    }
+
+    #[test]
+    fn sends_each_range_as_a_part() {
+        let path =
+            std::env::temp_dir().join(format!("file_transfer-{}-multipart", std::process::id()));
+        std::fs::write(&path, "File contents.").unwrap();
+        let ranges = [
+            ByteRange { first: 0, last: 3 },
+            ByteRange { first: 5, last: 12 },
+        ];
+        let segments = multipart_segments(&ranges, 14, "text/plain", "XYZ");
+        let chunks = FileChunks::with_segments(std::fs::File::open(&path).unwrap(), segments);
+        let part = |range: &str, contents: &str| {
+            format!(
+                "\r\n--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes {}/14\r\n\r\n{}",
+                range, contents
+            )
+        };
+        let expected = part("0-3", "File") + &part("5-12", "contents") + "\r\n--XYZ--\r\n";
+        assert_eq!(chunks.body_len(), expected.len() as u64);
+        let body: Vec<u8> = chunks
+            .wait()
+            .flat_map(|chunk| chunk.unwrap().to_vec())
+            .collect();
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::{Component, Path, PathBuf};
+
+/// The directory holding the served files; no request can reach outside it.
+pub struct Storage {
+    root: PathBuf,
+}
+
+impl Storage {
+    /// Uses the given directory as the root, creating it if missing.
+    pub fn open(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&root)?;
+        Ok(Self {
+            root: std::fs::canonicalize(root)?,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Removes the temporary files left by the uploads interrupted by a crash,
+    /// returning how many were removed.
+    pub fn remove_temp_files(&self) -> std::io::Result<usize> {
+        remove_temp_files(&self.root)
+    }
+
+    /// How many bytes the files under the root take, not following symbolic links.
+    pub fn used_bytes(&self) -> std::io::Result<u64> {
+        dir_size(&self.root)
+    }
+
+    /// The path of the file named by a request, or an error explaining why
+    /// the name is rejected; the file system is only read, never changed.
+    pub fn resolve(&self, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty() {
+            return Err("Empty file name".to_string());
+        }
+        // A backslash is a separator on Windows, and a NUL truncates the name.
+        if name.contains('\\') || name.contains('\0') {
+            return Err(format!("Invalid character in file name: \"{}\"", name));
+        }
+        // Rejects "..", "." and absolute paths.
+        if !Path::new(name)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("Invalid file name: \"{}\"", name));
+        }
+        let path = self.root.join(name);
+        self.check_inside(&path)?;
+        Ok(path)
+    }
+
+    /// Checks that the path, or the nearest of its ancestors that exists,
+    /// is really inside the root, even following any symbolic link.
+    fn check_inside(&self, path: &Path) -> Result<(), String> {
+        let outside = || format!("Path outside the storage root: \"{}\"", path.display());
+        let mut current = path;
+        loop {
+            if current.symlink_metadata().is_ok() {
+                // A dangling link cannot be canonicalized, and writing to it
+                // would create its target, wherever that is.
+                return match current.canonicalize() {
+                    Ok(real_path) if real_path.starts_with(&self.root) => Ok(()),
+                    _ => Err(outside()),
+                };
+            }
+            current = current.parent().ok_or_else(outside)?;
+        }
+    }
+}
+
+/// How many bytes the file, or the files under the directory, at `path` take,
+/// not following symbolic links; 0 if there is nothing there.
+pub fn size_of(path: &Path) -> u64 {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => dir_size(path).unwrap_or(0),
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => 0,
+    }
+}
+
+fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // Unlike `std::fs::metadata`, this does not follow symbolic links.
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// A hidden name in the same directory as `path`, unique to the caller,
+/// for a file to be renamed to `path` once complete.
+pub fn temp_sibling(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+    path.with_file_name(format!(".{}.{}{}", name, uuid::Uuid::new_v4(), TEMP_SUFFIX))
+}
+
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Whether a name is one given by `temp_sibling`.
+pub fn is_temp_name(name: &str) -> bool {
+    match name
+        .strip_prefix('.')
+        .and_then(|name| name.strip_suffix(TEMP_SUFFIX))
+    {
+        Some(rest) => match rest.rfind('.') {
+            Some(dot) => uuid::Uuid::parse_str(&rest[dot + 1..]).is_ok(),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+/// Removes the temporary files under `dir`, left by the uploads
+/// interrupted by a crash, returning how many were removed.
+fn remove_temp_files(dir: &Path) -> std::io::Result<usize> {
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            removed += remove_temp_files(&entry.path())?;
+        } else if file_type.is_file() && entry.file_name().to_str().map_or(false, is_temp_name) {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A new storage in a directory of its own, named after the test.
+    fn temp_storage(name: &str) -> Storage {
+        let dir =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        Storage::open(dir.join("root")).unwrap()
+    }
+
+    #[test]
+    fn resolves_names_inside_the_root() {
+        let storage = temp_storage("inside");
+        assert_eq!(
+            storage.resolve("a/b.txt"),
+            Ok(storage.root().join("a/b.txt"))
+        );
+        // Without decoding, "%2e%2e" is just an odd name.
+        assert_eq!(
+            storage.resolve("%2e%2e/b.txt"),
+            Ok(storage.root().join("%2e%2e/b.txt"))
+        );
+    }
+
+    #[test]
+    fn rejects_parent_and_absolute_names() {
+        let storage = temp_storage("parent");
+        for name in &[
+            "",
+            "..",
+            "../b.txt",
+            "a/../../b.txt",
+            "./b.txt",
+            "/etc/passwd",
+            "a\\..\\b.txt",
+            "a\0b",
+        ] {
+            assert!(storage.resolve(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symbolic_links_leading_outside() {
+        let storage = temp_storage("symlink");
+        let outside = storage.root().parent().unwrap().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        let link = storage.root().join("link");
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+        assert!(storage.resolve("link").is_err());
+        assert!(storage.resolve("link/secret.txt").is_err());
+        assert!(storage.resolve("link/a/b.txt").is_err());
+
+        // Writing through a dangling link would create its target.
+        std::os::unix::fs::symlink(outside.join("missing"), storage.root().join("dangling"))
+            .unwrap();
+        assert!(storage.resolve("dangling").is_err());
+
+        // A link to a directory inside the root is followed.
+        std::fs::create_dir(storage.root().join("a")).unwrap();
+        std::os::unix::fs::symlink(storage.root().join("a"), storage.root().join("b")).unwrap();
+        assert!(storage.resolve("b/c.txt").is_ok());
+    }
+}
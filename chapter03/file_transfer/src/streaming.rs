@@ -0,0 +1,306 @@
+use actix_web::http::StatusCode;
+use actix_web::{error, web, Error, HttpRequest};
+use futures::{Async, Future, Poll, Stream};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use crate::digest::{Sha256Sum, UploadHasher};
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// A part of a response body sent from a file.
+pub enum Segment {
+    Bytes(web::Bytes),
+    /// `len` bytes of the file, starting at `start`.
+    File {
+        start: u64,
+        len: u64,
+    },
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        match self {
+            Segment::Bytes(bytes) => bytes.len() as u64,
+            Segment::File { len, .. } => *len,
+        }
+    }
+}
+
+/// Reads parts of a file as a stream of chunks, so that it never needs to fit in memory.
+/// The reads of a local file are short, so they are done on the server thread.
+pub struct FileChunks {
+    file: File,
+    segments: VecDeque<Segment>,
+    /// Where the next read starts, if already known.
+    position: Option<u64>,
+}
+
+impl FileChunks {
+    /// Streams the next `len` bytes of the file.
+    pub fn new(file: File, len: u64) -> Self {
+        Self {
+            file,
+            segments: vec![Segment::File { start: 0, len }].into(),
+            position: Some(0),
+        }
+    }
+
+    /// Streams the given segments, in order.
+    pub fn with_segments(file: File, segments: Vec<Segment>) -> Self {
+        Self {
+            file,
+            segments: segments.into(),
+            position: None,
+        }
+    }
+
+    /// How many bytes will be streamed.
+    pub fn body_len(&self) -> u64 {
+        self.segments.iter().map(Segment::len).sum()
+    }
+}
+
+impl Stream for FileChunks {
+    type Item = web::Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<web::Bytes>, Error> {
+        loop {
+            match self.segments.pop_front() {
+                None => return Ok(Async::Ready(None)),
+                Some(Segment::Bytes(bytes)) => return Ok(Async::Ready(Some(bytes))),
+                Some(Segment::File { len: 0, .. }) => {}
+                Some(Segment::File { start, len }) => {
+                    if self.position != Some(start) {
+                        self.file.seek(SeekFrom::Start(start))?;
+                    }
+                    let mut buffer = vec![0; CHUNK_SIZE.min(len) as usize];
+                    let read_count = self.file.read(&mut buffer)?;
+                    if read_count == 0 {
+                        // The file was truncated while it was being sent.
+                        return Err(error::ErrorInternalServerError(
+                            "File shorter than expected",
+                        ));
+                    }
+                    buffer.truncate(read_count);
+                    let read_count = read_count as u64;
+                    self.position = Some(start + read_count);
+                    self.segments.push_front(Segment::File {
+                        start: start + read_count,
+                        len: len - read_count,
+                    });
+                    return Ok(Async::Ready(Some(web::Bytes::from(buffer))));
+                }
+            }
+        }
+    }
+}
+
+/// Whether the file system has no space left (ENOSPC on Linux and macOS).
+pub fn is_storage_full(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(28)
+}
+
+/// A failed write is answered with 507 Insufficient Storage if the disk is full.
+fn write_error(error: std::io::Error) -> Error {
+    if is_storage_full(&error) {
+        error::InternalError::new("Not enough storage space", StatusCode::INSUFFICIENT_STORAGE)
+            .into()
+    } else {
+        error.into()
+    }
+}
+
+/// How many bytes an upload may write, and what sets that limit.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UploadLimit {
+    /// The size limit of every upload.
+    Size(u64),
+    /// The space left in the quota of the account.
+    Quota(u64),
+}
+
+impl UploadLimit {
+    pub fn bytes(self) -> u64 {
+        match self {
+            UploadLimit::Size(bytes) | UploadLimit::Quota(bytes) => bytes,
+        }
+    }
+
+    /// The stricter of two limits.
+    pub fn min(self, other: Self) -> Self {
+        if other.bytes() < self.bytes() {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// 413 Payload Too Large beyond the size limit,
+    /// 507 Insufficient Storage beyond the quota.
+    pub fn exceeded(self) -> Error {
+        match self {
+            UploadLimit::Size(bytes) => {
+                error::ErrorPayloadTooLarge(format!("Uploads are limited to {} bytes", bytes))
+            }
+            UploadLimit::Quota(bytes) => error::InternalError::new(
+                format!("Quota exceeded: only {} more bytes can be stored", bytes),
+                StatusCode::INSUFFICIENT_STORAGE,
+            )
+            .into(),
+        }
+    }
+}
+
+/// The size of the body declared by the `Content-Length` header, if any.
+pub fn declared_size(req: &HttpRequest) -> Option<u64> {
+    req.headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+}
+
+/// Rejects a request declaring a body beyond the limit, before anything is written.
+pub fn check_content_length(req: &HttpRequest, limit: UploadLimit) -> Result<(), Error> {
+    match declared_size(req) {
+        Some(size) if size > limit.bytes() => Err(limit.exceeded()),
+        _ => Ok(()),
+    }
+}
+
+/// What was stored of an upload.
+pub struct Written {
+    /// In bytes.
+    pub len: u64,
+    pub sha256: Sha256Sum,
+}
+
+/// Writes the payload to the file at `path` chunk by chunk as it arrives,
+/// failing as soon as it goes beyond the limit, and at the end with
+/// 400 Bad Request if it does not match the digests declared by the client.
+/// On failure the file is removed.
+pub fn write_payload(
+    payload: web::Payload,
+    file: File,
+    path: PathBuf,
+    limit: UploadLimit,
+    hasher: UploadHasher,
+) -> impl Future<Item = Written, Error = Error> {
+    payload
+        .map_err(Error::from)
+        .fold(
+            (file, 0u64, hasher),
+            move |(mut file, written, mut hasher), chunk| -> Result<_, Error> {
+                let written = written + chunk.len() as u64;
+                if written > limit.bytes() {
+                    return Err(limit.exceeded());
+                }
+                file.write_all(&chunk).map_err(write_error)?;
+                hasher.input(&chunk);
+                Ok((file, written, hasher))
+            },
+        )
+        .and_then(|(file, written, hasher)| -> Result<_, Error> {
+            let sha256 = hasher.finish().map_err(error::ErrorBadRequest)?;
+            file.sync_all().map_err(write_error)?;
+            Ok(Written {
+                len: written,
+                sha256,
+            })
+        })
+        .then(move |result| {
+            if result.is_err() {
+                let _ = std::fs::remove_file(&path);
+            }
+            result
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    /// A new file named after the test, with the given contents.
+    fn temp_file(name: &str, contents: &[u8]) -> File {
+        let path =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        File::open(path).unwrap()
+    }
+
+    fn streamed(chunks: FileChunks) -> Result<Vec<u8>, Error> {
+        let mut body = vec![];
+        for chunk in chunks.wait() {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body)
+    }
+
+    #[test]
+    fn streams_a_file_in_chunks() {
+        let contents: Vec<u8> = (0..150_000u32).map(|i| i as u8).collect();
+        let chunks = FileChunks::new(temp_file("chunks", &contents), contents.len() as u64);
+        assert_eq!(chunks.body_len(), 150_000);
+        let sizes: Vec<usize> = chunks.wait().map(|chunk| chunk.unwrap().len()).collect();
+        assert_eq!(sizes, vec![65_536, 65_536, 18_928]);
+        let chunks = FileChunks::new(temp_file("chunks", &contents), contents.len() as u64);
+        assert_eq!(streamed(chunks).unwrap(), contents);
+    }
+
+    #[test]
+    fn streams_segments_in_order() {
+        let segments = vec![
+            Segment::Bytes(web::Bytes::from("<")),
+            Segment::File { start: 5, len: 8 },
+            Segment::Bytes(web::Bytes::from("|")),
+            Segment::File { start: 0, len: 4 },
+            Segment::File { start: 0, len: 0 },
+            Segment::Bytes(web::Bytes::from(">")),
+        ];
+        let chunks = FileChunks::with_segments(temp_file("segments", b"File contents."), segments);
+        assert_eq!(chunks.body_len(), 15);
+        assert_eq!(streamed(chunks).unwrap(), b"<contents|File>".to_vec());
+    }
+
+    #[test]
+    fn fails_on_a_file_shorter_than_expected() {
+        let chunks = FileChunks::new(temp_file("truncated", b"File contents."), 20);
+        assert!(streamed(chunks).is_err());
+    }
+
+    #[test]
+    fn answers_each_limit_with_its_status() {
+        let size = UploadLimit::Size(100);
+        let quota = UploadLimit::Quota(10);
+        assert_eq!(size.min(quota), quota);
+        assert_eq!(quota.min(size), quota);
+        assert_eq!(size.min(UploadLimit::Quota(100)), size);
+        let status = |limit: UploadLimit| {
+            limit
+                .exceeded()
+                .as_response_error()
+                .error_response()
+                .status()
+        };
+        assert_eq!(status(size), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(status(quota), StatusCode::INSUFFICIENT_STORAGE);
+    }
+
+    #[test]
+    fn checks_the_declared_size() {
+        let req = TestRequest::default()
+            .header("content-length", "100")
+            .to_http_request();
+        assert_eq!(declared_size(&req), Some(100));
+        assert!(check_content_length(&req, UploadLimit::Size(100)).is_ok());
+        assert!(check_content_length(&req, UploadLimit::Size(99)).is_err());
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(declared_size(&req), None);
+        assert!(check_content_length(&req, UploadLimit::Size(0)).is_ok());
+    }
+}
@@ -0,0 +1,175 @@
+use actix_web::http::header::HttpDate;
+use actix_web::HttpRequest;
+use std::fs::Metadata;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What identifies a version of a file, for the conditional requests.
+pub struct Validators {
+    /// Quoted, as sent in the `ETag` header.
+    pub etag: String,
+    /// Truncated to seconds, as sent in the `Last-Modified` header.
+    pub last_modified: SystemTime,
+}
+
+impl Validators {
+    /// The validators of a file, changing whenever its size or its
+    /// modification time changes.
+    pub fn of(metadata: &Metadata) -> Self {
+        let since_epoch = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            etag: format!(
+                "\"{:x}-{:x}-{:x}\"",
+                metadata.len(),
+                since_epoch.as_secs(),
+                since_epoch.subsec_nanos()
+            ),
+            last_modified: UNIX_EPOCH + Duration::from_secs(since_epoch.as_secs()),
+        }
+    }
+
+    pub fn last_modified_header(&self) -> String {
+        HttpDate::from(self.last_modified).to_string()
+    }
+
+    /// Whether the copy the client has is still current,
+    /// by `If-None-Match` or else by `If-Modified-Since`.
+    pub fn is_not_modified(&self, req: &HttpRequest) -> bool {
+        if let Some(tags) = header_str(req, "if-none-match") {
+            return matches_etag_list(tags, &self.etag);
+        }
+        match header_str(req, "if-modified-since").and_then(parse_http_date) {
+            Some(date) => self.last_modified <= date,
+            None => false,
+        }
+    }
+
+    /// Whether the `Range` header may be honoured, by `If-Range`: a range of
+    /// a version of the file other than the one the client has is useless.
+    pub fn is_range_allowed(&self, req: &HttpRequest) -> bool {
+        match header_str(req, "if-range").map(str::trim) {
+            None => true,
+            // Only a strong validator can be used.
+            Some(tag) if tag.starts_with('"') => tag == self.etag,
+            Some(tag) if tag.starts_with("W/") => false,
+            Some(date) => parse_http_date(date) == Some(self.last_modified),
+        }
+    }
+}
+
+/// Whether a request may change a file, by `If-Match` and `If-None-Match`,
+/// given the validators of its current version, if it exists.
+/// So a client can avoid overwriting a version it has not seen,
+/// or any existing version with "If-None-Match: *".
+pub fn is_change_allowed(req: &HttpRequest, current: Option<&Validators>) -> bool {
+    if let Some(tags) = header_str(req, "if-match") {
+        // Only the strong comparison is allowed here.
+        let matches = current.map_or(false, |current| {
+            tags.trim() == "*" || tags.split(',').any(|tag| tag.trim() == current.etag)
+        });
+        if !matches {
+            return false;
+        }
+    }
+    match (header_str(req, "if-none-match"), current) {
+        (Some(tags), Some(current)) => !matches_etag_list(tags, &current.etag),
+        _ => true,
+    }
+}
+
+fn header_str<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    value.trim().parse::<HttpDate>().ok().map(SystemTime::from)
+}
+
+/// Whether the list of entity tags of an `If-None-Match` header includes
+/// the given one, with the weak comparison.
+fn matches_etag_list(list: &str, etag: &str) -> bool {
+    list.trim() == "*"
+        || list
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    /// The validators of a 14 bytes file last modified at
+    /// 1600000000.000000005, "Sun, 13 Sep 2020 12:26:40 GMT".
+    fn validators(name: &str) -> Validators {
+        let path =
+            std::env::temp_dir().join(format!("file_transfer-{}-{}", std::process::id(), name));
+        std::fs::write(&path, "File contents.").unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::new(1_600_000_000, 5))
+            .unwrap();
+        Validators::of(&file.metadata().unwrap())
+    }
+
+    fn request(headers: &[(&str, &str)]) -> HttpRequest {
+        headers
+            .iter()
+            .fold(TestRequest::default(), |req, (name, value)| {
+                req.header(*name, *value)
+            })
+            .to_http_request()
+    }
+
+    const LAST_MODIFIED: &str = "Sun, 13 Sep 2020 12:26:40 GMT";
+
+    #[test]
+    fn changes_with_the_size_and_the_time() {
+        let validators = validators("validators_format");
+        assert_eq!(validators.etag, "\"e-5f5e1000-5\"");
+        assert_eq!(validators.last_modified_header(), LAST_MODIFIED);
+    }
+
+    #[test]
+    fn finds_the_copies_not_modified() {
+        let validators = validators("validators_not_modified");
+        let not_modified = |headers: &[(&str, &str)]| validators.is_not_modified(&request(headers));
+        let etag = validators.etag.as_str();
+        let tags = format!("\"other\", W/{}", etag);
+        assert!(not_modified(&[("if-none-match", etag)]));
+        assert!(not_modified(&[("if-none-match", &tags)]));
+        assert!(not_modified(&[("if-none-match", "*")]));
+        assert!(!not_modified(&[("if-none-match", "\"other\", W/\"more\"")]));
+
+        assert!(not_modified(&[("if-modified-since", LAST_MODIFIED)]));
+        let later = "Sun, 13 Sep 2020 12:26:41 GMT";
+        assert!(not_modified(&[("if-modified-since", later)]));
+        let earlier = "Sun, 13 Sep 2020 12:26:39 GMT";
+        assert!(!not_modified(&[("if-modified-since", earlier)]));
+        assert!(!not_modified(&[("if-modified-since", "yesterday")]));
+        // If-None-Match wins over If-Modified-Since.
+        assert!(!not_modified(&[
+            ("if-none-match", "\"other\""),
+            ("if-modified-since", LAST_MODIFIED),
+        ]));
+        assert!(!not_modified(&[]));
+    }
+
+    #[test]
+    fn allows_ranges_of_the_same_version_only() {
+        let validators = validators("validators_range");
+        let etag = validators.etag.as_str();
+        let weak = format!("W/{}", etag);
+        assert!(validators.is_range_allowed(&request(&[])));
+        assert!(validators.is_range_allowed(&request(&[("if-range", etag)])));
+        assert!(!validators.is_range_allowed(&request(&[("if-range", "\"other\"")])));
+        // A weak validator never allows a range.
+        assert!(!validators.is_range_allowed(&request(&[("if-range", &weak)])));
+        assert!(validators.is_range_allowed(&request(&[("if-range", LAST_MODIFIED)])));
+        let later = "Sun, 13 Sep 2020 12:26:41 GMT";
+        assert!(!validators.is_range_allowed(&request(&[("if-range", later)])));
+    }
+}
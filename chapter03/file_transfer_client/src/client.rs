@@ -0,0 +1,188 @@
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde_derive::Deserialize;
+use std::fmt;
+
+/// Why a command failed, deciding the exit code of the program.
+#[derive(Debug)]
+pub enum ClientError {
+    /// A local file could not be read or written.
+    Local(std::io::Error),
+    /// The server could not be reached.
+    Connection(reqwest::Error),
+    /// The connection was lost while receiving a body.
+    Interrupted(std::io::Error),
+    /// The server answered with a failure status, and this text.
+    Status(StatusCode, String),
+}
+
+impl ClientError {
+    /// 1 for a local error, 2 for a connection error, otherwise a code
+    /// for each class of failure status, from 3 to 8.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ClientError::Local(_) => 1,
+            ClientError::Connection(_) | ClientError::Interrupted(_) => 2,
+            ClientError::Status(status, _) => match *status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => 3,
+                StatusCode::NOT_FOUND | StatusCode::GONE => 4,
+                StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => 5,
+                StatusCode::PAYLOAD_TOO_LARGE | StatusCode::INSUFFICIENT_STORAGE => 6,
+                status if status.is_server_error() => 8,
+                _ => 7,
+            },
+        }
+    }
+
+    /// Whether trying again may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ClientError::Connection(_) | ClientError::Interrupted(_)
+        )
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Local(error) => write!(f, "Local error: {}", error),
+            ClientError::Connection(error) => write!(f, "Connection error: {}", error),
+            ClientError::Interrupted(error) => write!(f, "Transfer interrupted: {}", error),
+            ClientError::Status(status, text) if text.is_empty() => write!(f, "Server: {}", status),
+            ClientError::Status(status, text) => write!(f, "Server: {}: {}", status, text),
+        }
+    }
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(error: std::io::Error) -> Self {
+        ClientError::Local(error)
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(error: reqwest::Error) -> Self {
+        ClientError::Connection(error)
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// An entry of a directory listing, as sent by the server.
+#[derive(Deserialize, Debug)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+/// Turns a failure status into an error.
+pub fn check_status(mut response: Response) -> ClientResult<Response> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(ClientError::Status(
+            status,
+            response.text().unwrap_or_default().trim().to_string(),
+        ))
+    }
+}
+
+enum Credentials {
+    Anonymous,
+    Basic(String, Option<String>),
+    Bearer(String),
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    server: String,
+    credentials: Credentials,
+}
+
+impl Client {
+    /// A client of the server at the given base URL, authenticated by
+    /// "username:password", or else by a token, if any.
+    pub fn new(server: &str, user: Option<&str>, token: Option<&str>) -> ClientResult<Self> {
+        let credentials = match (user, token) {
+            (Some(user), _) => match user.find(':') {
+                Some(colon) => Credentials::Basic(
+                    user[..colon].to_string(),
+                    Some(user[colon + 1..].to_string()),
+                ),
+                None => Credentials::Basic(user.to_string(), None),
+            },
+            (None, Some(token)) => Credentials::Bearer(token.to_string()),
+            (None, None) => Credentials::Anonymous,
+        };
+        Ok(Self {
+            // Large files take longer than any timeout.
+            http: reqwest::Client::builder().timeout(None).build()?,
+            server: server.trim_end_matches('/').to_string(),
+            credentials,
+        })
+    }
+
+    /// The URL of a remote name, with its segments percent-encoded.
+    pub fn url(&self, remote: &str) -> String {
+        let mut url = self.server.clone();
+        for segment in remote.trim_start_matches('/').split('/') {
+            url.push('/');
+            for byte in segment.bytes() {
+                if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                    url.push(byte as char);
+                } else {
+                    url += &format!("%{:02X}", byte);
+                }
+            }
+        }
+        url
+    }
+
+    pub fn request(&self, method: Method, remote: &str) -> RequestBuilder {
+        let request = self.http.request(method, &self.url(remote));
+        match &self.credentials {
+            Credentials::Anonymous => request,
+            Credentials::Basic(username, password) => {
+                request.basic_auth(username, password.clone())
+            }
+            Credentials::Bearer(token) => {
+                request.header(AUTHORIZATION, format!("Bearer {}", token))
+            }
+        }
+    }
+
+    /// Sends a request, turning a failure status into an error.
+    pub fn send(&self, request: RequestBuilder) -> ClientResult<Response> {
+        check_status(request.send()?)
+    }
+
+    /// The entries of a remote directory, "" being the root.
+    pub fn list(&self, remote_dir: &str) -> ClientResult<Vec<Entry>> {
+        let request = self
+            .request(Method::GET, remote_dir)
+            .query(&[("format", "json")]);
+        Ok(self.send(request)?.json()?)
+    }
+
+    /// Creates a remote directory, unless it already exists.
+    pub fn make_directory(&self, remote_dir: &str) -> ClientResult<()> {
+        let mkcol = Method::from_bytes(b"MKCOL").unwrap();
+        match self.send(self.request(mkcol, remote_dir)) {
+            Err(ClientError::Status(StatusCode::METHOD_NOT_ALLOWED, _)) => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    pub fn delete(&self, remote: &str, recursive: bool) -> ClientResult<()> {
+        let mut request = self.request(Method::DELETE, remote);
+        if recursive {
+            request = request.query(&[("recursive", "true")]);
+        }
+        self.send(request).map(|_| ())
+    }
+}
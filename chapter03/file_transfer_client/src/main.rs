@@ -0,0 +1,198 @@
+// A client of the file_transfer server; test it with the following commands,
+// while the server is running:
+// cargo run -- put datafile.txt
+// cargo run -- get datafile.txt copy.txt
+// cargo run -- post data.txt reports/data.txt
+// cargo run -- ls reports
+// cargo run -- sync ./photos photos --delete
+// cargo run -- -u alice:secret delete reports --recursive
+//
+// The exit code is 0 on success, 1 after a local error, 2 if the server
+// could not be reached, 3 if the credentials were refused, 4 if a remote file
+// was not found, 5 if a remote file conflicts with the change, 6 if an upload
+// is too large or the storage is full, 7 after any other refused request,
+// and 8 after a failure of the server.
+
+mod client;
+mod progress;
+mod sync;
+mod transfer;
+
+use persons_domain::format_timestamp;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+use client::{Client, ClientResult, Entry};
+use sync::SyncOptions;
+
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Transfers files to and from a file_transfer server")]
+struct Opt {
+    /// The base URL of the server.
+    #[structopt(short = "s", long = "server", default_value = "http://localhost:8080")]
+    server: String,
+
+    /// The credentials of a Basic authentication, as "username:password".
+    #[structopt(short = "u", long = "user")]
+    user: Option<String>,
+
+    /// The token of a Bearer authentication.
+    #[structopt(short = "t", long = "token")]
+    token: Option<String>,
+
+    /// How many times a transfer is tried again after the connection is lost.
+    #[structopt(long = "retries", default_value = "3")]
+    retries: u32,
+
+    /// Hides the progress bars.
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Downloads a file.
+    Get {
+        remote: String,
+
+        /// By default, the last segment of the remote name.
+        #[structopt(parse(from_os_str))]
+        local: Option<PathBuf>,
+
+        /// Continues the download of a partial local file.
+        #[structopt(short = "c", long = "continue")]
+        resume: bool,
+    },
+    /// Uploads a file, replacing the remote file having its name.
+    Put {
+        #[structopt(parse(from_os_str))]
+        local: PathBuf,
+
+        /// By default, the name of the local file.
+        remote: Option<String>,
+
+        /// Fails rather than replacing an existing remote file.
+        #[structopt(long = "no-clobber")]
+        no_clobber: bool,
+    },
+    /// Uploads a file to a new name, made unique by the server.
+    Post {
+        #[structopt(parse(from_os_str))]
+        local: PathBuf,
+
+        /// By default, the name of the local file.
+        remote: Option<String>,
+    },
+    /// Deletes a file or a directory.
+    Delete {
+        remote: String,
+
+        /// Deletes a directory with its contents.
+        #[structopt(short = "r", long = "recursive")]
+        recursive: bool,
+    },
+    /// Lists a directory.
+    Ls {
+        #[structopt(default_value = "")]
+        remote: String,
+    },
+    /// Uploads the files of a local directory which are missing or changed on the server.
+    Sync {
+        #[structopt(parse(from_os_str))]
+        local: PathBuf,
+
+        #[structopt(default_value = "")]
+        remote: String,
+
+        /// Also deletes the remote files missing locally.
+        #[structopt(long = "delete")]
+        delete: bool,
+    },
+}
+
+/// The name of a local file, as remote name.
+fn local_name(local: &Path) -> String {
+    local
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+fn print_listing(entries: &[Entry]) {
+    for entry in entries {
+        println!(
+            "{:>12}  {}  {}{}",
+            if entry.is_dir {
+                "-".to_string()
+            } else {
+                entry.size.to_string()
+            },
+            format_timestamp(entry.modified),
+            entry.name,
+            if entry.is_dir { "/" } else { "" }
+        );
+    }
+}
+
+fn run(opt: Opt) -> ClientResult<()> {
+    let client = Client::new(&opt.server, opt.user.as_deref(), opt.token.as_deref())?;
+    match opt.command {
+        Command::Get {
+            remote,
+            local,
+            resume,
+        } => {
+            let local = local.unwrap_or_else(|| {
+                PathBuf::from(remote.trim_end_matches('/').rsplit('/').next().unwrap())
+            });
+            transfer::get(&client, &remote, &local, resume, opt.retries, opt.quiet)
+        }
+        Command::Put {
+            local,
+            remote,
+            no_clobber,
+        } => {
+            let remote = remote.unwrap_or_else(|| local_name(&local));
+            transfer::put(&client, &local, &remote, no_clobber, opt.retries, opt.quiet)
+        }
+        Command::Post { local, remote } => {
+            let remote = remote.unwrap_or_else(|| local_name(&local));
+            transfer::post(&client, &local, &remote, opt.quiet)
+        }
+        Command::Delete { remote, recursive } => {
+            client.delete(&remote, recursive)?;
+            println!("Deleted: {}", remote);
+            Ok(())
+        }
+        Command::Ls { remote } => {
+            print_listing(&client.list(&remote)?);
+            Ok(())
+        }
+        Command::Sync {
+            local,
+            remote,
+            delete,
+        } => {
+            let options = SyncOptions {
+                delete,
+                retries: opt.retries,
+                quiet: opt.quiet,
+            };
+            let counts = sync::sync(&client, &local, &remote, &options)?;
+            println!(
+                "Synchronized: {} files uploaded, {} entries deleted",
+                counts.uploaded, counts.deleted
+            );
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    if let Err(error) = run(Opt::from_args()) {
+        eprintln!("{}", error);
+        std::process::exit(error.exit_code());
+    }
+}
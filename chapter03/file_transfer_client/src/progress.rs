@@ -0,0 +1,50 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Read;
+
+/// A progress bar of a transfer of `len` bytes, if known, named by `name`.
+pub fn bar(len: Option<u64>, name: &str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let bar = match len {
+        Some(len) => {
+            let bar = ProgressBar::new(len);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{msg} [{bar:40}] {bytes}/{total_bytes} {bytes_per_sec} {eta}")
+                    .progress_chars("=> "),
+            );
+            bar
+        }
+        None => {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{msg} {spinner} {bytes} {bytes_per_sec}"),
+            );
+            bar
+        }
+    };
+    bar.set_message(name);
+    bar
+}
+
+/// A reader advancing a progress bar by the bytes read.
+pub struct ProgressRead<R> {
+    inner: R,
+    bar: ProgressBar,
+}
+
+impl<R: Read> ProgressRead<R> {
+    pub fn new(inner: R, bar: ProgressBar) -> Self {
+        Self { inner, bar }
+    }
+}
+
+impl<R: Read> Read for ProgressRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read_count = self.inner.read(buf)?;
+        self.bar.inc(read_count as u64);
+        Ok(read_count)
+    }
+}
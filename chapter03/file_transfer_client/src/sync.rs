@@ -0,0 +1,126 @@
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::client::{Client, ClientError, ClientResult, Entry};
+use crate::transfer;
+
+/// What a synchronization changed on the server.
+#[derive(Default)]
+pub struct SyncCounts {
+    pub uploaded: usize,
+    pub deleted: usize,
+}
+
+pub struct SyncOptions {
+    /// Also delete the remote entries missing locally.
+    pub delete: bool,
+    pub retries: u32,
+    pub quiet: bool,
+}
+
+/// Makes the remote directory have the files of the local one, uploading those
+/// missing on the server, having another size there, or changed locally since
+/// their upload. The missing directories are created.
+pub fn sync(
+    client: &Client,
+    local_dir: &Path,
+    remote_dir: &str,
+    options: &SyncOptions,
+) -> ClientResult<SyncCounts> {
+    let mut counts = SyncCounts::default();
+    sync_dir(client, local_dir, remote_dir, options, &mut counts)?;
+    Ok(counts)
+}
+
+fn join_names(dir_name: &str, name: &str) -> String {
+    let dir_name = dir_name.trim_end_matches('/');
+    if dir_name.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir_name, name)
+    }
+}
+
+fn sync_dir(
+    client: &Client,
+    local_dir: &Path,
+    remote_dir: &str,
+    options: &SyncOptions,
+    counts: &mut SyncCounts,
+) -> ClientResult<()> {
+    let mut remote_entries: HashMap<String, Entry> = match client.list(remote_dir) {
+        Ok(entries) => entries.into_iter().map(|e| (e.name.clone(), e)).collect(),
+        Err(ClientError::Status(StatusCode::NOT_FOUND, _)) if !remote_dir.is_empty() => {
+            client.make_directory(remote_dir)?;
+            HashMap::new()
+        }
+        Err(error) => return Err(error),
+    };
+
+    let mut local_entries = std::fs::read_dir(local_dir)?.collect::<Result<Vec<_>, _>>()?;
+    local_entries.sort_by_key(|entry| entry.file_name());
+    for local_entry in local_entries {
+        let name = match local_entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(name) => {
+                eprintln!("Skipped name not in UTF-8: {:?}", name);
+                continue;
+            }
+        };
+        let remote_name = join_names(remote_dir, &name);
+        // Follows the symbolic links, to send what they point to.
+        let metadata = std::fs::metadata(local_entry.path())?;
+        let remote_entry = remote_entries.remove(&name);
+        if metadata.is_dir() {
+            if remote_entry.as_ref().map_or(false, |e| !e.is_dir) {
+                eprintln!(
+                    "Skipped directory, being a file on the server: {}",
+                    remote_name
+                );
+                continue;
+            }
+            sync_dir(client, &local_entry.path(), &remote_name, options, counts)?;
+        } else if metadata.is_file() {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            let is_current = match &remote_entry {
+                Some(e) if e.is_dir => {
+                    eprintln!(
+                        "Skipped file, being a directory on the server: {}",
+                        remote_name
+                    );
+                    continue;
+                }
+                // The remote file is as recent as its upload.
+                Some(e) => e.size == metadata.len() && e.modified >= modified,
+                None => false,
+            };
+            if !is_current {
+                transfer::put(
+                    client,
+                    &local_entry.path(),
+                    &remote_name,
+                    false,
+                    options.retries,
+                    options.quiet,
+                )?;
+                counts.uploaded += 1;
+            }
+        }
+    }
+
+    if options.delete {
+        for (name, entry) in remote_entries {
+            let remote_name = join_names(remote_dir, &name);
+            client.delete(&remote_name, entry.is_dir)?;
+            println!("Deleted: {}", remote_name);
+            counts.deleted += 1;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_NONE_MATCH, IF_RANGE, LOCATION, RANGE};
+use reqwest::{Body, Method, Response, StatusCode};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::client::{check_status, Client, ClientError, ClientResult};
+use crate::progress::{self, ProgressRead};
+
+/// Downloads a remote file to a local one. After the connection is lost, up
+/// to `retries` times, the download continues from where it stopped by
+/// a `Range` request; with `resume`, it continues a previous download.
+pub fn get(
+    client: &Client,
+    remote: &str,
+    local: &Path,
+    resume: bool,
+    retries: u32,
+    quiet: bool,
+) -> ClientResult<()> {
+    let mut resume = resume;
+    let mut retries_left = retries;
+    // The version being downloaded, so that only the same version is resumed.
+    let mut etag = None;
+    loop {
+        match download(client, remote, local, resume, &mut etag, quiet) {
+            Err(ref error) if error.is_transient() && retries_left > 0 => {
+                eprintln!("{}; resuming", error);
+                retries_left -= 1;
+                resume = true;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn download(
+    client: &Client,
+    remote: &str,
+    local: &Path,
+    resume: bool,
+    etag: &mut Option<String>,
+    quiet: bool,
+) -> ClientResult<()> {
+    let offset = if resume {
+        std::fs::metadata(local).map_or(0, |m| m.len())
+    } else {
+        0
+    };
+    let mut request = client.request(Method::GET, remote);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        // Without a known version, the local file is trusted to be
+        // the beginning of the remote one.
+        if let Some(etag) = etag.as_ref() {
+            request = request.header(IF_RANGE, etag.as_str());
+        }
+    }
+    let response = request.send()?;
+    // Nothing is left to download if the local file has the size of the remote one,
+    // as told by a Content-Range such as "bytes */1234".
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE
+        && header_str(&response, CONTENT_RANGE) == Some(format!("bytes */{}", offset).as_str())
+    {
+        println!("Already downloaded: {} -> {}", remote, local.display());
+        return Ok(());
+    }
+    let mut response = check_status(response)?;
+    *etag = header_str(&response, ETAG).map(String::from);
+
+    // Without a partial response, the whole file is sent again.
+    let (mut file, start) = if response.status() == StatusCode::PARTIAL_CONTENT
+        && header_str(&response, CONTENT_RANGE).map_or(false, |range| {
+            range.starts_with(&format!("bytes {}-", offset))
+        }) {
+        (OpenOptions::new().append(true).open(local)?, offset)
+    } else {
+        (File::create(local)?, 0)
+    };
+    let expected_len = response.content_length();
+    let bar = progress::bar(expected_len.map(|len| start + len), remote, quiet);
+    bar.set_position(start);
+
+    let mut buffer = vec![0; 64 * 1024];
+    let mut received = 0;
+    loop {
+        let read_count = response
+            .read(&mut buffer)
+            .map_err(ClientError::Interrupted)?;
+        if read_count == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read_count])?;
+        received += read_count as u64;
+        bar.inc(read_count as u64);
+    }
+    bar.finish_and_clear();
+    match expected_len {
+        Some(len) if received < len => Err(ClientError::Interrupted(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("received {} of {} bytes", received, len),
+        ))),
+        _ => {
+            println!("Downloaded: {} -> {}", remote, local.display());
+            Ok(())
+        }
+    }
+}
+
+fn header_str(response: &Response, name: reqwest::header::HeaderName) -> Option<&str> {
+    response.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Uploads a local file with the given method, sending its size first
+/// so that the server can reject a too large file at once.
+fn upload(
+    client: &Client,
+    method: Method,
+    local: &Path,
+    remote: &str,
+    no_clobber: bool,
+    quiet: bool,
+) -> ClientResult<Response> {
+    let file = File::open(local)?;
+    let len = file.metadata()?.len();
+    let bar = progress::bar(Some(len), remote, quiet);
+    let mut request = client
+        .request(method, remote)
+        .body(Body::sized(ProgressRead::new(file, bar.clone()), len));
+    if no_clobber {
+        request = request.header(IF_NONE_MATCH, "*");
+    }
+    let result = client.send(request);
+    bar.finish_and_clear();
+    result
+}
+
+/// Uploads a local file to a remote name, trying again after the connection
+/// is lost, up to `retries` times; with `no_clobber`, an existing remote file
+/// is not replaced.
+pub fn put(
+    client: &Client,
+    local: &Path,
+    remote: &str,
+    no_clobber: bool,
+    retries: u32,
+    quiet: bool,
+) -> ClientResult<()> {
+    let mut retries_left = retries;
+    loop {
+        match upload(client, Method::PUT, local, remote, no_clobber, quiet) {
+            Err(ref error) if error.is_transient() && retries_left > 0 => {
+                eprintln!("{}; trying again", error);
+                retries_left -= 1;
+            }
+            result => {
+                result?;
+                println!("Uploaded: {} -> {}", local.display(), remote);
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Uploads a local file to a new name chosen by the server from `remote`,
+/// and prints that name. Never tried again, as it could create two files.
+pub fn post(client: &Client, local: &Path, remote: &str, quiet: bool) -> ClientResult<()> {
+    let mut response = upload(client, Method::POST, local, remote, false, quiet)?;
+    let location = header_str(&response, LOCATION).map(String::from);
+    let name = response.text()?;
+    println!(
+        "Uploaded: {} -> {}",
+        local.display(),
+        location.unwrap_or(name)
+    );
+    Ok(())
+}
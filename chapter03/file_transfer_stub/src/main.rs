@@ -9,14 +9,17 @@
 // Contents of the file.
 //
 // After running all five commands, the server should have printed:
-// Listening at address 127.0.0.1:8080 ...
+// Listening at address: http://127.0.0.1:8080
 // Deleting file "datafile.txt" ... Deleted file "datafile.txt"
 // Downloading file "datafile.txt" ... Downloaded file "datafile.txt"
 // Uploading file "datafile.txt" ... Uploaded file "datafile.txt"
 // Uploading file "data_*.txt" ... Uploaded file "data_17.txt"
 // Invalid URI: "/a/b"
 
-use actix_web::{web, web::Path, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    middleware, web, web::Path, App, HttpRequest, HttpResponse, HttpServer, Responder,
+};
+use server_config::Config;
 use std::io::Write;
 
 fn flush_stdout() {
@@ -84,10 +87,12 @@ fn invalid_resource(req: HttpRequest) -> impl Responder {
 }
 
 fn main() -> std::io::Result<()> {
-    let server_addr = "127.0.0.1:8080";
-    println!("listening at address {} ...", server_addr);
-    HttpServer::new(|| {
+    let config = Config::load("FILE_TRANSFER_STUB")?;
+    config.init_logger();
+    let server_addr = config.server.bind_address.clone();
+    let mut server = HttpServer::new(|| {
         App::new()
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
             .service(
                 web::resource("/{filename}")
                     .route(web::delete().to(delete_file))
@@ -96,7 +101,21 @@ fn main() -> std::io::Result<()> {
                     .route(web::post().to(upload_new_file)),
             )
             .default_service(web::route().to(invalid_resource))
-    })
-    .bind(server_addr)?
-    .run()
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&server_addr, acceptor)?,
+        None => server.bind(&server_addr)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&server_addr)?;
+    println!(
+        "listening at address: {}://{}",
+        config.scheme(),
+        server_addr
+    );
+    server.run()
 }
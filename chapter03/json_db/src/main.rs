@@ -1,20 +1,28 @@
-mod db_access;
-
-use actix_web::{web, web::Path, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    middleware, web, web::Path, App, HttpRequest, HttpResponse, HttpServer, Responder,
+};
+use persons_domain::{MemoryPersons, Person, PersonsStore};
 use serde_derive::Deserialize;
 use serde_json::json;
+use server_config::Config;
 use std::sync::Mutex;
 
 struct AppState {
-    db: db_access::DbConnection,
+    db: MemoryPersons,
 }
 
 fn get_all_persons_ids(state: web::Data<Mutex<AppState>>) -> impl Responder {
     println!("in get_all_persons_ids");
     let db_conn = &state.lock().unwrap().db;
-    HttpResponse::Ok()
-        .content_type("application/json")
-        .body(json!(db_conn.get_all_persons_ids().collect::<Vec<_>>()).to_string())
+    HttpResponse::Ok().content_type("application/json").body(
+        json!(db_conn
+            .get_persons_by_partial_name("")
+            .unwrap_or_default()
+            .iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>())
+        .to_string(),
+    )
 }
 
 fn get_person_name_by_id(
@@ -33,10 +41,10 @@ fn get_person_name_by_id(
 
     let db_conn = &state.lock().unwrap().db;
 
-    if let Some(name) = db_conn.get_person_name_by_id(id) {
+    if let Ok(Some(person)) = db_conn.get_person_by_id(id) {
         HttpResponse::Ok()
             .content_type("application/json")
-            .body(json!(name).to_string())
+            .body(json!(person.name).to_string())
     } else {
         HttpResponse::NotFound().finish()
     }
@@ -52,9 +60,12 @@ fn get_persons(state: web::Data<Mutex<AppState>>, query: web::Query<Filter>) ->
     let db_conn = &state.lock().unwrap().db;
     HttpResponse::Ok().content_type("application/json").body(
         json!(db_conn
-            .get_persons_id_and_name_by_partial_name(
+            .get_persons_by_partial_name(
                 &query.partial_name.clone().unwrap_or_else(|| "".to_string()),
             )
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p.id, p.name))
             .collect::<Vec<_>>())
         .to_string(),
     )
@@ -62,12 +73,18 @@ fn get_persons(state: web::Data<Mutex<AppState>>, query: web::Query<Filter>) ->
 
 fn insert_person(state: web::Data<Mutex<AppState>>, info: Path<(String,)>) -> impl Responder {
     println!("in insert_person");
-    let name = &info.0;
+    let name = info.0.clone();
     let db_conn = &mut state.lock().unwrap().db;
-    // format!("{}", db_conn.insert_person(name))
-    HttpResponse::Ok()
-        .content_type("application/json")
-        .body(json!(db_conn.insert_person(name)).to_string())
+    match db_conn.insert_person(Person {
+        id: 0,
+        name,
+        ..Person::default()
+    }) {
+        Ok(id) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(json!(id).to_string()),
+        Err(msg) => HttpResponse::InternalServerError().body(msg),
+    }
 }
 
 fn invalid_resource(req: HttpRequest) -> impl Responder {
@@ -75,24 +92,83 @@ fn invalid_resource(req: HttpRequest) -> impl Responder {
     HttpResponse::NotFound()
 }
 
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/persons/ids").route(web::get().to(get_all_persons_ids)))
+        .service(
+            web::resource("/person/name_by_id/{id}").route(web::get().to(get_person_name_by_id)),
+        )
+        .service(web::resource("/persons").route(web::get().to(get_persons)))
+        .service(web::resource("/person/{name}").route(web::post().to(insert_person)));
+}
+
 fn main() -> std::io::Result<()> {
-    let serv_addr = "127.0.0.1:8080";
-    println!("listening at addr: {}", serv_addr);
+    let config = Config::load("JSON_DB")?;
+    config.init_logger();
+    let serv_addr = config.server.bind_address.clone();
     let db_conn = web::Data::new(Mutex::new(AppState {
-        db: db_access::DbConnection::new(),
+        db: MemoryPersons::new(),
     }));
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
             .register_data(db_conn.clone())
-            .service(web::resource("/persons/ids").route(web::get().to(get_all_persons_ids)))
-            .service(
-                web::resource("/person/name_by_id/{id}")
-                    .route(web::get().to(get_person_name_by_id)),
-            )
-            .service(web::resource("/persons").route(web::get().to(get_persons)))
-            .service(web::resource("/person/{name}").route(web::post().to(insert_person)))
+            .configure(routes)
             .default_service(web::route().to(invalid_resource))
-    })
-    .bind(serv_addr)?
-    .run()
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&serv_addr, acceptor)?,
+        None => server.bind(&serv_addr)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&serv_addr)?;
+    println!("listening at addr: {}://{}", config.scheme(), serv_addr);
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+
+    fn get_body(uri: &str) -> String {
+        let mut db = MemoryPersons::new();
+        for name in &["Hamlet", "Élise", "Ophelia", "Annelise"] {
+            db.insert_person(Person {
+                name: name.to_string(),
+                ..Person::default()
+            })
+            .unwrap();
+        }
+        let mut app = test::init_service(
+            App::new()
+                .register_data(web::Data::new(Mutex::new(AppState { db })))
+                .configure(routes),
+        );
+        let body = test::read_response(&mut app, test::TestRequest::get().uri(uri).to_request());
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn finds_persons_by_partial_name_ignoring_case_and_accents() {
+        assert_eq!(
+            get_body("/persons?partial_name=ELIS"),
+            r#"[[2,"Élise"],[4,"Annelise"]]"#
+        );
+        assert_eq!(
+            get_body("/persons?partial_name=%C3%A9lis"),
+            r#"[[2,"Élise"],[4,"Annelise"]]"#
+        );
+    }
+
+    #[test]
+    fn finds_everyone_without_a_partial_name() {
+        assert_eq!(
+            get_body("/persons"),
+            r#"[[1,"Hamlet"],[2,"Élise"],[3,"Ophelia"],[4,"Annelise"]]"#
+        );
+    }
 }
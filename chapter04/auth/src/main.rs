@@ -1,16 +1,29 @@
-mod db_access;
-
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
-use actix_web_httpauth::extractors::basic::{BasicAuth, Config};
-use lazy_static::lazy_static;
-use serde_derive::Deserialize;
+use actix_web::dev::{Service, ServiceRequest};
+use actix_web::http::cookie::SameSite;
+use actix_web::http::{Cookie, Method};
+use actix_web::{
+    middleware, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder,
+};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use serde_derive::{Deserialize, Serialize};
+use server_config::Config;
 use std::sync::Mutex;
 
-use db_access::DbPrivilege;
-use db_access::Person;
+use persons_domain::{
+    date_to_secs, format_timestamp, now_secs, AppState, AuditEntry, AuditQuery, DbConnection,
+    FieldChange, FieldError, Permission, Person, PersonsFilter, PersonsQuery, Role, User,
+};
+use persons_web::{
+    render_page, resolve_session, set_templates, AppError, Authorized, CanDelete, CanExport,
+    CanInsert, CanManageUsers, CanRead, CanUpdate, CanViewAudit, LoggedUser, PageError,
+};
+
+const SESSION_COOKIE: &str = "session";
 
-struct AppState {
-    db: db_access::DbConnection,
+/// How the session cookie is sent back by the browsers.
+struct SessionCookie {
+    /// Only over HTTPS, when TLS is configured.
+    secure: bool,
 }
 
 #[derive(Deserialize)]
@@ -19,77 +32,181 @@ pub struct ToDelete {
 }
 
 #[derive(Deserialize)]
-pub struct ToInsert {
+pub struct ToSave {
+    id: Option<u32>,
     name: Option<String>,
+    email: Option<String>,
+    birth_date: Option<String>,
+    phone: Option<String>,
+    notes: Option<String>,
+}
+
+impl ToSave {
+    /// Builds the person to save, failing with an error for each invalid field.
+    fn to_person(&self) -> Result<Person, Vec<FieldError>> {
+        let mut person = Person {
+            id: self.id.unwrap_or(0),
+            name: self.name.clone().unwrap_or_default(),
+            email: self.email.clone(),
+            birth_date: self.birth_date.clone(),
+            phone: self.phone.clone(),
+            notes: self.notes.clone(),
+            ..Person::default()
+        };
+        person.normalize();
+        person.validate()?;
+        Ok(person)
+    }
 }
 
 #[derive(Deserialize)]
-pub struct ToUpdate {
-    id: Option<u32>,
-    name: Option<String>,
+pub struct AuditFilter {
+    username: Option<String>,
+    /// The first day, as "YYYY-MM-DD".
+    from: Option<String>,
+    /// The last day, included, as "YYYY-MM-DD".
+    to: Option<String>,
 }
 
+impl AuditFilter {
+    /// Builds the query, failing on an invalid date; empty values are ignored.
+    fn to_query(&self) -> Result<AuditQuery, AppError> {
+        let day_start = |value: &Option<String>| -> Result<Option<u64>, AppError> {
+            match value.as_deref().filter(|d| !d.is_empty()) {
+                Some(date) => date_to_secs(date)
+                    .map(Some)
+                    .ok_or_else(|| AppError::BadRequest(format!("Invalid date: {}", date))),
+                None => Ok(None),
+            }
+        };
+        Ok(AuditQuery {
+            username: self.username.clone().filter(|u| !u.is_empty()),
+            since: day_start(&self.from)?,
+            // Ends at the start of the next day.
+            until: day_start(&self.to)?.map(|t| t + 24 * 60 * 60),
+        })
+    }
+}
+
+/// An audit entry as shown in the audit page.
+#[derive(Serialize)]
+struct AuditRow<'a> {
+    entry: &'a AuditEntry,
+    time: String,
+    changes: Vec<FieldChange>,
+}
+
+/// A form body rather than a query string,
+/// so that the password is not logged with the URL.
 #[derive(Deserialize)]
-pub struct Filter {
-    partial_name: Option<String>,
+pub struct ToInsertUser {
+    username: Option<String>,
+    password: Option<String>,
+    role: Option<String>,
 }
 
-lazy_static! {
-    pub static ref TERA: tera::Tera = tera::Tera::new("templates/**/*").unwrap();
+/// A form body, as `ToInsertUser`.
+#[derive(Deserialize)]
+pub struct ToUpdatePassword {
+    username: Option<String>,
+    password: Option<String>,
 }
 
-fn check_cretentials(
-    auth: BasicAuth,
-    state: &web::Data<Mutex<AppState>>,
-    required_privilege: DbPrivilege,
-) -> Result<Vec<DbPrivilege>, String> {
-    let db_conn = &state.lock().unwrap().db;
-    if let Some(user) = db_conn.get_user_by_username(auth.user_id()) {
-        if auth.password().is_some() && &user.password == auth.password().unwrap() {
-            if user.privileges.contains(&required_privilege) {
-                Ok(user.privileges.clone())
-            } else {
-                Err(format!(
-                    "Insufficient privileges for user: {}",
-                    user.username
-                ))
-            }
-        } else {
-            Err(format!("Invalid password for user {}", user.username))
-        }
-    } else {
-        Err(format!("User {} not found", auth.user_id()))
+#[derive(Deserialize)]
+pub struct ToUpdateRole {
+    username: Option<String>,
+    role: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ToUpdateEnabled {
+    username: Option<String>,
+    enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ToRevokeSessions {
+    username: Option<String>,
+}
+
+/// The token of the session cookie of the request, if any.
+fn session_token(req: &ServiceRequest) -> Option<String> {
+    // A custom header cannot be added by cross-site forms,
+    // so it protects the commands from request forgery.
+    if req.method() != Method::GET && !req.headers().contains_key("x-requested-with") {
+        return None;
     }
+    Some(req.cookie(SESSION_COOKIE)?.value().to_string())
 }
 
-fn get_main() -> impl Responder {
-    let context = tera::Context::new();
+/// Puts the user of the session cookie of the request, if any, in its extensions.
+fn attach_logged_user(req: &ServiceRequest, state: &Mutex<AppState>) {
+    if let Some(logged_user) = session_token(req).and_then(|token| resolve_session(token, state)) {
+        req.extensions_mut().insert(logged_user);
+    }
+}
+
+fn login(
+    auth: BasicAuth,
+    state: web::Data<Mutex<AppState>>,
+    session_cookie: web::Data<SessionCookie>,
+) -> Result<HttpResponse, AppError> {
+    let mut state = state.lock().unwrap();
+    let user = state
+        .db
+        .check_credentials(auth.user_id(), auth.password().map(|p| p.as_ref()))
+        .map_err(AppError::Unauthorized)?;
+    let token = state.sessions.issue(&user.username);
+    Ok(HttpResponse::Ok()
+        .cookie(
+            Cookie::build(SESSION_COOKIE, token)
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Strict)
+                .secure(session_cookie.secure)
+                .finish(),
+        )
+        .content_type("text/plain")
+        .body(user.username))
+}
+
+fn logout(logged_user: LoggedUser, state: web::Data<Mutex<AppState>>) -> HttpResponse {
+    state.lock().unwrap().sessions.revoke(&logged_user.token);
     HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("main.html", &context).unwrap())
+        .del_cookie(&Cookie::build(SESSION_COOKIE, "").path("/").finish())
+        .content_type("text/plain")
+        .body("1")
+}
+
+fn get_main() -> Result<HttpResponse, PageError> {
+    Ok(render_page("main.html", &tera::Context::new())?)
 }
 
 fn get_page_persons(
-    query: web::Query<Filter>,
-    auth: BasicAuth,
+    query: web::Query<PersonsFilter>,
+    auth: Authorized<CanRead>,
     state: web::Data<Mutex<AppState>>,
-) -> HttpResponse {
-    match check_cretentials(auth, &state, DbPrivilege::CanRead) {
-        Ok(privileges) => {
-            let partial_name = &query.partial_name.clone().unwrap_or_else(|| "".to_string());
-            let db_conn = &state.lock().unwrap().db;
-            let person_list = db_conn.get_persons_by_partial_name(&partial_name);
-            let mut context = tera::Context::new();
-            context.insert("can_write", &privileges.contains(&DbPrivilege::CanWrite));
-            context.insert("id_error", &"");
-            context.insert("partial_name", &partial_name);
-            context.insert("persons", &person_list.collect::<Vec<_>>());
-            HttpResponse::Ok()
-                .content_type("text/html")
-                .body(TERA.render("persons.html", &context).unwrap())
-        }
-        Err(msg) => get_page_login_with_message(&msg),
-    }
+) -> Result<HttpResponse, PageError> {
+    let db_conn = &state.lock().unwrap().db;
+    let mut context = tera::Context::new();
+    context.insert("permissions", auth.user.role.permissions());
+    insert_persons_page(&mut context, db_conn, &query.to_query())?;
+    Ok(render_page("persons.html", &context)?)
+}
+
+/// Inserts in the context a page of the persons matching the query,
+/// with the values needed by the pagination controls.
+fn insert_persons_page(
+    context: &mut tera::Context,
+    db_conn: &DbConnection,
+    query: &PersonsQuery,
+) -> Result<(), AppError> {
+    let page = db_conn.query_persons(query).map_err(AppError::Storage)?;
+    context.insert("query", query);
+    context.insert("persons", &page.persons);
+    context.insert("total", &page.total);
+    context.insert("page_count", &page.page_count());
+    Ok(())
 }
 
 fn get_favicon() -> impl Responder {
@@ -98,182 +215,602 @@ fn get_favicon() -> impl Responder {
         .body(include_bytes!("favicon.ico") as &[u8])
 }
 
+/// Parses a comma-separated list of ids, failing on the first invalid one.
+fn parse_id_list(id_list: &str) -> Result<Vec<u32>, AppError> {
+    id_list
+        .split_terminator(',')
+        .map(|id| {
+            id.trim()
+                .parse::<u32>()
+                .map_err(|_| AppError::BadRequest(format!("Invalid id: {}", id)))
+        })
+        .collect()
+}
+
 fn delete_persons(
     query: web::Query<ToDelete>,
-    auth: BasicAuth,
+    auth: Authorized<CanDelete>,
     state: web::Data<Mutex<AppState>>,
-) -> HttpResponse {
-    match check_cretentials(auth, &state, DbPrivilege::CanWrite) {
-        Ok(_) => {
-            let db_conn = &mut state.lock().unwrap().db;
-            let mut deleted_count = 0;
-            query
-                .id_list
-                .clone()
-                .unwrap_or_else(|| "".to_string())
-                .split_terminator(",")
-                .for_each(|id| {
-                    deleted_count += if db_conn.delete_by_id(id.parse::<u32>().unwrap()) {
-                        1
-                    } else {
-                        0
-                    };
-                });
-            HttpResponse::Ok()
-                .content_type("text/plain")
-                .body(deleted_count.to_string())
+) -> Result<HttpResponse, AppError> {
+    let ids = parse_id_list(query.id_list.as_ref().map_or("", |s| s))?;
+    if ids.len() > 1 && !auth.user.has_permission(Permission::BulkDelete) {
+        return Err(AppError::insufficient_privileges(&auth.user.username));
+    }
+    let db_conn = &mut state.lock().unwrap().db;
+    let mut deleted_count = 0;
+    for id in ids {
+        if db_conn
+            .delete_by_id(&auth.user.username, id)
+            .map_err(AppError::Storage)?
+        {
+            deleted_count += 1;
         }
-        Err(msg) => get_page_login_with_message(&msg),
     }
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain")
+        .body(deleted_count.to_string()))
 }
 
-fn get_page_new_person(auth: BasicAuth, state: web::Data<Mutex<AppState>>) -> HttpResponse {
-    match check_cretentials(auth, &state, DbPrivilege::CanWrite) {
-        Ok(privileges) => {
-            let mut context = tera::Context::default();
-            context.insert("can_write", &privileges.contains(&DbPrivilege::CanWrite));
-            context.insert("person_id", &"");
-            context.insert("person_name", &"");
-            context.insert("inserting", &true);
-            HttpResponse::Ok()
-                .content_type("text/html")
-                .body(TERA.render("one_person.html", &context).unwrap())
-        }
-        Err(msg) => get_page_login_with_message(&msg),
+/// Downloads the persons matching the filter as a CSV file.
+fn export_persons(
+    query: web::Query<PersonsFilter>,
+    _auth: Authorized<CanExport>,
+    state: web::Data<Mutex<AppState>>,
+) -> Result<HttpResponse, AppError> {
+    // Exports all the matching persons, in the order shown, ignoring the pages.
+    let mut query = query.to_query();
+    query.page = 1;
+    query.page_size = u32::MAX;
+    let db_conn = &state.lock().unwrap().db;
+    let person_list = db_conn
+        .query_persons(&query)
+        .map_err(AppError::Storage)?
+        .persons;
+    let mut csv = "id,name,email,birth_date,phone,notes\n".to_string();
+    for person in person_list {
+        csv += &format!(
+            "{},{},{},{},{},{}\n",
+            person.id,
+            csv_field(&person.name),
+            csv_field(person.email.as_ref().map_or("", |s| s)),
+            csv_field(person.birth_date.as_ref().map_or("", |s| s)),
+            csv_field(person.phone.as_ref().map_or("", |s| s)),
+            csv_field(person.notes.as_ref().map_or("", |s| s)),
+        );
     }
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv")
+        .header(
+            "Content-Disposition",
+            "attachment; filename=\"persons.csv\"",
+        )
+        .body(csv))
+}
+
+/// Quotes a CSV field, doubling any embedded quote.
+fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+fn get_page_new_person(auth: Authorized<CanInsert>) -> Result<HttpResponse, PageError> {
+    let mut context = tera::Context::default();
+    context.insert("permissions", auth.user.role.permissions());
+    context.insert("person", &Person::default());
+    context.insert("inserting", &true);
+    Ok(render_page("one_person.html", &context)?)
 }
 
 fn get_page_edit_person(
     state: web::Data<Mutex<AppState>>,
-    auth: BasicAuth,
+    auth: Authorized<CanRead>,
     path: web::Path<(String,)>,
-) -> HttpResponse {
-    match check_cretentials(auth, &state, DbPrivilege::CanRead) {
-        Ok(privileges) => {
-            let id = &path.0;
-            let db_conn = &state.lock().unwrap().db;
-            let mut context = tera::Context::new();
-            context.insert("can_write", &privileges.contains(&DbPrivilege::CanWrite));
-            if let Ok(id_n) = id.parse::<u32>() {
-                if let Some(person) = db_conn.get_person_by_id(id_n) {
-                    context.insert("person_id", &id);
-                    context.insert("person_name", &person.name);
-                    context.insert("inserting", &false);
-                    return HttpResponse::Ok()
-                        .content_type("text/html")
-                        .body(TERA.render("one_person.html", &context).unwrap());
-                }
-            }
-            let person_list = db_conn.get_persons_by_partial_name(&"");
-            context.insert("id_error", &"Person id not found");
-            context.insert("partial_name", &"");
-            context.insert("persons", &person_list.collect::<Vec<_>>());
-
-            HttpResponse::Ok()
-                .content_type("text/html")
-                .body(TERA.render("persons.html", &context).unwrap())
-        }
-        Err(msg) => get_page_login_with_message(&msg),
-    }
+) -> Result<HttpResponse, PageError> {
+    let id = &path.0;
+    let id_n = id
+        .parse::<u32>()
+        .map_err(|_| AppError::BadRequest(format!("Invalid id: {}", id)))?;
+    let db_conn = &state.lock().unwrap().db;
+    let person = db_conn
+        .get_person_by_id(id_n)
+        .map_err(AppError::Storage)?
+        .ok_or_else(|| AppError::NotFound(format!("Person {} not found", id_n)))?;
+    let mut context = tera::Context::new();
+    context.insert("permissions", auth.user.role.permissions());
+    context.insert("person", &person);
+    context.insert("inserting", &false);
+    Ok(render_page("one_person.html", &context)?)
 }
 
 fn insert_person(
     state: web::Data<Mutex<AppState>>,
-    query: web::Query<ToInsert>,
-    auth: BasicAuth,
-) -> HttpResponse {
-    match check_cretentials(auth, &state, DbPrivilege::CanWrite) {
-        Ok(_) => {
-            let db_conn = &mut state.lock().unwrap().db;
-            let mut inserted_count = 0;
-            if let Some(name) = &query.name.clone() {
-                inserted_count += db_conn.insert_person(Person {
-                    id: 0,
-                    name: name.clone(),
-                });
-            }
-            HttpResponse::Ok()
-                .content_type("text/plain")
-                .body(inserted_count.to_string())
-        }
-        Err(msg) => get_page_login_with_message(&msg),
-    }
+    query: web::Query<ToSave>,
+    auth: Authorized<CanInsert>,
+) -> Result<HttpResponse, AppError> {
+    let mut person = query.to_person().map_err(AppError::InvalidFields)?;
+    person.created_at = now_secs();
+    person.updated_at = person.created_at;
+    let db_conn = &mut state.lock().unwrap().db;
+    db_conn
+        .insert_person(&auth.user.username, person)
+        .map_err(AppError::Storage)?;
+    Ok(HttpResponse::Ok().content_type("text/plain").body("1"))
 }
 
 fn update_person(
-    auth: BasicAuth,
+    auth: Authorized<CanUpdate>,
     state: web::Data<Mutex<AppState>>,
-    query: web::Query<ToUpdate>,
-) -> HttpResponse {
-    match check_cretentials(auth, &state, DbPrivilege::CanWrite) {
-        Ok(_) => {
-            let db_conn = &mut state.lock().unwrap().db;
-            let mut updated_count = 0;
-            let id = query.id.unwrap_or(0);
-            let name = query.name.clone().unwrap_or_else(|| "".to_string()).clone();
-            updated_count += if db_conn.update_person(Person { id, name }) {
-                1
-            } else {
-                0
-            };
-            HttpResponse::Ok()
-                .content_type("text/plain")
-                .body(updated_count.to_string())
-        }
-        Err(msg) => get_page_login_with_message(&msg),
+    query: web::Query<ToSave>,
+) -> Result<HttpResponse, AppError> {
+    let id = query
+        .id
+        .ok_or_else(|| AppError::BadRequest("Missing id".to_string()))?;
+    let mut person = query.to_person().map_err(AppError::InvalidFields)?;
+    person.updated_at = now_secs();
+    let db_conn = &mut state.lock().unwrap().db;
+    if !db_conn
+        .update_person(&auth.user.username, person)
+        .map_err(AppError::Storage)?
+    {
+        return Err(AppError::NotFound(format!("Person {} not found", id)));
     }
+    Ok(HttpResponse::Ok().content_type("text/plain").body("1"))
 }
 
-fn invalid_resource() -> impl Responder {
+fn get_page_audit(
+    query: web::Query<AuditFilter>,
+    _auth: Authorized<CanViewAudit>,
+    state: web::Data<Mutex<AppState>>,
+) -> Result<HttpResponse, PageError> {
+    let audit_query = query.to_query()?;
+    let db_conn = &state.lock().unwrap().db;
+    let entries = db_conn
+        .query_audit(&audit_query)
+        .map_err(AppError::Storage)?;
+    let rows: Vec<AuditRow> = entries
+        .iter()
+        .map(|entry| AuditRow {
+            entry,
+            time: format_timestamp(entry.timestamp),
+            changes: entry.changes(),
+        })
+        .collect();
     let mut context = tera::Context::new();
-    context.insert("can_write", &false);
-    context.insert("id_error", &"Invalid request.");
-    context.insert("partial_name", &"");
-    context.insert("persons", &Vec::<Person>::new());
+    context.insert("username", &query.username.clone().unwrap_or_default());
+    context.insert("from", &query.from.clone().unwrap_or_default());
+    context.insert("to", &query.to.clone().unwrap_or_default());
+    context.insert("rows", &rows);
+    Ok(render_page("audit.html", &context)?)
+}
 
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("persons.html", &context).unwrap())
+/// Returns the matching audit entries as JSON, the newest first.
+fn get_audit(
+    query: web::Query<AuditFilter>,
+    _auth: Authorized<CanViewAudit>,
+    state: web::Data<Mutex<AppState>>,
+) -> Result<HttpResponse, AppError> {
+    let audit_query = query.to_query()?;
+    let db_conn = &state.lock().unwrap().db;
+    let entries = db_conn
+        .query_audit(&audit_query)
+        .map_err(AppError::Storage)?;
+    Ok(HttpResponse::Ok().json(entries))
 }
 
-fn get_page_login() -> HttpResponse {
-    get_page_login_with_message("")
+fn get_page_users(
+    _auth: Authorized<CanManageUsers>,
+    state: web::Data<Mutex<AppState>>,
+) -> Result<HttpResponse, PageError> {
+    let db_conn = &state.lock().unwrap().db;
+    let users = db_conn.get_all_users().map_err(AppError::Storage)?;
+    let mut context = tera::Context::new();
+    context.insert("users", &users);
+    context.insert(
+        "role_names",
+        &Role::ALL.iter().map(|r| r.name()).collect::<Vec<_>>(),
+    );
+    Ok(render_page("users.html", &context)?)
+}
+
+fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= 32
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn insert_user(
+    _auth: Authorized<CanManageUsers>,
+    state: web::Data<Mutex<AppState>>,
+    form: web::Form<ToInsertUser>,
+) -> Result<HttpResponse, AppError> {
+    let (username, password) = match (&form.username, &form.password) {
+        (Some(username), Some(password)) if is_valid_username(username) && !password.is_empty() => {
+            (username, password)
+        }
+        _ => return Err(bad_request("A valid username and a password are required.")),
+    };
+    let role = form
+        .role
+        .as_ref()
+        .and_then(|r| Role::from_name(r))
+        .ok_or_else(|| bad_request("A valid role is required."))?;
+    let user = User::new(username, password, role).map_err(AppError::Storage)?;
+    let db_conn = &mut state.lock().unwrap().db;
+    if !db_conn.insert_user(user).map_err(AppError::Storage)? {
+        return Err(bad_request(&format!("User {} already exists.", username)));
+    }
+    Ok(HttpResponse::Ok().content_type("text/plain").body("1"))
 }
-fn get_page_login_with_message(error_message: &str) -> HttpResponse {
+
+/// Loads the user named `username`, lets `change` modify it, and saves it.
+fn change_user(
+    state: &web::Data<Mutex<AppState>>,
+    username: &str,
+    change: impl FnOnce(&mut User) -> Result<(), String>,
+) -> Result<HttpResponse, AppError> {
+    let user_not_found = || AppError::NotFound(format!("User {} not found.", username));
+    let db_conn = &mut state.lock().unwrap().db;
+    let mut user = db_conn
+        .get_user_by_username(username)
+        .map_err(AppError::Storage)?
+        .ok_or_else(user_not_found)?;
+    change(&mut user).map_err(AppError::BadRequest)?;
+    if !db_conn.update_user(user).map_err(AppError::Storage)? {
+        return Err(user_not_found());
+    }
+    Ok(HttpResponse::Ok().content_type("text/plain").body("1"))
+}
+
+fn update_user_password(
+    auth: Authorized<CanRead>,
+    state: web::Data<Mutex<AppState>>,
+    form: web::Form<ToUpdatePassword>,
+) -> Result<HttpResponse, AppError> {
+    let (username, password) = match (&form.username, &form.password) {
+        (Some(username), Some(password)) if !password.is_empty() => (username, password),
+        _ => return Err(bad_request("A username and a new password are required.")),
+    };
+    // Every user may change their own password,
+    // only user managers may change the password of other users.
+    if *username != auth.user.username && !auth.user.has_permission(Permission::ManageUsers) {
+        return Err(AppError::insufficient_privileges(&auth.user.username));
+    }
+    let response = change_user(&state, username, |user| user.set_password(password))?;
+    // Whoever knew the old password must log in again.
+    state
+        .lock()
+        .unwrap()
+        .sessions
+        .revoke_user(username, Some(&auth.token));
+    Ok(response)
+}
+
+fn update_user_role(
+    auth: Authorized<CanManageUsers>,
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<ToUpdateRole>,
+) -> Result<HttpResponse, AppError> {
+    let username = query
+        .username
+        .clone()
+        .ok_or_else(|| bad_request("A username is required."))?;
+    // Prevents user managers from locking themselves out.
+    if username == auth.user.username {
+        return Err(bad_request("You cannot change your own role."));
+    }
+    let role = query
+        .role
+        .as_ref()
+        .and_then(|r| Role::from_name(r))
+        .ok_or_else(|| bad_request("A valid role is required."))?;
+    change_user(&state, &username, |user| {
+        user.role = role;
+        Ok(())
+    })
+}
+
+fn update_user_enabled(
+    auth: Authorized<CanManageUsers>,
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<ToUpdateEnabled>,
+) -> Result<HttpResponse, AppError> {
+    let (username, enabled) = match (&query.username, query.enabled) {
+        (Some(username), Some(enabled)) => (username.clone(), enabled),
+        _ => return Err(bad_request("A username and the enabled flag are required.")),
+    };
+    // Prevents user managers from locking themselves out.
+    if username == auth.user.username {
+        return Err(bad_request("You cannot disable yourself."));
+    }
+    let response = change_user(&state, &username, |user| {
+        user.enabled = enabled;
+        Ok(())
+    })?;
+    if !enabled {
+        state.lock().unwrap().sessions.revoke_user(&username, None);
+    }
+    Ok(response)
+}
+
+fn revoke_user_sessions(
+    _auth: Authorized<CanManageUsers>,
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<ToRevokeSessions>,
+) -> Result<HttpResponse, AppError> {
+    let username = query
+        .username
+        .as_ref()
+        .ok_or_else(|| bad_request("A username is required."))?;
+    let revoked_count = state.lock().unwrap().sessions.revoke_user(username, None);
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain")
+        .body(revoked_count.to_string()))
+}
+
+fn invalid_resource(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    println!("Invalid URI: \"{}\"", req.uri());
+    Err(AppError::NotFound("Invalid request.".to_string()).for_request(&req))
+}
+
+fn bad_request(error_message: &str) -> AppError {
+    AppError::BadRequest(error_message.to_string())
+}
+
+fn get_page_login() -> Result<HttpResponse, PageError> {
     let mut context = tera::Context::new();
-    context.insert("error_message", error_message);
+    context.insert("error_message", "");
+    Ok(render_page("login.html", &context)?)
+}
 
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("login.html", &context).unwrap())
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/").route(web::get().to(get_main)))
+        .service(web::resource("/login").route(web::post().to(login)))
+        .service(web::resource("/logout").route(web::post().to(logout)))
+        .service(web::resource("/page/login").route(web::get().to(get_page_login)))
+        .service(web::resource("/page/persons").route(web::get().to(get_page_persons)))
+        .service(web::resource("/persons").route(web::delete().to(delete_persons)))
+        .service(web::resource("/persons/export").route(web::get().to(export_persons)))
+        .service(web::resource("/page/new_person").route(web::get().to(get_page_new_person)))
+        .service(web::resource("/page/edit_person/{id}").route(web::get().to(get_page_edit_person)))
+        .service(
+            web::resource("/one_person")
+                .route(web::post().to(insert_person))
+                .route(web::put().to(update_person)),
+        )
+        .service(web::resource("/page/audit").route(web::get().to(get_page_audit)))
+        .service(web::resource("/audit").route(web::get().to(get_audit)))
+        .service(web::resource("/page/users").route(web::get().to(get_page_users)))
+        .service(web::resource("/user").route(web::post().to(insert_user)))
+        .service(web::resource("/user/password").route(web::put().to(update_user_password)))
+        .service(web::resource("/user/role").route(web::put().to(update_user_role)))
+        .service(web::resource("/user/enabled").route(web::put().to(update_user_enabled)))
+        .service(web::resource("/user/sessions").route(web::delete().to(revoke_user_sessions)))
+        .service(web::resource("/favicon.ico").route(web::get().to(get_favicon)));
 }
 
 fn main() -> std::io::Result<()> {
-    let server_address = "127.0.0.1:8080";
-    println!("listening at address: {}", server_address);
+    let config = Config::load("AUTH")?;
+    config.init_logger();
+    let templates = tera::Tera::new(&config.templates_glob())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    set_templates(templates);
+    let server_address = config.server.bind_address.clone();
+
+    let to_io_error = |e: String| std::io::Error::new(std::io::ErrorKind::Other, e);
+
+    // With a database file, configured or given as argument, the persons,
+    // the users and the audit log are kept in SQLite, otherwise they are
+    // kept in memory.
+    let database_file = config
+        .storage
+        .database_file
+        .clone()
+        .or_else(|| config.args.first().cloned());
+    let mut db = DbConnection::open(database_file.as_deref()).map_err(to_io_error)?;
+    // The first user can only be created from the environment.
+    let admin_password = std::env::var("AUTH_ADMIN_PASSWORD").ok();
+    db.bootstrap_admin(admin_password.as_deref())
+        .map_err(to_io_error)?;
 
-    let db_conn = web::Data::new(Mutex::new(AppState {
-        db: db_access::DbConnection::new(),
-    }));
+    let db_conn = web::Data::new(Mutex::new(AppState::new(db)));
+    let session_cookie = web::Data::new(SessionCookie {
+        secure: config.server.tls.is_some(),
+    });
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
+        let state = db_conn.clone();
         App::new()
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
             .register_data(db_conn.clone())
-            .service(web::resource("/").route(web::get().to(get_main)))
-            .service(web::resource("/page/persons").route(web::get().to(get_page_persons)))
-            .service(web::resource("/persons").route(web::delete().to(delete_persons)))
-            .service(web::resource("/page/new_person").route(web::get().to(get_page_new_person)))
-            .service(
-                web::resource("/page/edit_person/{id}").route(web::get().to(get_page_edit_person)),
-            )
-            .service(
-                web::resource("/one_person")
-                    .route(web::post().to(insert_person))
-                    .route(web::put().to(update_person)),
-            )
-            .service(web::resource("/favicon.ico").route(web::get().to(get_favicon)))
+            .register_data(session_cookie.clone())
+            .wrap_fn(move |req, srv| {
+                attach_logged_user(&req, &state);
+                srv.call(req)
+            })
+            .configure(routes)
             .default_service(web::get().to(invalid_resource))
-    })
-    .bind(server_address)?
-    .run()
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&server_address, acceptor)?,
+        None => server.bind(&server_address)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&server_address)?;
+    println!(
+        "listening at address: {}://{}",
+        config.scheme(),
+        server_address
+    );
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use persons_domain::{MemoryAudit, MemoryPersons, MemoryUsers};
+
+    /// The authorization headers of "admin", with password "xadmin",
+    /// and of "joe", a viewer with password "xjoe".
+    const ADMIN: &str = "Basic YWRtaW46eGFkbWlu";
+    const JOE: &str = "Basic am9lOnhqb2U=";
+
+    fn app_state() -> web::Data<Mutex<AppState>> {
+        let mut db = DbConnection::new(
+            Box::new(MemoryPersons::new()),
+            Box::new(MemoryUsers::new()),
+            Box::new(MemoryAudit::new()),
+        );
+        db.bootstrap_admin(Some("xadmin")).unwrap();
+        db.insert_user(User::new("joe", "xjoe", Role::Viewer).unwrap())
+            .unwrap();
+        web::Data::new(Mutex::new(AppState::new(db)))
+    }
+
+    /// The application as run by `main`, without the logger and TLS.
+    macro_rules! init_app {
+        ($state:expr) => {{
+            let state = $state.clone();
+            test::init_service(
+                App::new()
+                    .register_data($state.clone())
+                    .register_data(web::Data::new(SessionCookie { secure: false }))
+                    .wrap_fn(move |req, srv| {
+                        attach_logged_user(&req, &state);
+                        srv.call(req)
+                    })
+                    .configure(routes),
+            )
+        }};
+    }
+
+    /// The session cookie set by logging in with the authorization header.
+    macro_rules! log_in {
+        ($app:expr, $authorization:expr) => {{
+            let request = TestRequest::post()
+                .uri("/login")
+                .header(header::AUTHORIZATION, $authorization)
+                .to_request();
+            let response = test::call_service(&mut $app, request);
+            assert_eq!(response.status(), StatusCode::OK);
+            let set_cookie = response.headers().get(header::SET_COOKIE).unwrap();
+            Cookie::parse(set_cookie.to_str().unwrap().to_string()).unwrap()
+        }};
+    }
+
+    /// A command sent with the session cookie, as sent by the pages.
+    fn command(request: TestRequest, cookie: &Cookie<'static>) -> TestRequest {
+        request
+            .cookie(cookie.clone())
+            .header("x-requested-with", "XMLHttpRequest")
+    }
+
+    #[test]
+    fn parses_only_lists_of_valid_ids() {
+        assert_eq!(parse_id_list("2, 7,").unwrap(), vec![2, 7]);
+        match parse_id_list("2,x,7") {
+            Err(AppError::BadRequest(message)) => assert_eq!(message, "Invalid id: x"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sets_a_strict_session_cookie_at_login() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let cookie = log_in!(app, ADMIN);
+        assert_eq!(cookie.name(), SESSION_COOKIE);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_ne!(cookie.secure(), Some(true));
+
+        let request = TestRequest::post()
+            .uri("/login")
+            .header(header::AUTHORIZATION, "Basic YWRtaW46eGpvZQ==")
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn accepts_commands_only_with_the_custom_header() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let cookie = log_in!(app, ADMIN);
+        let request = TestRequest::put()
+            .uri("/one_person?id=5&name=Banquo")
+            .cookie(cookie.clone())
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = command(
+            TestRequest::put().uri("/one_person?id=5&name=Banquo"),
+            &cookie,
+        );
+        let body = test::read_response(&mut app, request.to_request());
+        assert_eq!(body, r#"{"error":"Person 5 not found"}"#);
+    }
+
+    #[test]
+    fn allows_only_the_permissions_of_the_role() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let joe = log_in!(app, JOE);
+        let request = command(TestRequest::post().uri("/one_person?name=Lear"), &joe);
+        let body = test::read_response(&mut app, request.to_request());
+        assert_eq!(body, r#"{"error":"Insufficient privileges for user: joe"}"#);
+
+        let admin = log_in!(app, ADMIN);
+        let request = command(TestRequest::post().uri("/one_person?name=Lear"), &admin);
+        assert_eq!(test::read_response(&mut app, request.to_request()), "1");
+        let request = command(TestRequest::post().uri("/one_person?name="), &admin);
+        let response = test::call_service(&mut app, request.to_request());
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn inserts_users_from_the_form_and_changes_their_role() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let admin = log_in!(app, ADMIN);
+        let insert_user = |form: &str| {
+            command(TestRequest::post().uri("/user"), &admin)
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(form.to_string())
+                .to_request()
+        };
+        let request = insert_user("username=mary&password=xmary&role=Editor");
+        assert_eq!(test::read_response(&mut app, request), "1");
+        let request = insert_user("username=mary&password=xmary&role=Editor");
+        let body = test::read_response(&mut app, request);
+        assert_eq!(body, r#"{"error":"User mary already exists."}"#);
+        let request = insert_user("username=lear&password=xlear&role=King");
+        let body = test::read_response(&mut app, request);
+        assert_eq!(body, r#"{"error":"A valid role is required."}"#);
+
+        let mary = state.lock().unwrap().db.get_user_by_username("mary");
+        assert_eq!(mary.unwrap().unwrap().role, Role::Editor);
+
+        let request = command(
+            TestRequest::put().uri("/user/role?username=mary&role=Viewer"),
+            &admin,
+        );
+        assert_eq!(test::read_response(&mut app, request.to_request()), "1");
+        let mary = state.lock().unwrap().db.get_user_by_username("mary");
+        assert_eq!(mary.unwrap().unwrap().role, Role::Viewer);
+
+        let request = command(
+            TestRequest::put().uri("/user/role?username=admin&role=Viewer"),
+            &admin,
+        );
+        let body = test::read_response(&mut app, request.to_request());
+        assert_eq!(body, r#"{"error":"You cannot change your own role."}"#);
+    }
 }
@@ -2,28 +2,39 @@
 The client can send two possible kinds of messages:
 - sendCommand: sends a specified REST command
 - getPage: requests HTML code that will be assigned to the body
+The credentials are sent only by login; then the server
+recognizes the user by the session cookie it has set.
 */
 var username = '';
 
-var password = '';
-
 function sendCommand(method, uri, body, success, failure) {
     var xhttp = new XMLHttpRequest();
     xhttp.onreadystatechange = function() {
         if (this.readyState == 4)
             if (this.status == 200) success();
-            else failure();
+            else failure(this);
     };
     xhttp.open(method, uri, true);
-    xhttp.setRequestHeader("Authorization",
-        "Basic " + btoa(username + ":" + password));
+    xhttp.setRequestHeader("X-Requested-With", "XMLHttpRequest");
+    if (body)
+        xhttp.setRequestHeader("Content-Type", "application/x-www-form-urlencoded");
     xhttp.send(body);
 }
 
+// Extracts the message of a failed command, or else returns the fallback.
+function errorMessage(xhttp, fallback) {
+    try {
+        return JSON.parse(xhttp.responseText).error || fallback;
+    } catch (e) {
+        return fallback;
+    }
+}
+
 function getPage(uri) {
     var xhttp = new XMLHttpRequest();
     xhttp.onreadystatechange = function() {
-        if (this.readyState == 4 && this.status == 200) {
+        // Error pages are shown as well, with their message.
+        if (this.readyState == 4 && this.status != 0) {
             document.getElementById('body')
                 .innerHTML = xhttp.responseText;
             var cur_user = document.getElementById('current_user');
@@ -32,45 +43,175 @@ function getPage(uri) {
         }
     };
     xhttp.open('GET', uri, true);
-    xhttp.setRequestHeader("Authorization",
-        "Basic " + btoa(username + ":" + password));
     xhttp.send();
 }
 
-function delete_selected_persons() {
+// Builds the query string of the persons page from its filters,
+// keeping the current sort order.
+function personsQuery(page) {
+    var value = function(id) {
+        return encodeURIComponent(document.getElementById(id).value);
+    };
+    return 'partial_name=' + value('name_portion')
+        + '&email=' + value('filter_email')
+        + '&phone=' + value('filter_phone')
+        + '&born_from=' + value('born_from')
+        + '&born_to=' + value('born_to')
+        + '&sort_by=' + value('sort_by')
+        + '&order=' + value('order')
+        + '&page=' + page
+        + '&page_size=' + value('page_size');
+}
+
+function filterPersons(page) {
+    getPage('/page/persons?' + personsQuery(page));
+}
+
+// Sorts by the given column, reversing the order if already sorted by it.
+function sortPersons(column) {
+    var sort_by = document.getElementById('sort_by');
+    var order = document.getElementById('order');
+    order.value = sort_by.value === column && order.value === 'asc' ? 'desc' : 'asc';
+    sort_by.value = column;
+    filterPersons(1);
+}
+
+// Builds the query string of the audit page from its filters.
+function auditQuery() {
+    var value = function(id) {
+        return encodeURIComponent(document.getElementById(id).value);
+    };
+    return 'username=' + value('audit_username')
+        + '&from=' + value('audit_from')
+        + '&to=' + value('audit_to');
+}
+
+function filterAudit() {
+    getPage('/page/audit?' + auditQuery());
+}
+
+function delete_selected_persons(can_bulk_delete) {
     var items;
     for (var item of document.getElementsByName('selector'))
         if (item.checked)
             if (items) items += ',' + item.id;
             else items = '' + item.id;
-    if (items)
+    if (items && items.indexOf(',') >= 0 && !can_bulk_delete)
+        alert('You can delete only one person at a time.');
+    else if (items)
         sendCommand('DELETE', '/persons?id_list=' + items, '',
-            function() { getPage('/page/persons'); },
-            function() { alert('Failed deletion.'); });
+            function() {
+                filterPersons(document.getElementById('page').value);
+            },
+            function(xhttp) { alert(errorMessage(xhttp, 'Failed deletion.')); });
+}
+
+// Shows the messages of a rejected form next to their fields,
+// returning false if the response does not contain field errors.
+function showFieldErrors(xhttp) {
+    for (var label of document.getElementsByClassName('field-error'))
+        label.textContent = '';
+    var errors;
+    try {
+        errors = JSON.parse(xhttp.responseText);
+    } catch (e) {
+        return false;
+    }
+    if (xhttp.status != 400 || !Array.isArray(errors))
+        return false;
+    for (var error of errors) {
+        var label = document.getElementById('error_' + error.field);
+        if (label) label.textContent = error.message;
+    }
+    return true;
 }
 
 function savePerson(method) {
-    sendCommand(method,
-        '/one_person?'
-        + (method === 'POST' ? '' :
-        'id='
-        + document.getElementById('person_id').value
-        + '&')
-        + 'name='
-        + encodeURIComponent(
-            document.getElementById('person_name')
-            .value),
-        '',
+    var uri = '/one_person?';
+    if (method !== 'POST')
+        uri += 'id=' + document.getElementById('person_id').value + '&';
+    uri += ['name', 'email', 'birth_date', 'phone', 'notes']
+        .map(function(field) {
+            return field + '=' + encodeURIComponent(
+                document.getElementById('person_' + field).value);
+        })
+        .join('&');
+    sendCommand(method, uri, '',
         function() {
             getPage('/page/persons');
         },
-        function() {
-            alert('Failed command.');
+        function(xhttp) {
+            if (!showFieldErrors(xhttp))
+                alert(errorMessage(xhttp, 'Failed command.'));
         });
 }
 
 function login() {
-    username = document.getElementById('username').value;
-    password = document.getElementById('password').value;
-    getPage('/page/persons');
+    var new_username = document.getElementById('username').value;
+    var password = document.getElementById('password').value;
+    var xhttp = new XMLHttpRequest();
+    xhttp.onreadystatechange = function() {
+        if (this.readyState == 4)
+            if (this.status == 200) {
+                username = new_username;
+                getPage('/page/persons');
+            }
+            else alert(errorMessage(xhttp, 'Failed login.'));
+    };
+    xhttp.open('POST', '/login', true);
+    xhttp.setRequestHeader("Authorization",
+        "Basic " + btoa(new_username + ":" + password));
+    xhttp.setRequestHeader("X-Requested-With", "XMLHttpRequest");
+    xhttp.send();
+}
+
+function logout() {
+    sendCommand('POST', '/logout', '',
+        function() {
+            username = '';
+            getPage('/page/login');
+        },
+        function() { getPage('/page/login'); });
+}
+
+function insertUser() {
+    // The password goes in the body, to be kept out of the logged URLs.
+    sendCommand('POST', '/user',
+        'username='
+        + encodeURIComponent(document.getElementById('new_username').value)
+        + '&password='
+        + encodeURIComponent(document.getElementById('new_password').value)
+        + '&role='
+        + encodeURIComponent(document.getElementById('new_role').value),
+        function() { getPage('/page/users'); },
+        function(xhttp) { alert(errorMessage(xhttp, 'Failed user creation.')); });
+}
+
+function updateUserRole(user) {
+    sendCommand('PUT',
+        '/user/role?username=' + encodeURIComponent(user)
+        + '&role=' + encodeURIComponent(
+            document.getElementById('role_' + user).value),
+        '',
+        function() { getPage('/page/users'); },
+        function(xhttp) { alert(errorMessage(xhttp, 'Failed role change.')); });
+}
+
+function updateUserEnabled(user, enabled) {
+    sendCommand('PUT',
+        '/user/enabled?username=' + encodeURIComponent(user)
+        + '&enabled=' + enabled,
+        '',
+        function() { getPage('/page/users'); },
+        function(xhttp) { alert(errorMessage(xhttp, 'Failed user change.')); });
+}
+
+function updateUserPassword(user) {
+    var new_password = prompt('New password for ' + user + ':');
+    if (new_password)
+        sendCommand('PUT', '/user/password',
+            'username=' + encodeURIComponent(user)
+            + '&password=' + encodeURIComponent(new_password),
+            function() { getPage('/page/users'); },
+            function(xhttp) { alert(errorMessage(xhttp, 'Failed password change.')); });
 }
@@ -1,14 +1,14 @@
-mod db_access;
-
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
-use lazy_static::lazy_static;
+use actix_web::{middleware, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use persons_domain::{
+    now_secs, FieldError, MemoryPersons, Person, PersonsFilter, PersonsQuery, PersonsStore,
+};
+use persons_web::{render_page, set_templates, AppError, PageError};
 use serde_derive::Deserialize;
+use server_config::Config;
 use std::sync::Mutex;
 
-use db_access::Person;
-
 struct AppState {
-    db: db_access::DbConnection,
+    db: MemoryPersons,
 }
 
 #[derive(Deserialize)]
@@ -17,47 +17,60 @@ pub struct ToDelete {
 }
 
 #[derive(Deserialize)]
-pub struct ToInsert {
-    name: Option<String>,
-}
-
-#[derive(Deserialize)]
-pub struct ToUpdate {
+pub struct ToSave {
     id: Option<u32>,
     name: Option<String>,
+    email: Option<String>,
+    birth_date: Option<String>,
+    phone: Option<String>,
+    notes: Option<String>,
 }
 
-#[derive(Deserialize)]
-pub struct Filter {
-    partial_name: Option<String>,
-}
-
-lazy_static! {
-    pub static ref TERA: tera::Tera = tera::Tera::new("templates/**/*").unwrap();
+impl ToSave {
+    /// Builds the person to save, failing with an error for each invalid field.
+    fn to_person(&self) -> Result<Person, Vec<FieldError>> {
+        let mut person = Person {
+            id: self.id.unwrap_or(0),
+            name: self.name.clone().unwrap_or_default(),
+            email: self.email.clone(),
+            birth_date: self.birth_date.clone(),
+            phone: self.phone.clone(),
+            notes: self.notes.clone(),
+            ..Person::default()
+        };
+        person.normalize();
+        person.validate()?;
+        Ok(person)
+    }
 }
 
-fn get_main() -> impl Responder {
-    let context = tera::Context::new();
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("main.html", &context).unwrap())
+fn get_main() -> Result<HttpResponse, PageError> {
+    Ok(render_page("main.html", &tera::Context::new())?)
 }
 
 fn get_page_persons(
-    query: web::Query<Filter>,
+    query: web::Query<PersonsFilter>,
     state: web::Data<Mutex<AppState>>,
-) -> impl Responder {
-    let partial_name = &query.partial_name.clone().unwrap_or_else(|| "".to_string());
+) -> Result<HttpResponse, PageError> {
     let db_conn = &state.lock().unwrap().db;
-    let person_list = db_conn.get_persons_by_partial_name(&partial_name);
     let mut context = tera::Context::new();
-    context.insert("id_error", &"");
-    context.insert("partial_name", &partial_name);
-    context.insert("persons", &person_list.collect::<Vec<_>>());
+    insert_persons_page(&mut context, db_conn, &query.to_query())?;
+    Ok(render_page("persons.html", &context)?)
+}
 
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("persons.html", &context).unwrap())
+/// Inserts in the context a page of the persons matching the query,
+/// with the values needed by the pagination controls.
+fn insert_persons_page(
+    context: &mut tera::Context,
+    db_conn: &MemoryPersons,
+    query: &PersonsQuery,
+) -> Result<(), AppError> {
+    let page = db_conn.query_persons(query).map_err(AppError::Storage)?;
+    context.insert("query", query);
+    context.insert("persons", &page.persons);
+    context.insert("total", &page.total);
+    context.insert("page_count", &page.page_count());
+    Ok(())
 }
 
 fn get_favicon() -> impl Responder {
@@ -66,128 +79,249 @@ fn get_favicon() -> impl Responder {
         .body(include_bytes!("favicon.ico") as &[u8])
 }
 
+/// Parses a comma-separated list of ids, failing on the first invalid one.
+fn parse_id_list(id_list: &str) -> Result<Vec<u32>, AppError> {
+    id_list
+        .split_terminator(',')
+        .map(|id| {
+            id.trim()
+                .parse::<u32>()
+                .map_err(|_| AppError::BadRequest(format!("Invalid id: {}", id)))
+        })
+        .collect()
+}
+
 fn delete_persons(
     query: web::Query<ToDelete>,
     state: web::Data<Mutex<AppState>>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
+    let ids = parse_id_list(query.id_list.as_ref().map_or("", |s| s))?;
     let db_conn = &mut state.lock().unwrap().db;
     let mut deleted_count = 0;
-    query
-        .id_list
-        .clone()
-        .unwrap_or_else(|| "".to_string())
-        .split_terminator(",")
-        .for_each(|id| {
-            deleted_count += if db_conn.delete_by_id(id.parse::<u32>().unwrap()) {
-                1
-            } else {
-                0
-            };
-        });
-    deleted_count.to_string()
-}
-
-fn get_page_new_person() -> impl Responder {
+    for id in ids {
+        if db_conn.delete_by_id(id).map_err(AppError::Storage)? {
+            deleted_count += 1;
+        }
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain")
+        .body(deleted_count.to_string()))
+}
+
+fn get_page_new_person() -> Result<HttpResponse, PageError> {
     let mut context = tera::Context::default();
-    context.insert("person_id", &"");
-    context.insert("person_name", &"");
+    context.insert("person", &Person::default());
     context.insert("inserting", &true);
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("one_person.html", &context).unwrap())
+    Ok(render_page("one_person.html", &context)?)
 }
 
 fn get_page_edit_person(
     state: web::Data<Mutex<AppState>>,
     path: web::Path<(String,)>,
-) -> impl Responder {
+) -> Result<HttpResponse, PageError> {
     let id = &path.0;
+    let id_n = id
+        .parse::<u32>()
+        .map_err(|_| AppError::BadRequest(format!("Invalid id: {}", id)))?;
     let db_conn = &state.lock().unwrap().db;
+    let person = db_conn
+        .get_person_by_id(id_n)
+        .map_err(AppError::Storage)?
+        .ok_or_else(|| AppError::NotFound(format!("Person {} not found", id_n)))?;
     let mut context = tera::Context::new();
-    if let Ok(id_n) = id.parse::<u32>() {
-        if let Some(person) = db_conn.get_person_by_id(id_n) {
-            context.insert("person_id", &id);
-            context.insert("person_name", &person.name);
-            context.insert("inserting", &false);
-            return HttpResponse::Ok()
-                .content_type("text/html")
-                .body(TERA.render("one_person.html", &context).unwrap());
-        }
-    }
-    context.insert("id_error", &"Person id not found");
-    context.insert("partial_name", &"");
-    let person_list = db_conn.get_persons_by_partial_name(&"");
-    context.insert("persons", &person_list.collect::<Vec<_>>());
+    context.insert("person", &person);
+    context.insert("inserting", &false);
+    Ok(render_page("one_person.html", &context)?)
+}
 
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(TERA.render("persons.html", &context).unwrap())
+fn insert_person(
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<ToSave>,
+) -> Result<HttpResponse, AppError> {
+    let mut person = query.to_person().map_err(AppError::InvalidFields)?;
+    person.created_at = now_secs();
+    person.updated_at = person.created_at;
+    let db_conn = &mut state.lock().unwrap().db;
+    db_conn.insert_person(person).map_err(AppError::Storage)?;
+    Ok(HttpResponse::Ok().content_type("text/plain").body("1"))
 }
 
-fn insert_person(state: web::Data<Mutex<AppState>>, query: web::Query<ToInsert>) -> impl Responder {
+fn update_person(
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<ToSave>,
+) -> Result<HttpResponse, AppError> {
+    let id = query
+        .id
+        .ok_or_else(|| AppError::BadRequest("Missing id".to_string()))?;
+    let mut person = query.to_person().map_err(AppError::InvalidFields)?;
+    person.updated_at = now_secs();
     let db_conn = &mut state.lock().unwrap().db;
-    let mut inserted_count = 0;
-    if let Some(name) = &query.name.clone() {
-        inserted_count += db_conn.insert_person(Person {
-            id: 0,
-            name: name.clone(),
-        });
+    if !db_conn.update_person(person).map_err(AppError::Storage)? {
+        return Err(AppError::NotFound(format!("Person {} not found", id)));
     }
-    inserted_count.to_string()
+    Ok(HttpResponse::Ok().content_type("text/plain").body("1"))
 }
 
-fn update_person(state: web::Data<Mutex<AppState>>, query: web::Query<ToUpdate>) -> impl Responder {
-    let db_conn = &mut state.lock().unwrap().db;
-    let mut updated_count = 0;
-    let id = query.id.unwrap_or(0);
-    let name = query.name.clone().unwrap_or_else(|| "".to_string()).clone();
-    updated_count += if db_conn.update_person(Person { id, name }) {
-        1
-    } else {
-        0
-    };
-    updated_count.to_string()
+fn invalid_resource(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    println!("Invalid URI: \"{}\"", req.uri());
+    Err(AppError::NotFound("Invalid request.".to_string()).for_request(&req))
 }
 
-fn invalid_resource(state: web::Data<Mutex<AppState>>) -> impl Responder {
-    let db_conn = &state.lock().unwrap().db;
-    let mut context = tera::Context::new();
-    context.insert("id_error", &"Invalid request.");
-    context.insert("partial_name", &"");
-    let person_list = db_conn.get_persons_by_partial_name(&"");
-    context.insert("persons", &person_list.collect::<Vec<_>>());
+/// The persons the example starts with.
+fn sample_persons() -> Vec<Person> {
+    vec![
+        Person {
+            id: 2,
+            name: "Hamlet".to_string(),
+            ..Person::default()
+        },
+        Person {
+            id: 4,
+            name: "Macbeth".to_string(),
+            ..Person::default()
+        },
+        Person {
+            id: 7,
+            name: "Othello".to_string(),
+            ..Person::default()
+        },
+    ]
+}
 
-    HttpResponse::NotFound()
-        .content_type("text/html")
-        .body(TERA.render("persons.html", &context).unwrap())
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/").route(web::get().to(get_main)))
+        .service(web::resource("/page/persons").route(web::get().to(get_page_persons)))
+        .service(web::resource("/persons").route(web::delete().to(delete_persons)))
+        .service(web::resource("/page/new_person").route(web::get().to(get_page_new_person)))
+        .service(web::resource("/page/edit_person/{id}").route(web::get().to(get_page_edit_person)))
+        .service(
+            web::resource("/one_person")
+                .route(web::post().to(insert_person))
+                .route(web::put().to(update_person)),
+        )
+        .service(web::resource("/favicon.ico").route(web::get().to(get_favicon)));
 }
 
 fn main() -> std::io::Result<()> {
-    let server_address = "127.0.0.1:8080";
-    println!("listening at address: {}", server_address);
+    let config = Config::load("CRUD")?;
+    config.init_logger();
+    let templates = tera::Tera::new(&config.templates_glob())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    set_templates(templates);
+    let server_address = config.server.bind_address.clone();
 
     let db_conn = web::Data::new(Mutex::new(AppState {
-        db: db_access::DbConnection::new(),
+        db: MemoryPersons::with_persons(sample_persons()),
     }));
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
             .register_data(db_conn.clone())
-            .service(web::resource("/").route(web::get().to(get_main)))
-            .service(web::resource("/page/persons").route(web::get().to(get_page_persons)))
-            .service(web::resource("/persons").route(web::delete().to(delete_persons)))
-            .service(web::resource("/page/new_person").route(web::get().to(get_page_new_person)))
-            .service(
-                web::resource("/page/edit_person/{id}").route(web::get().to(get_page_edit_person)),
-            )
-            .service(
-                web::resource("/one_person")
-                    .route(web::post().to(insert_person))
-                    .route(web::put().to(update_person)),
-            )
-            .service(web::resource("/favicon.ico").route(web::get().to(get_favicon)))
+            .configure(routes)
             .default_service(web::get().to(invalid_resource))
-    })
-    .bind(server_address)?
-    .run()
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&server_address, acceptor)?,
+        None => server.bind(&server_address)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&server_address)?;
+    println!(
+        "listening at address: {}://{}",
+        config.scheme(),
+        server_address
+    );
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+
+    fn app_state() -> web::Data<Mutex<AppState>> {
+        web::Data::new(Mutex::new(AppState {
+            db: MemoryPersons::with_persons(sample_persons()),
+        }))
+    }
+
+    fn to_save(name: &str, email: Option<&str>) -> ToSave {
+        ToSave {
+            id: Some(2),
+            name: Some(name.to_string()),
+            email: email.map(str::to_string),
+            birth_date: None,
+            phone: None,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn parses_only_lists_of_valid_ids() {
+        assert_eq!(parse_id_list("").unwrap(), Vec::<u32>::new());
+        assert_eq!(parse_id_list("2, 7,").unwrap(), vec![2, 7]);
+        match parse_id_list("2,x,7") {
+            Err(AppError::BadRequest(message)) => assert_eq!(message, "Invalid id: x"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn builds_only_valid_persons() {
+        let person = to_save("  Hamlet ", None).to_person().unwrap();
+        assert_eq!(person.id, 2);
+        assert_eq!(person.name, "Hamlet");
+
+        let errors = to_save("", Some("not an address")).to_person().unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["name", "email"]);
+    }
+
+    #[test]
+    fn saves_and_deletes_persons() {
+        let state = app_state();
+        let mut app = test::init_service(App::new().register_data(state.clone()).configure(routes));
+
+        let request = TestRequest::post()
+            .uri("/one_person?name=Lear")
+            .to_request();
+        assert_eq!(test::read_response(&mut app, request), "1");
+        let request = TestRequest::post().uri("/one_person?name=").to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = TestRequest::put()
+            .uri("/one_person?id=4&name=Banquo")
+            .to_request();
+        assert_eq!(test::read_response(&mut app, request), "1");
+        let updated = state.lock().unwrap().db.get_person_by_id(4).unwrap();
+        assert_eq!(updated.unwrap().name, "Banquo");
+
+        let request = TestRequest::put()
+            .uri("/one_person?id=5&name=Banquo")
+            .to_request();
+        let body = test::read_response(&mut app, request);
+        assert_eq!(body, r#"{"error":"Person 5 not found"}"#);
+        let request = TestRequest::put()
+            .uri("/one_person?name=Banquo")
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = TestRequest::delete()
+            .uri("/persons?id_list=2,5,7")
+            .to_request();
+        assert_eq!(test::read_response(&mut app, request), "2");
+        let request = TestRequest::delete()
+            .uri("/persons?id_list=2,x")
+            .to_request();
+        let body = test::read_response(&mut app, request);
+        assert_eq!(body, r#"{"error":"Invalid id: x"}"#);
+    }
 }
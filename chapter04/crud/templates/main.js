@@ -8,16 +8,26 @@ function sendCommand(method, uri, body, success, failure) {
     xhttp.onreadystatechange = function() {
         if (this.readyState == 4)
             if (this.status == 200) success();
-            else failure();
+            else failure(this);
     };
     xhttp.open(method, uri, true);
     xhttp.send(body);
 }
 
+// Extracts the message of a failed command, or else returns the fallback.
+function errorMessage(xhttp, fallback) {
+    try {
+        return JSON.parse(xhttp.responseText).error || fallback;
+    } catch (e) {
+        return fallback;
+    }
+}
+
 function getPage(uri) {
     var xhttp = new XMLHttpRequest();
     xhttp.onreadystatechange = function() {
-        if (this.readyState == 4 && this.status == 200) {
+        // Error pages are shown as well, with their message.
+        if (this.readyState == 4 && this.status != 0) {
             document.getElementById('body')
                 .innerHTML = xhttp.responseText;
         }
@@ -26,6 +36,36 @@ function getPage(uri) {
     xhttp.send();
 }
 
+// Builds the query string of the persons page from its filters,
+// keeping the current sort order.
+function personsQuery(page) {
+    var value = function(id) {
+        return encodeURIComponent(document.getElementById(id).value);
+    };
+    return 'partial_name=' + value('name_portion')
+        + '&email=' + value('filter_email')
+        + '&phone=' + value('filter_phone')
+        + '&born_from=' + value('born_from')
+        + '&born_to=' + value('born_to')
+        + '&sort_by=' + value('sort_by')
+        + '&order=' + value('order')
+        + '&page=' + page
+        + '&page_size=' + value('page_size');
+}
+
+function filterPersons(page) {
+    getPage('/page/persons?' + personsQuery(page));
+}
+
+// Sorts by the given column, reversing the order if already sorted by it.
+function sortPersons(column) {
+    var sort_by = document.getElementById('sort_by');
+    var order = document.getElementById('order');
+    order.value = sort_by.value === column && order.value === 'asc' ? 'desc' : 'asc';
+    sort_by.value = column;
+    filterPersons(1);
+}
+
 function delete_selected_persons() {
     var items;
     for (var item of document.getElementsByName('selector'))
@@ -34,26 +74,48 @@ function delete_selected_persons() {
             else items = '' + item.id;
     if (items)
         sendCommand('DELETE', '/persons?id_list=' + items, '',
-            function() { getPage('/page/persons'); },
-            function() { alert('Failed deletion.'); });
+            function() {
+                filterPersons(document.getElementById('page').value);
+            },
+            function(xhttp) { alert(errorMessage(xhttp, 'Failed deletion.')); });
+}
+
+// Shows the messages of a rejected form next to their fields,
+// returning false if the response does not contain field errors.
+function showFieldErrors(xhttp) {
+    for (var label of document.getElementsByClassName('field-error'))
+        label.textContent = '';
+    var errors;
+    try {
+        errors = JSON.parse(xhttp.responseText);
+    } catch (e) {
+        return false;
+    }
+    if (xhttp.status != 400 || !Array.isArray(errors))
+        return false;
+    for (var error of errors) {
+        var label = document.getElementById('error_' + error.field);
+        if (label) label.textContent = error.message;
+    }
+    return true;
 }
 
 function savePerson(method) {
-    sendCommand(method,
-        '/one_person?'
-        + (method === 'POST' ? '' :
-        'id='
-        + document.getElementById('person_id').value
-        + '&')
-        + 'name='
-        + encodeURIComponent(
-            document.getElementById('person_name')
-            .value),
-        '',
+    var uri = '/one_person?';
+    if (method !== 'POST')
+        uri += 'id=' + document.getElementById('person_id').value + '&';
+    uri += ['name', 'email', 'birth_date', 'phone', 'notes']
+        .map(function(field) {
+            return field + '=' + encodeURIComponent(
+                document.getElementById('person_' + field).value);
+        })
+        .join('&');
+    sendCommand(method, uri, '',
         function() {
             getPage('/page/persons');
         },
-        function() {
-            alert('Failed command.');
+        function(xhttp) {
+            if (!showFieldErrors(xhttp))
+                alert(errorMessage(xhttp, 'Failed command.'));
         });
 }
@@ -1,12 +1,12 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{middleware, web, App, HttpResponse, HttpServer, Responder};
 use lazy_static::lazy_static;
+use persons_domain::{MemoryPersons, Person, PersonsStore};
 use serde_derive::Deserialize;
+use server_config::Config;
 use std::sync::Mutex;
 
-mod db_access;
-
 struct AppState {
-    db: db_access::DbConnection,
+    db: MemoryPersons,
 }
 
 #[derive(Deserialize)]
@@ -15,7 +15,11 @@ pub struct Filter {
 }
 
 lazy_static! {
-    pub static ref TERA: tera::Tera = tera::Tera::new("templates/**/*").unwrap();
+    static ref CONFIG: Config = Config::load("LIST").unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    pub static ref TERA: tera::Tera = tera::Tera::new(&CONFIG.templates_glob()).unwrap();
 }
 
 fn get_main() -> impl Responder {
@@ -31,10 +35,12 @@ fn get_page_persons(
 ) -> impl Responder {
     let partial_name = &query.partial_name.clone().unwrap_or_else(|| "".to_string());
     let db_conn = &state.lock().unwrap().db;
-    let person_list = db_conn.get_persons_by_partial_name(&partial_name);
+    let person_list = db_conn
+        .get_persons_by_partial_name(&partial_name)
+        .unwrap_or_default();
     let mut context = tera::Context::new();
     context.insert("partial_name", &partial_name);
-    context.insert("persons", &person_list.collect::<Vec<_>>());
+    context.insert("persons", &person_list);
 
     HttpResponse::Ok()
         .content_type("text/html")
@@ -53,22 +59,59 @@ fn invalid_resource() -> impl Responder {
         .body("<h2>Invalid request.</h2>")
 }
 
+/// The persons the example starts with.
+fn sample_persons() -> Vec<Person> {
+    vec![
+        Person {
+            id: 2,
+            name: "Hamlet".to_string(),
+            ..Person::default()
+        },
+        Person {
+            id: 4,
+            name: "Macbeth".to_string(),
+            ..Person::default()
+        },
+        Person {
+            id: 7,
+            name: "Othello".to_string(),
+            ..Person::default()
+        },
+    ]
+}
+
 fn main() -> std::io::Result<()> {
-    let server_address = "127.0.0.1:8080";
-    println!("listening at address: {}", server_address);
+    let config = &*CONFIG;
+    config.init_logger();
+    let server_address = config.server.bind_address.clone();
 
     let db_conn = web::Data::new(Mutex::new(AppState {
-        db: db_access::DbConnection::new(),
+        db: MemoryPersons::with_persons(sample_persons()),
     }));
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
             .register_data(db_conn.clone())
             .service(web::resource("/").route(web::get().to(get_main)))
             .service(web::resource("/page/persons").route(web::get().to(get_page_persons)))
             .service(web::resource("/favicon.ico").route(web::get().to(get_favicon)))
             .default_service(web::get().to(invalid_resource))
-    })
-    .bind(server_address)?
-    .run()
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&server_address, acceptor)?,
+        None => server.bind(&server_address)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&server_address)?;
+    println!(
+        "listening at address: {}://{}",
+        config.scheme(),
+        server_address
+    );
+    server.run()
 }
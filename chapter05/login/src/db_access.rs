@@ -1,44 +1,38 @@
-#[derive(Clone, Copy, PartialEq, Debug)]
-pub enum DbPrivilege {
-    CanRead,
-    CanWrite,
-}
-
-#[derive(Clone, Debug, PartialEq)]
-pub struct User {
-    pub username: String,
-    pub password: String,
-    pub privileges: Vec<DbPrivilege>,
-}
+use persons_domain::{Role, User};
 
+/// The users of this frontend-only example, kept in the browser memory.
 #[derive(PartialEq, Clone)]
 pub struct DbConnection {
-    users: Vec<User>,
+    /// The demo users with their passwords, kept in clear
+    /// as there is no server to hash them.
+    accounts: Vec<(User, String)>,
+}
+
+fn demo_account(username: &str, password: &str, role: Role) -> (User, String) {
+    let user = User {
+        username: username.to_string(),
+        password_hash: String::new(),
+        role,
+        enabled: true,
+    };
+    (user, password.to_string())
 }
 
 impl DbConnection {
     pub fn new() -> Self {
         Self {
-            users: vec![
-                User {
-                    username: "joe".to_string(),
-                    password: "xjoe".to_string(),
-                    privileges: vec![DbPrivilege::CanRead],
-                },
-                User {
-                    username: "susan".to_string(),
-                    password: "xsusan".to_string(),
-                    privileges: vec![DbPrivilege::CanRead, DbPrivilege::CanWrite],
-                },
+            accounts: vec![
+                demo_account("joe", "xjoe", Role::Viewer),
+                demo_account("susan", "xsusan", Role::Editor),
             ],
         }
     }
 
-    pub fn get_user_by_username(&self, username: &str) -> Option<&User> {
-        if let Some(u) = self.users.iter().find(|u| u.username == username) {
-            Some(u)
-        } else {
-            None
+    pub fn check_credentials(&self, username: &str, password: &str) -> Result<User, &'static str> {
+        match self.accounts.iter().find(|(u, _)| u.username == username) {
+            Some((user, user_password)) if *user_password == password => Ok(user.clone()),
+            Some(_) => Err("Invalid password for the specified user."),
+            None => Err("User not found."),
         }
     }
 }
@@ -2,8 +2,9 @@ use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
 
 mod db_access;
 mod login;
-use db_access::{DbConnection, DbPrivilege, User};
+use db_access::DbConnection;
 use login::LoginModel;
+use persons_domain::{Permission, User};
 
 enum Page {
     Login,
@@ -13,7 +14,7 @@ enum Page {
 struct MainModel {
     page: Page,
     current_user: Option<String>,
-    can_write: bool,
+    permissions: Vec<Permission>,
     db_connection: std::rc::Rc<std::cell::RefCell<DbConnection>>,
 }
 
@@ -31,7 +32,7 @@ impl Component for MainModel {
         MainModel {
             page: Page::Login,
             current_user: None,
-            can_write: false,
+            permissions: vec![],
             db_connection: std::rc::Rc::new(std::cell::RefCell::new(DbConnection::new())),
         }
     }
@@ -41,7 +42,7 @@ impl Component for MainModel {
             MainMsg::LoggedIn(user) => {
                 self.page = Page::PersonsList;
                 self.current_user = Some(user.username);
-                self.can_write = user.privileges.contains(&DbPrivilege::CanWrite);
+                self.permissions = user.role.permissions().to_vec();
             }
             MainMsg::ChangeUserPressed => self.page = Page::Login,
         }
@@ -0,0 +1,516 @@
+// JSON backend for the yclient frontend.
+// Users are created from the chapter04/auth pages, sharing the same database file.
+// The first run creates the "admin" user, as in:
+// AUTH_ADMIN_PASSWORD=xadmin cargo run -- persons.db
+// test it with the following commands:
+// curl -u admin:xadmin -X GET http://localhost:8080/authenticate
+// TOKEN=<the token returned by /authenticate>
+// curl -H "Authorization: Bearer $TOKEN" -H "Content-Type: application/json" -X POST http://localhost:8080/one_person -d '{"id":0,"name":"Hamlet","email":"hamlet@elsinore.dk"}'
+// curl -H "Authorization: Bearer $TOKEN" -H "Content-Type: application/json" -X PUT http://localhost:8080/one_person -d '{"id":1,"name":"Macbeth","birth_date":"1005-08-15"}'
+// curl -H "Authorization: Bearer $TOKEN" -X GET http://localhost:8080/person/id/1
+// curl -H "Authorization: Bearer $TOKEN" -X GET "http://localhost:8080/persons?partial_name=ac&sort_by=name&order=desc&page=1&page_size=20"
+// curl -H "Authorization: Bearer $TOKEN" -X DELETE "http://localhost:8080/persons?id_list=1,2"
+// curl -H "Authorization: Bearer $TOKEN" -X POST http://localhost:8080/logout
+
+use actix_cors::Cors;
+use actix_web::dev::{Service, ServiceRequest};
+use actix_web::{
+    http::header, middleware, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer,
+    Responder,
+};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use serde_derive::{Deserialize, Serialize};
+use server_config::Config;
+use std::sync::Mutex;
+
+use persons_domain::{now_secs, AppState, DbConnection, Permission, Person, PersonsFilter, User};
+use persons_web::{
+    bearer_token, resolve_session, AppError, Authorized, CanDelete, CanInsert, CanRead, CanUpdate,
+    LoggedUser,
+};
+
+#[derive(Deserialize)]
+pub struct ToDelete {
+    id_list: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LoginSession {
+    user: User,
+    token: String,
+}
+
+#[derive(Serialize)]
+enum AuthenticationResult {
+    LoggedUser(LoginSession),
+    ErrorMessage(String),
+}
+
+fn authenticate(auth: BasicAuth, state: web::Data<Mutex<AppState>>) -> impl Responder {
+    println!("in authenticate: {}", auth.user_id());
+    let mut state = state.lock().unwrap();
+    let password = auth.password().map(|p| p.as_ref());
+    HttpResponse::Ok().json(match state.db.check_credentials(auth.user_id(), password) {
+        Ok(user) => {
+            let token = state.sessions.issue(&user.username);
+            AuthenticationResult::LoggedUser(LoginSession { user, token })
+        }
+        Err(msg) => AuthenticationResult::ErrorMessage(msg),
+    })
+}
+
+fn logout(logged_user: LoggedUser, state: web::Data<Mutex<AppState>>) -> HttpResponse {
+    println!("in logout: {}", logged_user.user.username);
+    HttpResponse::Ok().json(state.lock().unwrap().sessions.revoke(&logged_user.token))
+}
+
+fn get_person_by_id(
+    _auth: Authorized<CanRead>,
+    state: web::Data<Mutex<AppState>>,
+    path: web::Path<(String,)>,
+) -> Result<HttpResponse, AppError> {
+    println!("in get_person_by_id: {}", path.0);
+    let id = parse_id(&path.0)?;
+    let db_conn = &state.lock().unwrap().db;
+    let person = db_conn
+        .get_person_by_id(id)
+        .map_err(AppError::Storage)?
+        .ok_or_else(|| AppError::NotFound(format!("Person {} not found", id)))?;
+    Ok(HttpResponse::Ok().json(person))
+}
+
+fn get_persons(
+    _auth: Authorized<CanRead>,
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<PersonsFilter>,
+) -> Result<HttpResponse, AppError> {
+    println!("in get_persons");
+    let db_conn = &state.lock().unwrap().db;
+    let page = db_conn
+        .query_persons(&query.to_query())
+        .map_err(AppError::Storage)?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+fn parse_id(id: &str) -> Result<u32, AppError> {
+    id.parse::<u32>()
+        .map_err(|_| AppError::BadRequest(format!("Invalid id: {}", id)))
+}
+
+fn delete_persons(
+    auth: Authorized<CanDelete>,
+    state: web::Data<Mutex<AppState>>,
+    query: web::Query<ToDelete>,
+) -> Result<HttpResponse, AppError> {
+    println!("in delete_persons");
+    let ids = query
+        .id_list
+        .as_ref()
+        .map_or("", |s| s)
+        .split_terminator(',')
+        .map(parse_id)
+        .collect::<Result<Vec<_>, _>>()?;
+    if ids.len() > 1 && !auth.user.has_permission(Permission::BulkDelete) {
+        return Err(AppError::insufficient_privileges(&auth.user.username));
+    }
+    let db_conn = &mut state.lock().unwrap().db;
+    let mut deleted_count = 0;
+    for id in ids {
+        if db_conn
+            .delete_by_id(&auth.user.username, id)
+            .map_err(AppError::Storage)?
+        {
+            deleted_count += 1;
+        }
+    }
+    Ok(HttpResponse::Ok().json(deleted_count))
+}
+
+fn insert_person(
+    auth: Authorized<CanInsert>,
+    state: web::Data<Mutex<AppState>>,
+    person: web::Json<Person>,
+) -> Result<HttpResponse, AppError> {
+    println!("in insert_person");
+    let mut person = person.into_inner();
+    person.normalize();
+    person.validate().map_err(AppError::InvalidFields)?;
+    person.created_at = now_secs();
+    person.updated_at = person.created_at;
+    let db_conn = &mut state.lock().unwrap().db;
+    let id = db_conn
+        .insert_person(&auth.user.username, person)
+        .map_err(AppError::Storage)?;
+    Ok(HttpResponse::Ok().json(id))
+}
+
+fn update_person(
+    auth: Authorized<CanUpdate>,
+    state: web::Data<Mutex<AppState>>,
+    person: web::Json<Person>,
+) -> Result<HttpResponse, AppError> {
+    println!("in update_person");
+    let mut person = person.into_inner();
+    person.normalize();
+    person.validate().map_err(AppError::InvalidFields)?;
+    person.updated_at = now_secs();
+    let id = person.id;
+    let db_conn = &mut state.lock().unwrap().db;
+    if !db_conn
+        .update_person(&auth.user.username, person)
+        .map_err(AppError::Storage)?
+    {
+        return Err(AppError::NotFound(format!("Person {} not found", id)));
+    }
+    Ok(HttpResponse::Ok().json(true))
+}
+
+fn invalid_resource(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    println!("Invalid URI: \"{}\"", req.uri());
+    Err(AppError::NotFound("Invalid request.".to_string()))
+}
+
+/// Puts the user of the bearer token of the request, if any, in its extensions.
+fn attach_logged_user(req: &ServiceRequest, state: &Mutex<AppState>) {
+    if let Some(logged_user) = bearer_token(req).and_then(|token| resolve_session(token, state)) {
+        req.extensions_mut().insert(logged_user);
+    }
+}
+
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/authenticate").route(web::get().to(authenticate)))
+        .service(web::resource("/logout").route(web::post().to(logout)))
+        .service(web::resource("/person/id/{id}").route(web::get().to(get_person_by_id)))
+        .service(
+            web::resource("/persons")
+                .route(web::get().to(get_persons))
+                .route(web::delete().to(delete_persons)),
+        )
+        .service(
+            web::resource("/one_person")
+                .route(web::post().to(insert_person))
+                .route(web::put().to(update_person)),
+        );
+}
+
+fn main() -> std::io::Result<()> {
+    let config = Config::load("PERSONS_DB")?;
+    config.init_logger();
+    let server_address = config.server.bind_address.clone();
+
+    let to_io_error = |e: String| std::io::Error::new(std::io::ErrorKind::Other, e);
+
+    // With a database file, configured or given as argument, the persons,
+    // the users and the audit log are kept in SQLite, otherwise they are
+    // kept in memory.
+    let database_file = config
+        .storage
+        .database_file
+        .clone()
+        .or_else(|| config.args.first().cloned());
+    let mut db = DbConnection::open(database_file.as_deref()).map_err(to_io_error)?;
+    // The first user can only be created from the environment.
+    let admin_password = std::env::var("AUTH_ADMIN_PASSWORD").ok();
+    db.bootstrap_admin(admin_password.as_deref())
+        .map_err(to_io_error)?;
+
+    let db_conn = web::Data::new(Mutex::new(AppState::new(db)));
+
+    let mut server = HttpServer::new(move || {
+        let state = db_conn.clone();
+        App::new()
+            .wrap(middleware::Logger::new(server_config::ACCESS_LOG_FORMAT))
+            .register_data(db_conn.clone())
+            .wrap_fn(move |req, srv| {
+                attach_logged_user(&req, &state);
+                srv.call(req)
+            })
+            .wrap(
+                Cors::new()
+                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+                    .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
+                    .allowed_header(header::CONTENT_TYPE)
+                    .max_age(3600),
+            )
+            .configure(routes)
+            .default_service(web::route().to(invalid_resource))
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    #[cfg(feature = "tls")]
+    let server = match config.ssl_acceptor()? {
+        Some(acceptor) => server.bind_ssl(&server_address, acceptor)?,
+        None => server.bind(&server_address)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(&server_address)?;
+    println!(
+        "listening at address: {}://{}",
+        config.scheme(),
+        server_address
+    );
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use persons_domain::{MemoryAudit, MemoryPersons, MemoryUsers, Role};
+    use serde_json::Value;
+
+    /// The authorization headers of "admin", with password "xadmin",
+    /// and of "joe", a viewer with password "xjoe".
+    const ADMIN: &str = "Basic YWRtaW46eGFkbWlu";
+    const JOE: &str = "Basic am9lOnhqb2U=";
+
+    fn app_state() -> web::Data<Mutex<AppState>> {
+        let mut db = DbConnection::new(
+            Box::new(MemoryPersons::new()),
+            Box::new(MemoryUsers::new()),
+            Box::new(MemoryAudit::new()),
+        );
+        db.bootstrap_admin(Some("xadmin")).unwrap();
+        db.insert_user(User::new("joe", "xjoe", Role::Viewer).unwrap())
+            .unwrap();
+        web::Data::new(Mutex::new(AppState::new(db)))
+    }
+
+    /// The application as run by `main`, without the logger and CORS.
+    macro_rules! init_app {
+        ($state:expr) => {{
+            let state = $state.clone();
+            test::init_service(
+                App::new()
+                    .register_data($state.clone())
+                    .wrap_fn(move |req, srv| {
+                        attach_logged_user(&req, &state);
+                        srv.call(req)
+                    })
+                    .configure(routes)
+                    .default_service(web::route().to(invalid_resource)),
+            )
+        }};
+    }
+
+    fn bearer(token: &str) -> String {
+        format!("Bearer {}", token)
+    }
+
+    fn json(body: &[u8]) -> Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    fn json_request(request: TestRequest, token: &str, body: &str) -> TestRequest {
+        request
+            .header(header::AUTHORIZATION, bearer(token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(body.to_string())
+    }
+
+    /// The token of a new session of the user of the authorization header.
+    macro_rules! log_in {
+        ($app:expr, $authorization:expr) => {{
+            let request = TestRequest::get()
+                .uri("/authenticate")
+                .header(header::AUTHORIZATION, $authorization)
+                .to_request();
+            let body = json(&test::read_response(&mut $app, request));
+            body["LoggedUser"]["token"].as_str().unwrap().to_string()
+        }};
+    }
+
+    #[test]
+    fn authenticates_only_the_right_credentials() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let authenticate = |authorization: &str| {
+            TestRequest::get()
+                .uri("/authenticate")
+                .header(header::AUTHORIZATION, authorization)
+                .to_request()
+        };
+        let body = json(&test::read_response(&mut app, authenticate(ADMIN)));
+        assert_eq!(body["LoggedUser"]["user"]["username"], "admin");
+        assert!(body["LoggedUser"]["token"].is_string());
+
+        let wrong_password = "Basic YWRtaW46eGpvZQ==";
+        let body = json(&test::read_response(&mut app, authenticate(wrong_password)));
+        assert_eq!(body["ErrorMessage"], "Invalid credentials");
+    }
+
+    #[test]
+    fn inserts_lists_updates_and_deletes_persons() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let token = log_in!(app, ADMIN);
+
+        let request = json_request(
+            TestRequest::post().uri("/one_person"),
+            &token,
+            r#"{"id":0,"name":"Hamlet","email":"hamlet@elsinore.dk"}"#,
+        )
+        .to_request();
+        assert_eq!(json(&test::read_response(&mut app, request)), 1);
+
+        let request = TestRequest::get()
+            .uri("/persons?partial_name=HAM")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        let page = json(&test::read_response(&mut app, request));
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["persons"][0]["name"], "Hamlet");
+
+        let request = json_request(
+            TestRequest::put().uri("/one_person"),
+            &token,
+            r#"{"id":1,"name":"Macbeth","birth_date":"1005-08-15"}"#,
+        )
+        .to_request();
+        assert_eq!(json(&test::read_response(&mut app, request)), true);
+        let request = TestRequest::get()
+            .uri("/person/id/1")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        let person = json(&test::read_response(&mut app, request));
+        assert_eq!(person["name"], "Macbeth");
+        assert_eq!(person["birth_date"], "1005-08-15");
+
+        let request = TestRequest::delete()
+            .uri("/persons?id_list=1")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        assert_eq!(json(&test::read_response(&mut app, request)), 1);
+        let request = TestRequest::get()
+            .uri("/person/id/1")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn rejects_requests_without_a_valid_session() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let request = TestRequest::get().uri("/persons").to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = TestRequest::get()
+            .uri("/persons")
+            .header(header::AUTHORIZATION, bearer("not a token"))
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let token = log_in!(app, ADMIN);
+        let request = TestRequest::post()
+            .uri("/logout")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        assert_eq!(
+            test::call_service(&mut app, request).status(),
+            StatusCode::OK
+        );
+        let request = TestRequest::get()
+            .uri("/persons")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn rejects_changes_by_viewers() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let token = log_in!(app, JOE);
+
+        let request = TestRequest::get()
+            .uri("/persons")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        assert_eq!(
+            test::call_service(&mut app, request).status(),
+            StatusCode::OK
+        );
+
+        let request = json_request(
+            TestRequest::post().uri("/one_person"),
+            &token,
+            r#"{"id":0,"name":"Hamlet"}"#,
+        )
+        .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let request = json_request(
+            TestRequest::put().uri("/one_person"),
+            &token,
+            r#"{"id":1,"name":"Macbeth"}"#,
+        )
+        .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let request = TestRequest::delete()
+            .uri("/persons?id_list=1")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        let response = test::call_service(&mut app, request);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn answers_errors_with_a_json_body() {
+        let state = app_state();
+        let mut app = init_app!(state);
+        let token = log_in!(app, ADMIN);
+        let get = |uri: &str| {
+            TestRequest::get()
+                .uri(uri)
+                .header(header::AUTHORIZATION, bearer(&token))
+                .to_request()
+        };
+
+        let response = test::call_service(&mut app, get("/person/id/x"));
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = json(&test::read_response(&mut app, get("/person/id/x")));
+        assert_eq!(body, serde_json::json!({"error": "Invalid id: x"}));
+        let response = test::call_service(&mut app, get("/person/id/9"));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = json(&test::read_response(&mut app, get("/person/id/9")));
+        assert_eq!(body, serde_json::json!({"error": "Person 9 not found"}));
+        let body = json(&test::read_response(&mut app, get("/no/such/route")));
+        assert_eq!(body, serde_json::json!({"error": "Invalid request."}));
+
+        let request = json_request(
+            TestRequest::put().uri("/one_person"),
+            &token,
+            r#"{"id":9,"name":"Macbeth"}"#,
+        )
+        .to_request();
+        let body = json(&test::read_response(&mut app, request));
+        assert_eq!(body, serde_json::json!({"error": "Person 9 not found"}));
+
+        let request = TestRequest::delete()
+            .uri("/persons?id_list=1,x")
+            .header(header::AUTHORIZATION, bearer(&token))
+            .to_request();
+        let body = json(&test::read_response(&mut app, request));
+        assert_eq!(body, serde_json::json!({"error": "Invalid id: x"}));
+
+        // The invalid fields are listed, to be shown next to each field.
+        let request = json_request(
+            TestRequest::post().uri("/one_person"),
+            &token,
+            r#"{"id":0,"name":" "}"#,
+        )
+        .to_request();
+        let body = json(&test::read_response(&mut app, request));
+        assert_eq!(body[0]["field"], "name");
+    }
+}
@@ -0,0 +1,196 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::person::is_valid_date;
+use crate::{Person, StoreResult};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The kind of change made to a person.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 3] = [
+        AuditAction::Insert,
+        AuditAction::Update,
+        AuditAction::Delete,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AuditAction::Insert => "insert",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().cloned().find(|a| a.name() == name)
+    }
+}
+
+/// A change made by a user to a person, as recorded in the audit log.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct AuditEntry {
+    /// Given by the audit store, increasing with every entry.
+    pub id: u64,
+    pub username: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub action: AuditAction,
+    pub person_id: u32,
+    /// The person before the change, missing for an insert.
+    pub before: Option<Person>,
+    /// The person after the change, missing for a delete.
+    pub after: Option<Person>,
+}
+
+/// A field of a person changed by an audited action, with its values as text.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+impl AuditEntry {
+    /// The fields a user can edit whose values differ between before and after;
+    /// for an insert or a delete, the fields having a value.
+    pub fn changes(&self) -> Vec<FieldChange> {
+        fn fields(person: &Option<Person>) -> [(&'static str, String); 5] {
+            let person = person.clone().unwrap_or_default();
+            [
+                ("name", person.name),
+                ("email", person.email.unwrap_or_default()),
+                ("birth_date", person.birth_date.unwrap_or_default()),
+                ("phone", person.phone.unwrap_or_default()),
+                ("notes", person.notes.unwrap_or_default()),
+            ]
+        }
+        fields(&self.before)
+            .iter()
+            .zip(fields(&self.after).iter())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((field, before), (_, after))| FieldChange {
+                field,
+                before: before.clone(),
+                after: after.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Which audit entries to get; a missing filter matches every entry.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct AuditQuery {
+    pub username: Option<String>,
+    /// The earliest timestamp, included.
+    pub since: Option<u64>,
+    /// The latest timestamp, excluded.
+    pub until: Option<u64>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.username
+            .as_ref()
+            .map_or(true, |u| *u == entry.username)
+            && self.since.map_or(true, |since| entry.timestamp >= since)
+            && self.until.map_or(true, |until| entry.timestamp < until)
+    }
+}
+
+/// The first second of a day given as "YYYY-MM-DD", in UTC, or `None`
+/// if the date is not valid or is before the epoch, as the timestamps are.
+pub fn date_to_secs(date: &str) -> Option<u64> {
+    if !is_valid_date(date) {
+        return None;
+    }
+    let year: i64 = date[0..4].parse().ok()?;
+    let month: i64 = date[5..7].parse().ok()?;
+    let day: i64 = date[8..10].parse().ok()?;
+    // Counts the years from March, so that the leap day ends the year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    if days < 0 {
+        return None;
+    }
+    Some(days as u64 * SECS_PER_DAY)
+}
+
+/// Formats a timestamp as "YYYY-MM-DD HH:MM:SS", in UTC.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / SECS_PER_DAY) as i64 + 719_468;
+    let secs = timestamp % SECS_PER_DAY;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// The operations every audit log backend must provide.
+/// Entries can only be added, never changed or removed.
+pub trait AuditStore: Send {
+    /// Returns the id given to the new entry.
+    fn append(&mut self, entry: AuditEntry) -> StoreResult<u64>;
+    /// Returns the matching entries, the newest first.
+    fn query(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>>;
+}
+
+/// Keeps the audit log in a `Vec`, so it is lost when the process ends.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct MemoryAudit {
+    entries: Vec<AuditEntry>,
+}
+
+impl MemoryAudit {
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+}
+
+impl AuditStore for MemoryAudit {
+    fn append(&mut self, mut entry: AuditEntry) -> StoreResult<u64> {
+        entry.id = self.entries.len() as u64 + 1;
+        let id = entry.id;
+        self.entries.push(entry);
+        Ok(id)
+    }
+
+    fn query(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        Ok(self
+            .entries
+            .iter()
+            .rev()
+            .filter(|e| query.matches(e))
+            .cloned()
+            .collect())
+    }
+}
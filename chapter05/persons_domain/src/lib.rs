@@ -0,0 +1,43 @@
+//! The persons domain shared by the persons servers and their web frontends.
+//!
+//! Without features the crate also builds for WebAssembly. The `server`
+//! feature adds password hashing and session tokens, and the `sqlite`
+//! feature adds the storage backends kept in an SQLite database file.
+
+mod audit;
+#[cfg(feature = "server")]
+mod password;
+mod person;
+mod privileges;
+mod search;
+#[cfg(feature = "server")]
+mod session;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+mod store;
+mod user;
+
+pub use audit::{
+    date_to_secs, format_timestamp, AuditAction, AuditEntry, AuditQuery, AuditStore, FieldChange,
+    MemoryAudit,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use person::now_secs;
+pub use person::{FieldError, Person};
+pub use privileges::{Permission, Role};
+pub use search::{
+    fold_text, PersonsFilter, PersonsPage, PersonsQuery, SortColumn, DEFAULT_PAGE_SIZE,
+    MAX_PAGE_SIZE,
+};
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub use session::AppState;
+#[cfg(feature = "server")]
+pub use session::Sessions;
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::{
+    open_connection, SharedConnection, SqliteAudit, SqlitePersons, SqliteUsers,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use store::DbConnection;
+pub use store::{MemoryPersons, MemoryUsers, PersonsStore, StoreResult, UsersStore};
+pub use user::User;
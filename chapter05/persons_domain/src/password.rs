@@ -0,0 +1,65 @@
+use crate::store::StoreResult;
+
+/// The rounds of the new hashes, as recommended for PBKDF2-HMAC-SHA256.
+/// Every hash keeps its own rounds, as "$rpbkdf2$0$<rounds>$<salt>$<digest>$",
+/// so the hashes made before raising this value can still be checked,
+/// and are replaced when their users log in.
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// A hash made by `hash_password` of a password nobody has.
+const DUMMY_HASH: &str =
+    "$rpbkdf2$0$AAknwA==$Hhs0A7FEq5eYzje6V+2s4w==$QzNA9pyz7sfEtLuX+SRKwyRlLBycpTQr40lELwAC7wg=$";
+
+/// Returns a salted PBKDF2 hash of `password`, suitable to be stored.
+pub fn hash_password(password: &str) -> StoreResult<String> {
+    pbkdf2::pbkdf2_simple(password, PBKDF2_ROUNDS).map_err(|e| e.to_string())
+}
+
+/// Checks `password` against a hash produced by `hash_password`,
+/// comparing the digests in constant time.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    pbkdf2::pbkdf2_check(password, password_hash).is_ok()
+}
+
+/// Takes as long as checking the password of an existing user,
+/// so that the time of a failed login does not tell whether the user exists.
+pub fn verify_dummy_password(password: &str) {
+    verify_password(password, DUMMY_HASH);
+}
+
+/// Whether a hash was made with fewer rounds than the new hashes,
+/// so it should be replaced once the password is known.
+pub fn needs_rehash(password_hash: &str) -> bool {
+    rounds_of(password_hash).map_or(true, |rounds| rounds < PBKDF2_ROUNDS)
+}
+
+/// The rounds stored in a hash made by `hash_password`, as a base64
+/// big-endian number.
+fn rounds_of(password_hash: &str) -> Option<u32> {
+    let field = password_hash.split('$').nth(3)?;
+    let bytes = base64::decode(field).ok()?;
+    if bytes.len() != 4 {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |rounds, &byte| rounds << 8 | u32::from(byte)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_the_rounds_in_the_hash() {
+        assert_eq!(rounds_of(DUMMY_HASH), Some(PBKDF2_ROUNDS));
+        assert!(!needs_rehash(DUMMY_HASH));
+        let old_hash =
+            "$rpbkdf2$0$AAAnEA==$Gpm9jIqomrcXDF3J8AaOgA==$u4WNKf33Ltx06fXzqrtRHdizvL6OdUG7bQqnCbOVoG4=$";
+        assert_eq!(rounds_of(old_hash), Some(10_000));
+        assert!(needs_rehash(old_hash));
+        assert!(needs_rehash("not a hash"));
+    }
+}
@@ -0,0 +1,165 @@
+use serde_derive::{Deserialize, Serialize};
+
+pub const MAX_NAME_LEN: usize = 100;
+pub const MAX_EMAIL_LEN: usize = 254;
+pub const MAX_PHONE_LEN: usize = 30;
+pub const MAX_NOTES_LEN: usize = 2000;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Person {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// As "YYYY-MM-DD".
+    #[serde(default)]
+    pub birth_date: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Seconds since the Unix epoch, set by the servers.
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+/// Why the value of a field of a form was rejected.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: String) -> Self {
+        Self {
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl Person {
+    /// Trims the text fields, turning the blank optional ones into `None`.
+    pub fn normalize(&mut self) {
+        fn trim_optional(value: &mut Option<String>) {
+            *value = value
+                .as_ref()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+        }
+        self.name = self.name.trim().to_string();
+        trim_optional(&mut self.email);
+        trim_optional(&mut self.birth_date);
+        trim_optional(&mut self.phone);
+        trim_optional(&mut self.notes);
+    }
+
+    /// Checks the fields a user can edit, returning an error for each invalid one.
+    /// It should be called after `normalize`.
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = vec![];
+        if self.name.is_empty() {
+            errors.push(FieldError::new("name", "The name is required.".to_string()));
+        } else if self.name.chars().count() > MAX_NAME_LEN {
+            errors.push(FieldError::new("name", too_long(MAX_NAME_LEN)));
+        }
+        if let Some(email) = &self.email {
+            if email.chars().count() > MAX_EMAIL_LEN {
+                errors.push(FieldError::new("email", too_long(MAX_EMAIL_LEN)));
+            } else if !is_valid_email(email) {
+                errors.push(FieldError::new(
+                    "email",
+                    "The e-mail address is not valid.".to_string(),
+                ));
+            }
+        }
+        if let Some(birth_date) = &self.birth_date {
+            if !is_valid_date(birth_date) {
+                errors.push(FieldError::new(
+                    "birth_date",
+                    "The birth date must be a valid date as YYYY-MM-DD.".to_string(),
+                ));
+            }
+        }
+        if let Some(phone) = &self.phone {
+            if phone.chars().count() > MAX_PHONE_LEN {
+                errors.push(FieldError::new("phone", too_long(MAX_PHONE_LEN)));
+            } else if !is_valid_phone(phone) {
+                errors.push(FieldError::new(
+                    "phone",
+                    "The phone number may contain only digits, spaces and \"+-().\".".to_string(),
+                ));
+            }
+        }
+        if let Some(notes) = &self.notes {
+            if notes.chars().count() > MAX_NOTES_LEN {
+                errors.push(FieldError::new("notes", too_long(MAX_NOTES_LEN)));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn too_long(max_len: usize) -> String {
+    format!("At most {} characters are allowed.", max_len)
+}
+
+fn is_valid_email(email: &str) -> bool {
+    let mut parts = email.splitn(2, '@');
+    let local = parts.next().unwrap_or("");
+    let domain = parts.next().unwrap_or("");
+    !local.is_empty()
+        && !domain.contains('@')
+        && !email.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+pub(crate) fn is_valid_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return false;
+    }
+    let (year, month, day) = match (
+        date[0..4].parse::<u32>(),
+        date[5..7].parse::<u32>(),
+        date[8..10].parse::<u32>(),
+    ) {
+        (Ok(year), Ok(month), Ok(day)) => (year, month, day),
+        _ => return false,
+    };
+    let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    year >= 1800 && day >= 1 && day <= days_in_month
+}
+
+fn is_valid_phone(phone: &str) -> bool {
+    phone.chars().filter(char::is_ascii_digit).count() >= 3
+        && phone
+            .chars()
+            .enumerate()
+            .all(|(i, c)| c.is_ascii_digit() || " -().".contains(c) || c == '+' && i == 0)
+}
+
+/// The current time as seconds since the Unix epoch.
+/// Not available on WebAssembly, where the system clock cannot be read.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
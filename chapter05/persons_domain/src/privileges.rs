@@ -0,0 +1,67 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// A single operation a user may be allowed to perform.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Permission {
+    Read,
+    Insert,
+    Update,
+    Delete,
+    /// Deleting several persons with a single request.
+    BulkDelete,
+    ManageUsers,
+    Export,
+    /// Reading the audit log of the changes to the persons.
+    ViewAudit,
+}
+
+/// A named set of permissions, assigned to every user.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Editor, Role::Admin];
+
+    pub fn permissions(self) -> &'static [Permission] {
+        match self {
+            Role::Viewer => &[Permission::Read],
+            Role::Editor => &[
+                Permission::Read,
+                Permission::Insert,
+                Permission::Update,
+                Permission::Delete,
+                Permission::Export,
+            ],
+            Role::Admin => &[
+                Permission::Read,
+                Permission::Insert,
+                Permission::Update,
+                Permission::Delete,
+                Permission::BulkDelete,
+                Permission::ManageUsers,
+                Permission::Export,
+                Permission::ViewAudit,
+            ],
+        }
+    }
+
+    pub fn has_permission(self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Viewer => "Viewer",
+            Role::Editor => "Editor",
+            Role::Admin => "Admin",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Role> {
+        Role::ALL.iter().cloned().find(|r| r.name() == name)
+    }
+}
@@ -0,0 +1,242 @@
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::Person;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Lowercases the text and removes its accents, so that "Élise" matches "elise".
+pub fn fold_text(text: &str) -> String {
+    deunicode::deunicode(text).to_lowercase()
+}
+
+/// A column the persons can be sorted by.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SortColumn {
+    Id,
+    Name,
+    Email,
+    BirthDate,
+    Phone,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 7] = [
+        SortColumn::Id,
+        SortColumn::Name,
+        SortColumn::Email,
+        SortColumn::BirthDate,
+        SortColumn::Phone,
+        SortColumn::CreatedAt,
+        SortColumn::UpdatedAt,
+    ];
+
+    /// The name used in query strings, equal to the name of the field.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortColumn::Id => "id",
+            SortColumn::Name => "name",
+            SortColumn::Email => "email",
+            SortColumn::BirthDate => "birth_date",
+            SortColumn::Phone => "phone",
+            SortColumn::CreatedAt => "created_at",
+            SortColumn::UpdatedAt => "updated_at",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().cloned().find(|c| c.name() == name)
+    }
+}
+
+/// The value of a sort column, compared only with those of the same column.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Text(String),
+    Number(u64),
+}
+
+/// Which persons to get, in which order, and which page of them.
+///
+/// Text filters match any part of the field, ignoring case and accents;
+/// an empty filter matches every person.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PersonsQuery {
+    pub partial_name: String,
+    pub email: String,
+    pub phone: String,
+    /// The earliest birth date, as "YYYY-MM-DD".
+    pub born_from: Option<String>,
+    /// The latest birth date, as "YYYY-MM-DD".
+    pub born_to: Option<String>,
+    pub sort_by: SortColumn,
+    pub descending: bool,
+    /// Starting from 1.
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl Default for PersonsQuery {
+    fn default() -> Self {
+        Self {
+            partial_name: String::new(),
+            email: String::new(),
+            phone: String::new(),
+            born_from: None,
+            born_to: None,
+            sort_by: SortColumn::Id,
+            descending: false,
+            page: 1,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl PersonsQuery {
+    /// Brings the page number and the page size into their allowed ranges.
+    pub fn normalize(&mut self) {
+        self.page = self.page.max(1);
+        self.page_size = self.page_size.max(1).min(MAX_PAGE_SIZE);
+    }
+
+    /// How many matching persons come before the requested page.
+    pub fn offset(&self) -> u32 {
+        (self.page.max(1) - 1).saturating_mul(self.page_size)
+    }
+
+    pub fn matches(&self, person: &Person) -> bool {
+        fn contains(value: &str, filter: &str) -> bool {
+            filter.is_empty() || fold_text(value).contains(&fold_text(filter))
+        }
+        let birth_date = person.birth_date.as_deref();
+        let born_after = match (self.born_from.as_deref(), birth_date) {
+            (None, _) => true,
+            (Some(from), Some(date)) => date >= from,
+            (Some(_), None) => false,
+        };
+        let born_before = match (self.born_to.as_deref(), birth_date) {
+            (None, _) => true,
+            (Some(to), Some(date)) => date <= to,
+            (Some(_), None) => false,
+        };
+        contains(&person.name, &self.partial_name)
+            && contains(person.email.as_ref().map_or("", |s| s), &self.email)
+            && contains(person.phone.as_ref().map_or("", |s| s), &self.phone)
+            && born_after
+            && born_before
+    }
+
+    /// The value of the sort column of the person, then its id;
+    /// text is folded, so that case and accents are ignored,
+    /// and a missing value comes before any other.
+    fn sort_key(&self, person: &Person) -> (SortValue, u32) {
+        fn text(value: &Option<String>) -> SortValue {
+            SortValue::Text(fold_text(value.as_ref().map_or("", |s| s)))
+        }
+        let value = match self.sort_by {
+            SortColumn::Id => SortValue::Number(0),
+            SortColumn::Name => SortValue::Text(fold_text(&person.name)),
+            SortColumn::Email => text(&person.email),
+            SortColumn::BirthDate => text(&person.birth_date),
+            SortColumn::Phone => text(&person.phone),
+            SortColumn::CreatedAt => SortValue::Number(person.created_at),
+            SortColumn::UpdatedAt => SortValue::Number(person.updated_at),
+        };
+        (value, person.id)
+    }
+
+    /// Orders by the sort column, then by id, as `apply` does.
+    pub fn compare(&self, a: &Person, b: &Person) -> Ordering {
+        let ordering = self.sort_key(a).cmp(&self.sort_key(b));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// Filters, sorts and pages the given persons,
+    /// for the stores which keep them all at hand.
+    pub fn apply<'a>(&self, persons: impl Iterator<Item = &'a Person>) -> PersonsPage {
+        let mut found: Vec<&Person> = persons.filter(|p| self.matches(p)).collect();
+        // Folds the text of each person once, rather than at every comparison.
+        found.sort_by_cached_key(|p| self.sort_key(p));
+        if self.descending {
+            found.reverse();
+        }
+        PersonsPage {
+            total: found.len() as u32,
+            persons: found
+                .into_iter()
+                .skip(self.offset() as usize)
+                .take(self.page_size as usize)
+                .cloned()
+                .collect(),
+            page: self.page.max(1),
+            page_size: self.page_size,
+        }
+    }
+}
+
+/// The query string of a persons search, where every parameter is optional.
+#[derive(Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct PersonsFilter {
+    pub partial_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub born_from: Option<String>,
+    pub born_to: Option<String>,
+    pub sort_by: Option<String>,
+    /// "desc" for the descending order.
+    pub order: Option<String>,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+impl PersonsFilter {
+    /// Builds the query, using the defaults for the missing or unknown values.
+    pub fn to_query(&self) -> PersonsQuery {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let date = |value: &Option<String>| value.clone().filter(|d| !d.is_empty());
+        let mut query = PersonsQuery {
+            partial_name: text(&self.partial_name),
+            email: text(&self.email),
+            phone: text(&self.phone),
+            born_from: date(&self.born_from),
+            born_to: date(&self.born_to),
+            sort_by: self
+                .sort_by
+                .as_ref()
+                .and_then(|name| SortColumn::from_name(name))
+                .unwrap_or(SortColumn::Id),
+            descending: self.order.as_deref() == Some("desc"),
+            page: self.page.unwrap_or(1),
+            page_size: self.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+        };
+        query.normalize();
+        query
+    }
+}
+
+/// A page of the persons matching a query.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PersonsPage {
+    pub persons: Vec<Person>,
+    /// How many persons match the query, in all the pages.
+    pub total: u32,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl PersonsPage {
+    /// At least 1, so that an empty result still has a page to show.
+    pub fn page_count(&self) -> u32 {
+        let page_size = self.page_size.max(1);
+        let partial_page = if self.total % page_size > 0 { 1 } else { 0 };
+        (self.total / page_size + partial_page).max(1)
+    }
+}
@@ -0,0 +1,125 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+use crate::now_secs;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{DbConnection, User};
+
+const SESSION_DURATION_SECS: u64 = 8 * 60 * 60;
+
+struct Session {
+    username: String,
+    expires_at: u64,
+}
+
+/// Issues and checks the session tokens.
+///
+/// A token is "{id}.{expiration}.{signature}", where the signature is an
+/// HMAC-SHA256 of the rest of the token, so it cannot be forged or extended.
+/// The active sessions are also kept here, so that a token can be revoked
+/// before it expires.
+pub struct Sessions {
+    secret: [u8; 32],
+    active: HashMap<String, Session>,
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self {
+            secret: rand::random(),
+            active: HashMap::new(),
+        }
+    }
+
+    fn sign(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_varkey(&self.secret).unwrap();
+        mac.input(payload.as_bytes());
+        mac
+    }
+
+    pub fn issue(&mut self, username: &str) -> String {
+        let now = now_secs();
+        self.active.retain(|_, s| s.expires_at > now);
+
+        let id = hex::encode(rand::random::<[u8; 16]>());
+        let expires_at = now + SESSION_DURATION_SECS;
+        let payload = format!("{}.{}", id, expires_at);
+        let signature = hex::encode(self.sign(&payload).result().code());
+        self.active.insert(
+            id,
+            Session {
+                username: username.to_string(),
+                expires_at,
+            },
+        );
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Returns the username of the session of `token`,
+    /// if the token is authentic, not expired and not revoked.
+    pub fn resolve(&self, token: &str) -> Option<&str> {
+        let mut parts = token.rsplitn(2, '.');
+        let signature = hex::decode(parts.next()?).ok()?;
+        let payload = parts.next()?;
+        self.sign(payload).verify(&signature).ok()?;
+
+        let mut parts = payload.splitn(2, '.');
+        let id = parts.next()?;
+        let expires_at = parts.next()?.parse::<u64>().ok()?;
+        let session = self.active.get(id)?;
+        if session.expires_at != expires_at || expires_at <= now_secs() {
+            return None;
+        }
+        Some(&session.username)
+    }
+
+    pub fn revoke(&mut self, token: &str) -> bool {
+        match token.split('.').next() {
+            Some(id) => self.active.remove(id).is_some(),
+            None => false,
+        }
+    }
+
+    /// Revokes every session of `username`, except the one of `keep_token`.
+    pub fn revoke_user(&mut self, username: &str, keep_token: Option<&str>) -> usize {
+        let keep_id = keep_token.and_then(|t| t.split('.').next());
+        let before = self.active.len();
+        self.active
+            .retain(|id, s| s.username != username || Some(id.as_str()) == keep_id);
+        before - self.active.len()
+    }
+}
+
+/// The state shared by the handlers of a server having users.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AppState {
+    pub db: DbConnection,
+    pub sessions: Sessions,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AppState {
+    pub fn new(db: DbConnection) -> Self {
+        Self {
+            db,
+            sessions: Sessions::new(),
+        }
+    }
+
+    /// Returns the user of the session of `token`, if the session
+    /// is valid and the user still exists and is enabled.
+    pub fn logged_user(&self, token: &str) -> Option<User> {
+        let username = self.sessions.resolve(token)?;
+        match self.db.get_user_by_username(username) {
+            Ok(Some(user)) if user.enabled => Some(user),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{
+    fold_text, AuditAction, AuditEntry, AuditQuery, AuditStore, Person, PersonsPage, PersonsQuery,
+    PersonsStore, Role, SortColumn, StoreResult, User, UsersStore,
+};
+
+/// A connection shared by the stores kept in the same database file,
+/// so that a change to the persons and its audit entry share a transaction.
+pub type SharedConnection = Arc<Mutex<Connection>>;
+
+/// Opens the database file where the SQLite stores are kept.
+pub fn open_connection(database_file: &str) -> StoreResult<SharedConnection> {
+    let conn = Connection::open(database_file).map_err(|e| e.to_string())?;
+    // Lets the queries ignore case and accents as the other stores do.
+    conn.create_scalar_function(
+        "fold",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            Ok(fold_text(
+                &ctx.get::<Option<String>>(0)?.unwrap_or_default(),
+            ))
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(Arc::new(Mutex::new(conn)))
+}
+
+fn lock(conn: &SharedConnection) -> StoreResult<MutexGuard<'_, Connection>> {
+    conn.lock()
+        .map_err(|_| "The database connection is unusable".to_string())
+}
+
+/// Keeps the persons in an SQLite database file, so they survive a restart.
+pub struct SqlitePersons {
+    conn: SharedConnection,
+}
+
+impl SqlitePersons {
+    /// Opens the database file for the persons only.
+    pub fn open(database_file: &str) -> StoreResult<Self> {
+        Self::new(open_connection(database_file)?)
+    }
+
+    pub fn new(shared_conn: SharedConnection) -> StoreResult<Self> {
+        let conn = lock(&shared_conn)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS Persons (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL
+            )",
+            params![],
+        )
+        .map_err(|e| e.to_string())?;
+        add_missing_person_columns(&conn).map_err(|e| e.to_string())?;
+        drop(conn);
+        Ok(Self { conn: shared_conn })
+    }
+
+    fn execute_batch(&self, sql: &str) -> StoreResult<()> {
+        lock(&self.conn)?
+            .execute_batch(sql)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The columns added to the Persons table after its first version,
+/// so that the older databases can be upgraded in place.
+const PERSON_EXTRA_COLUMNS: [(&str, &str); 6] = [
+    ("email", "TEXT"),
+    ("birth_date", "TEXT"),
+    ("phone", "TEXT"),
+    ("notes", "TEXT"),
+    ("created_at", "INTEGER NOT NULL DEFAULT 0"),
+    ("updated_at", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+fn add_missing_person_columns(conn: &Connection) -> rusqlite::Result<()> {
+    for (column, definition) in PERSON_EXTRA_COLUMNS.iter() {
+        let exists = conn
+            .prepare("SELECT name FROM pragma_table_info('Persons') WHERE name = $1")?
+            .exists(params![column])?;
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE Persons ADD COLUMN {} {}", column, definition),
+                params![],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+const PERSON_COLUMNS: &str = "id, name, email, birth_date, phone, notes, created_at, updated_at";
+
+/// The filters of a `PersonsQuery`, whose values are bound as `$1` to `$5`.
+const PERSON_FILTERS: &str = "instr(fold(name), $1) > 0
+    AND instr(fold(email), $2) > 0
+    AND instr(fold(phone), $3) > 0
+    AND ($4 IS NULL OR birth_date >= $4)
+    AND ($5 IS NULL OR birth_date <= $5)";
+
+/// The expression to sort by, consistent with `PersonsQuery::compare`.
+fn sort_expression(column: SortColumn) -> String {
+    match column {
+        SortColumn::Id | SortColumn::CreatedAt | SortColumn::UpdatedAt => column.name().to_string(),
+        _ => format!("fold({})", column.name()),
+    }
+}
+
+fn row_to_person(row: &rusqlite::Row) -> rusqlite::Result<Person> {
+    Ok(Person {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        email: row.get(2)?,
+        birth_date: row.get(3)?,
+        phone: row.get(4)?,
+        notes: row.get(5)?,
+        created_at: row.get::<_, i64>(6)? as u64,
+        updated_at: row.get::<_, i64>(7)? as u64,
+    })
+}
+
+impl PersonsStore for SqlitePersons {
+    fn get_person_by_id(&self, id: u32) -> StoreResult<Option<Person>> {
+        lock(&self.conn)?
+            .query_row(
+                &format!("SELECT {} FROM Persons WHERE id = $1", PERSON_COLUMNS),
+                params![id],
+                row_to_person,
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    fn get_persons_by_partial_name(&self, subname: &str) -> StoreResult<Vec<Person>> {
+        let conn = lock(&self.conn)?;
+        let mut command = conn
+            .prepare(&format!(
+                "SELECT {} FROM Persons WHERE instr(fold(name), $1) > 0 ORDER BY id",
+                PERSON_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let persons = command
+            .query_map(params![fold_text(subname)], row_to_person)
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(persons)
+    }
+
+    fn query_persons(&self, query: &PersonsQuery) -> StoreResult<PersonsPage> {
+        let filters = params![
+            fold_text(&query.partial_name),
+            fold_text(&query.email),
+            fold_text(&query.phone),
+            query.born_from,
+            query.born_to
+        ];
+        let conn = lock(&self.conn)?;
+        let total = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM Persons WHERE {}", PERSON_FILTERS),
+                filters,
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|e| e.to_string())? as u32;
+        let direction = if query.descending { "DESC" } else { "ASC" };
+        let mut command = conn
+            .prepare(&format!(
+                "SELECT {} FROM Persons WHERE {} ORDER BY {} {}, id {} LIMIT $6 OFFSET $7",
+                PERSON_COLUMNS,
+                PERSON_FILTERS,
+                sort_expression(query.sort_by),
+                direction,
+                direction
+            ))
+            .map_err(|e| e.to_string())?;
+        let persons = command
+            .query_map(
+                params![
+                    fold_text(&query.partial_name),
+                    fold_text(&query.email),
+                    fold_text(&query.phone),
+                    query.born_from,
+                    query.born_to,
+                    query.page_size,
+                    query.offset()
+                ],
+                row_to_person,
+            )
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(PersonsPage {
+            persons,
+            total,
+            page: query.page.max(1),
+            page_size: query.page_size,
+        })
+    }
+
+    fn insert_person(&mut self, person: Person) -> StoreResult<u32> {
+        let conn = lock(&self.conn)?;
+        conn.execute(
+            "INSERT INTO Persons (
+                    name, email, birth_date, phone, notes, created_at, updated_at
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            params![
+                person.name,
+                person.email,
+                person.birth_date,
+                person.phone,
+                person.notes,
+                person.created_at as i64,
+                person.updated_at as i64
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid() as u32)
+    }
+
+    fn update_person(&mut self, person: Person) -> StoreResult<bool> {
+        lock(&self.conn)?
+            .execute(
+                "UPDATE Persons SET name = $1, email = $2, birth_date = $3, phone = $4,
+                notes = $5, updated_at = $6
+                WHERE id = $7",
+                params![
+                    person.name,
+                    person.email,
+                    person.birth_date,
+                    person.phone,
+                    person.notes,
+                    person.updated_at as i64,
+                    person.id
+                ],
+            )
+            .map(|n| n > 0)
+            .map_err(|e| e.to_string())
+    }
+
+    fn delete_by_id(&mut self, id: u32) -> StoreResult<bool> {
+        lock(&self.conn)?
+            .execute("DELETE FROM Persons WHERE id = $1", params![id])
+            .map(|n| n > 0)
+            .map_err(|e| e.to_string())
+    }
+
+    fn begin(&mut self) -> StoreResult<()> {
+        self.execute_batch("BEGIN")
+    }
+
+    fn commit(&mut self) -> StoreResult<()> {
+        self.execute_batch("COMMIT")
+    }
+
+    fn rollback(&mut self) -> StoreResult<()> {
+        self.execute_batch("ROLLBACK")
+    }
+}
+
+/// Keeps the users in the same SQLite database file as the persons.
+pub struct SqliteUsers {
+    conn: SharedConnection,
+}
+
+impl SqliteUsers {
+    /// Opens the database file for the users only.
+    pub fn open(database_file: &str) -> StoreResult<Self> {
+        Self::new(open_connection(database_file)?)
+    }
+
+    pub fn new(shared_conn: SharedConnection) -> StoreResult<Self> {
+        let conn = lock(&shared_conn)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS Users (
+                username TEXT PRIMARY KEY,
+                password_hash TEXT NOT NULL,
+                role TEXT NOT NULL,
+                enabled BOOLEAN NOT NULL
+            )",
+            params![],
+        )
+        .map_err(|e| e.to_string())?;
+        migrate_privileges_to_roles(&conn).map_err(|e| e.to_string())?;
+        drop(conn);
+        Ok(Self { conn: shared_conn })
+    }
+}
+
+/// Databases created before roles existed keep a comma-separated list of
+/// privileges per user; each user gets the closest role instead.
+fn migrate_privileges_to_roles(conn: &Connection) -> rusqlite::Result<()> {
+    let has_privileges = conn
+        .prepare("SELECT name FROM pragma_table_info('Users') WHERE name = 'privileges'")?
+        .exists(params![])?;
+    if !has_privileges {
+        return Ok(());
+    }
+    println!("migrating the Users table from privileges to roles");
+    conn.execute_batch(
+        "BEGIN;
+        ALTER TABLE Users RENAME TO OldUsers;
+        CREATE TABLE Users (
+            username TEXT PRIMARY KEY,
+            password_hash TEXT NOT NULL,
+            role TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL
+        );
+        INSERT INTO Users (username, password_hash, role, enabled)
+            SELECT username, password_hash,
+                CASE
+                    WHEN instr(privileges, 'CanManageUsers') > 0 THEN 'Admin'
+                    WHEN instr(privileges, 'CanWrite') > 0 THEN 'Editor'
+                    ELSE 'Viewer'
+                END,
+                enabled
+            FROM OldUsers;
+        DROP TABLE OldUsers;
+        COMMIT;",
+    )
+}
+
+fn row_to_user(row: &rusqlite::Row) -> rusqlite::Result<User> {
+    let role: String = row.get(2)?;
+    Ok(User {
+        username: row.get(0)?,
+        password_hash: row.get(1)?,
+        // An unknown role name can only come from a newer schema,
+        // so it gets the least powerful role rather than locking the user out.
+        role: Role::from_name(&role).unwrap_or(Role::Viewer),
+        enabled: row.get(3)?,
+    })
+}
+
+impl UsersStore for SqliteUsers {
+    fn get_user_by_username(&self, username: &str) -> StoreResult<Option<User>> {
+        lock(&self.conn)?
+            .query_row(
+                "SELECT username, password_hash, role, enabled
+                FROM Users WHERE username = $1",
+                params![username],
+                row_to_user,
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    fn get_all_users(&self) -> StoreResult<Vec<User>> {
+        let conn = lock(&self.conn)?;
+        let mut command = conn
+            .prepare(
+                "SELECT username, password_hash, role, enabled
+                FROM Users ORDER BY username",
+            )
+            .map_err(|e| e.to_string())?;
+        let users = command
+            .query_map(params![], row_to_user)
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(users)
+    }
+
+    fn insert_user(&mut self, user: User) -> StoreResult<bool> {
+        lock(&self.conn)?
+            .execute(
+                "INSERT OR IGNORE INTO Users (
+                    username, password_hash, role, enabled
+                    ) VALUES ($1, $2, $3, $4)",
+                params![
+                    user.username,
+                    user.password_hash,
+                    user.role.name(),
+                    user.enabled
+                ],
+            )
+            .map(|n| n > 0)
+            .map_err(|e| e.to_string())
+    }
+
+    fn update_user(&mut self, user: User) -> StoreResult<bool> {
+        lock(&self.conn)?
+            .execute(
+                "UPDATE Users SET password_hash = $1, role = $2, enabled = $3
+                WHERE username = $4",
+                params![
+                    user.password_hash,
+                    user.role.name(),
+                    user.enabled,
+                    user.username
+                ],
+            )
+            .map(|n| n > 0)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Keeps the audit log in the same SQLite database file as the persons.
+/// The persons before and after each change are stored as JSON.
+pub struct SqliteAudit {
+    conn: SharedConnection,
+}
+
+impl SqliteAudit {
+    /// Opens the database file for the audit log only.
+    pub fn open(database_file: &str) -> StoreResult<Self> {
+        Self::new(open_connection(database_file)?)
+    }
+
+    pub fn new(shared_conn: SharedConnection) -> StoreResult<Self> {
+        let conn = lock(&shared_conn)?;
+        // The triggers keep the log append-only,
+        // even for whoever opens the database file by other means.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS AuditLog (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                username TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                action TEXT NOT NULL,
+                person_id INTEGER NOT NULL,
+                before TEXT,
+                after TEXT
+            );
+            CREATE TRIGGER IF NOT EXISTS AuditLogNoUpdate BEFORE UPDATE ON AuditLog
+            BEGIN
+                SELECT RAISE(ABORT, 'The audit log cannot be changed');
+            END;
+            CREATE TRIGGER IF NOT EXISTS AuditLogNoDelete BEFORE DELETE ON AuditLog
+            BEGIN
+                SELECT RAISE(ABORT, 'The audit log cannot be changed');
+            END;",
+        )
+        .map_err(|e| e.to_string())?;
+        drop(conn);
+        Ok(Self { conn: shared_conn })
+    }
+}
+
+fn person_to_json(person: &Option<Person>) -> StoreResult<Option<String>> {
+    person
+        .as_ref()
+        .map(|p| serde_json::to_string(p).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn json_to_person(json: Option<String>) -> rusqlite::Result<Option<Person>> {
+    json.map(|json| {
+        serde_json::from_str(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        })
+    })
+    .transpose()
+}
+
+fn row_to_audit_entry(row: &rusqlite::Row) -> rusqlite::Result<AuditEntry> {
+    let action: String = row.get(3)?;
+    Ok(AuditEntry {
+        id: row.get::<_, i64>(0)? as u64,
+        username: row.get(1)?,
+        timestamp: row.get::<_, i64>(2)? as u64,
+        action: AuditAction::from_name(&action).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(3, action.clone(), rusqlite::types::Type::Text)
+        })?,
+        person_id: row.get(4)?,
+        before: json_to_person(row.get(5)?)?,
+        after: json_to_person(row.get(6)?)?,
+    })
+}
+
+impl AuditStore for SqliteAudit {
+    fn append(&mut self, entry: AuditEntry) -> StoreResult<u64> {
+        let conn = lock(&self.conn)?;
+        conn.execute(
+            "INSERT INTO AuditLog (
+                    username, timestamp, action, person_id, before, after
+                    ) VALUES ($1, $2, $3, $4, $5, $6)",
+            params![
+                entry.username,
+                entry.timestamp as i64,
+                entry.action.name(),
+                entry.person_id,
+                person_to_json(&entry.before)?,
+                person_to_json(&entry.after)?
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid() as u64)
+    }
+
+    fn query(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        let conn = lock(&self.conn)?;
+        let mut command = conn
+            .prepare(
+                "SELECT id, username, timestamp, action, person_id, before, after
+                FROM AuditLog
+                WHERE ($1 IS NULL OR username = $1)
+                AND ($2 IS NULL OR timestamp >= $2)
+                AND ($3 IS NULL OR timestamp < $3)
+                ORDER BY id DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let entries = command
+            .query_map(
+                params![
+                    query.username,
+                    query.since.map(|t| t as i64),
+                    query.until.map(|t| t as i64)
+                ],
+                row_to_audit_entry,
+            )
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(entries)
+    }
+}
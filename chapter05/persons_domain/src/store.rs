@@ -0,0 +1,404 @@
+#[cfg(feature = "server")]
+use crate::Role;
+use crate::{fold_text, Person, PersonsPage, PersonsQuery, User};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{now_secs, AuditAction, AuditEntry, AuditQuery, AuditStore};
+
+pub type StoreResult<T> = Result<T, String>;
+
+/// The operations every persons storage backend must provide.
+pub trait PersonsStore: Send {
+    fn get_person_by_id(&self, id: u32) -> StoreResult<Option<Person>>;
+    /// Ignores case and accents, and returns all the matching persons by id.
+    fn get_persons_by_partial_name(&self, subname: &str) -> StoreResult<Vec<Person>>;
+    fn query_persons(&self, query: &PersonsQuery) -> StoreResult<PersonsPage>;
+    /// Returns the id given to the new person.
+    fn insert_person(&mut self, person: Person) -> StoreResult<u32>;
+    /// Replaces every field of the person having the same id,
+    /// except `created_at`; returns false if there is no such person.
+    fn update_person(&mut self, person: Person) -> StoreResult<bool>;
+    fn delete_by_id(&mut self, id: u32) -> StoreResult<bool>;
+    /// Starts a transaction, ended by `commit` or `rollback`;
+    /// transactions cannot be nested.
+    fn begin(&mut self) -> StoreResult<()>;
+    fn commit(&mut self) -> StoreResult<()>;
+    /// Undoes every change made since `begin`.
+    fn rollback(&mut self) -> StoreResult<()>;
+}
+
+/// The operations every users storage backend must provide.
+pub trait UsersStore: Send {
+    fn get_user_by_username(&self, username: &str) -> StoreResult<Option<User>>;
+    fn get_all_users(&self) -> StoreResult<Vec<User>>;
+    /// Returns false if a user with the same username already exists.
+    fn insert_user(&mut self, user: User) -> StoreResult<bool>;
+    fn update_user(&mut self, user: User) -> StoreResult<bool>;
+}
+
+/// Keeps the persons in a `Vec`, so they are lost when the process ends.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MemoryPersons {
+    persons: Vec<Person>,
+    next_id: u32,
+    /// The persons and the next id when the transaction began.
+    saved: Option<(Vec<Person>, u32)>,
+}
+
+impl Default for MemoryPersons {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryPersons {
+    pub fn new() -> Self {
+        Self {
+            persons: vec![],
+            next_id: 1,
+            saved: None,
+        }
+    }
+
+    /// Starts with the given persons, keeping their ids.
+    pub fn with_persons(persons: Vec<Person>) -> Self {
+        let next_id = persons.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+        Self {
+            persons,
+            next_id,
+            saved: None,
+        }
+    }
+}
+
+impl PersonsStore for MemoryPersons {
+    fn get_person_by_id(&self, id: u32) -> StoreResult<Option<Person>> {
+        Ok(self.persons.iter().find(|p| p.id == id).cloned())
+    }
+
+    fn get_persons_by_partial_name(&self, subname: &str) -> StoreResult<Vec<Person>> {
+        let subname = fold_text(subname);
+        Ok(self
+            .persons
+            .iter()
+            .filter(|p| fold_text(&p.name).contains(&subname))
+            .cloned()
+            .collect())
+    }
+
+    fn query_persons(&self, query: &PersonsQuery) -> StoreResult<PersonsPage> {
+        Ok(query.apply(self.persons.iter()))
+    }
+
+    fn insert_person(&mut self, mut person: Person) -> StoreResult<u32> {
+        // Ids are never reused, even after the last person is deleted.
+        let new_id = self.next_id;
+        self.next_id += 1;
+        person.id = new_id;
+        self.persons.push(person);
+        Ok(new_id)
+    }
+
+    fn update_person(&mut self, mut person: Person) -> StoreResult<bool> {
+        if let Some(p) = self.persons.iter_mut().find(|p| p.id == person.id) {
+            person.created_at = p.created_at;
+            *p = person;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn delete_by_id(&mut self, id: u32) -> StoreResult<bool> {
+        if let Some((n, _)) = self.persons.iter().enumerate().find(|(_, p)| p.id == id) {
+            self.persons.remove(n);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn begin(&mut self) -> StoreResult<()> {
+        self.saved = Some((self.persons.clone(), self.next_id));
+        Ok(())
+    }
+
+    fn commit(&mut self) -> StoreResult<()> {
+        self.saved = None;
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> StoreResult<()> {
+        if let Some((persons, next_id)) = self.saved.take() {
+            self.persons = persons;
+            self.next_id = next_id;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the users in a `Vec`, so they are lost when the process ends.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MemoryUsers {
+    users: Vec<User>,
+}
+
+impl Default for MemoryUsers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryUsers {
+    pub fn new() -> Self {
+        Self { users: vec![] }
+    }
+}
+
+impl UsersStore for MemoryUsers {
+    fn get_user_by_username(&self, username: &str) -> StoreResult<Option<User>> {
+        Ok(self.users.iter().find(|u| u.username == username).cloned())
+    }
+
+    fn get_all_users(&self) -> StoreResult<Vec<User>> {
+        Ok(self.users.clone())
+    }
+
+    fn insert_user(&mut self, user: User) -> StoreResult<bool> {
+        if self.users.iter().any(|u| u.username == user.username) {
+            Ok(false)
+        } else {
+            self.users.push(user);
+            Ok(true)
+        }
+    }
+
+    fn update_user(&mut self, user: User) -> StoreResult<bool> {
+        if let Some(u) = self.users.iter_mut().find(|u| u.username == user.username) {
+            *u = user;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+const INVALID_CREDENTIALS: &str = "Invalid credentials";
+
+/// The persons and the users of a server, recording every change
+/// made to the persons in the audit log.
+/// Not available on WebAssembly, where the audit timestamps cannot be taken.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DbConnection {
+    persons: Box<dyn PersonsStore>,
+    users: Box<dyn UsersStore>,
+    audit: Box<dyn AuditStore>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DbConnection {
+    pub fn new(
+        persons: Box<dyn PersonsStore>,
+        users: Box<dyn UsersStore>,
+        audit: Box<dyn AuditStore>,
+    ) -> Self {
+        Self {
+            persons,
+            users,
+            audit,
+        }
+    }
+
+    /// Keeps the persons, the users and the audit log in the database file,
+    /// sharing one connection.
+    #[cfg(feature = "sqlite")]
+    pub fn open_sqlite(database_file: &str) -> StoreResult<Self> {
+        let conn = crate::open_connection(database_file)?;
+        Ok(Self::new(
+            Box::new(crate::SqlitePersons::new(conn.clone())?),
+            Box::new(crate::SqliteUsers::new(conn.clone())?),
+            Box::new(crate::SqliteAudit::new(conn)?),
+        ))
+    }
+
+    /// Keeps everything in the SQLite database file if one is given,
+    /// otherwise in memory.
+    #[cfg(feature = "sqlite")]
+    pub fn open(database_file: Option<&str>) -> StoreResult<Self> {
+        match database_file {
+            Some(database_file) => {
+                println!("using SQLite database: {}", database_file);
+                Self::open_sqlite(database_file)
+            }
+            None => Ok(Self::new(
+                Box::new(MemoryPersons::new()),
+                Box::new(MemoryUsers::new()),
+                Box::new(crate::MemoryAudit::new()),
+            )),
+        }
+    }
+
+    /// If there are no users yet and a password is given, creates an "admin"
+    /// user having the `Admin` role, so that the other users can be created
+    /// from the web pages; fails if there are still no users, as nobody could log in.
+    #[cfg(feature = "server")]
+    pub fn bootstrap_admin(&mut self, admin_password: Option<&str>) -> StoreResult<()> {
+        if !self.users.get_all_users()?.is_empty() {
+            return Ok(());
+        }
+        match admin_password {
+            Some(admin_password) => {
+                self.users
+                    .insert_user(User::new("admin", admin_password, Role::Admin)?)?;
+                Ok(())
+            }
+            None => Err(
+                "No users defined: set AUTH_ADMIN_PASSWORD to create the \"admin\" user"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Returns the enabled user having the credentials. Every failure has
+    /// the same message, so that it does not tell which usernames exist.
+    /// A password hashed with fewer rounds than the new hashes is hashed again.
+    #[cfg(feature = "server")]
+    pub fn check_credentials(
+        &mut self,
+        username: &str,
+        password: Option<&str>,
+    ) -> StoreResult<User> {
+        let password = password.unwrap_or_default();
+        match self.users.get_user_by_username(username)? {
+            Some(mut user) if user.check_password(password) && user.enabled => {
+                if crate::password::needs_rehash(&user.password_hash) {
+                    user.set_password(password)?;
+                    self.users.update_user(user.clone())?;
+                }
+                Ok(user)
+            }
+            Some(_) => Err(INVALID_CREDENTIALS.to_string()),
+            None => {
+                crate::password::verify_dummy_password(password);
+                Err(INVALID_CREDENTIALS.to_string())
+            }
+        }
+    }
+
+    pub fn get_user_by_username(&self, username: &str) -> StoreResult<Option<User>> {
+        self.users.get_user_by_username(username)
+    }
+
+    pub fn get_all_users(&self) -> StoreResult<Vec<User>> {
+        self.users.get_all_users()
+    }
+
+    pub fn insert_user(&mut self, user: User) -> StoreResult<bool> {
+        self.users.insert_user(user)
+    }
+
+    pub fn update_user(&mut self, user: User) -> StoreResult<bool> {
+        self.users.update_user(user)
+    }
+
+    pub fn get_person_by_id(&self, id: u32) -> StoreResult<Option<Person>> {
+        self.persons.get_person_by_id(id)
+    }
+
+    pub fn get_persons_by_partial_name(&self, subname: &str) -> StoreResult<Vec<Person>> {
+        self.persons.get_persons_by_partial_name(subname)
+    }
+
+    pub fn query_persons(&self, query: &PersonsQuery) -> StoreResult<PersonsPage> {
+        self.persons.query_persons(query)
+    }
+
+    /// Deletes the person as `username`, recording it in the audit log.
+    pub fn delete_by_id(&mut self, username: &str, id: u32) -> StoreResult<bool> {
+        self.in_transaction(|db| {
+            let before = match db.persons.get_person_by_id(id)? {
+                Some(person) => person,
+                None => return Ok(false),
+            };
+            if !db.persons.delete_by_id(id)? {
+                return Ok(false);
+            }
+            db.record(username, AuditAction::Delete, id, Some(before), None)?;
+            Ok(true)
+        })
+    }
+
+    /// Inserts the person as `username`, recording it in the audit log.
+    pub fn insert_person(&mut self, username: &str, person: Person) -> StoreResult<u32> {
+        self.in_transaction(|db| {
+            let id = db.persons.insert_person(person)?;
+            let after = db.persons.get_person_by_id(id)?;
+            db.record(username, AuditAction::Insert, id, None, after)?;
+            Ok(id)
+        })
+    }
+
+    /// Updates the person as `username`, recording it in the audit log.
+    pub fn update_person(&mut self, username: &str, person: Person) -> StoreResult<bool> {
+        self.in_transaction(|db| {
+            let id = person.id;
+            let before = match db.persons.get_person_by_id(id)? {
+                Some(person) => person,
+                None => return Ok(false),
+            };
+            if !db.persons.update_person(person)? {
+                return Ok(false);
+            }
+            let after = db.persons.get_person_by_id(id)?;
+            db.record(username, AuditAction::Update, id, Some(before), after)?;
+            Ok(true)
+        })
+    }
+
+    /// Returns the matching entries of the audit log, the newest first.
+    pub fn query_audit(&self, query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        self.audit.query(query)
+    }
+
+    /// Runs `change` in a transaction of the persons, so that a change
+    /// is undone when its audit entry cannot be recorded.
+    /// The SQLite stores must share their connection for the audit entry
+    /// to be in the same transaction.
+    fn in_transaction<T>(
+        &mut self,
+        change: impl FnOnce(&mut Self) -> StoreResult<T>,
+    ) -> StoreResult<T> {
+        self.persons.begin()?;
+        let result = change(self).and_then(|value| {
+            self.persons.commit()?;
+            Ok(value)
+        });
+        if let Err(e) = result {
+            return match self.persons.rollback() {
+                Ok(()) => Err(e),
+                Err(rollback_error) => Err(format!("{}; {}", e, rollback_error)),
+            };
+        }
+        result
+    }
+
+    fn record(
+        &mut self,
+        username: &str,
+        action: AuditAction,
+        person_id: u32,
+        before: Option<Person>,
+        after: Option<Person>,
+    ) -> StoreResult<()> {
+        self.audit.append(AuditEntry {
+            id: 0,
+            username: username.to_string(),
+            timestamp: now_secs(),
+            action,
+            person_id,
+            before,
+            after,
+        })?;
+        Ok(())
+    }
+}
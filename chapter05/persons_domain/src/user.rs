@@ -0,0 +1,40 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Permission, Role};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct User {
+    pub username: String,
+    /// Never sent to the clients, so it is empty in the users they receive.
+    #[serde(skip_serializing, default)]
+    pub password_hash: String,
+    pub role: Role,
+    pub enabled: bool,
+}
+
+impl User {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.role.has_permission(permission)
+    }
+}
+
+#[cfg(feature = "server")]
+impl User {
+    pub fn new(username: &str, password: &str, role: Role) -> crate::StoreResult<Self> {
+        Ok(Self {
+            username: username.to_string(),
+            password_hash: crate::password::hash_password(password)?,
+            role,
+            enabled: true,
+        })
+    }
+
+    pub fn check_password(&self, password: &str) -> bool {
+        crate::password::verify_password(password, &self.password_hash)
+    }
+
+    pub fn set_password(&mut self, password: &str) -> crate::StoreResult<()> {
+        self.password_hash = crate::password::hash_password(password)?;
+        Ok(())
+    }
+}
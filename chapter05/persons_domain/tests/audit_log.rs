@@ -0,0 +1,170 @@
+//! Every change made through a `DbConnection` is recorded exactly once,
+//! and is undone when it cannot be recorded. The dates filtering the log
+//! and those shown in it are in UTC.
+
+use persons_domain::{
+    date_to_secs, format_timestamp, AuditAction, AuditEntry, AuditQuery, AuditStore, DbConnection,
+    MemoryAudit, MemoryPersons, MemoryUsers, Person, PersonsStore, StoreResult,
+};
+#[cfg(feature = "sqlite")]
+use persons_domain::{open_connection, SqliteAudit, SqlitePersons, SqliteUsers};
+
+/// An audit log that cannot be written.
+struct FailingAudit;
+
+impl AuditStore for FailingAudit {
+    fn append(&mut self, _entry: AuditEntry) -> StoreResult<u64> {
+        Err("The audit log is full".to_string())
+    }
+
+    fn query(&self, _query: &AuditQuery) -> StoreResult<Vec<AuditEntry>> {
+        Ok(vec![])
+    }
+}
+
+fn person(name: &str) -> Person {
+    Person {
+        name: name.to_string(),
+        ..Person::default()
+    }
+}
+
+fn entries(db: &DbConnection) -> Vec<AuditEntry> {
+    db.query_audit(&AuditQuery::default()).unwrap()
+}
+
+fn records_each_change_once(mut db: DbConnection) {
+    let id = db.insert_person("susan", person("Hamlet")).unwrap();
+    let inserted = db.get_person_by_id(id).unwrap();
+    let log = entries(&db);
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].username, "susan");
+    assert_eq!(log[0].action, AuditAction::Insert);
+    assert_eq!(log[0].person_id, id);
+    assert_eq!(log[0].before, None);
+    assert_eq!(log[0].after, inserted);
+
+    let mut changed = person("Hamlet, Prince of Denmark");
+    changed.id = id;
+    assert!(db.update_person("joe", changed).unwrap());
+    let updated = db.get_person_by_id(id).unwrap();
+    let log = entries(&db);
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].username, "joe");
+    assert_eq!(log[0].action, AuditAction::Update);
+    assert_eq!(log[0].before, inserted);
+    assert_eq!(log[0].after, updated);
+
+    assert!(db.delete_by_id("susan", id).unwrap());
+    let log = entries(&db);
+    assert_eq!(log.len(), 3);
+    assert_eq!(log[0].action, AuditAction::Delete);
+    assert_eq!(log[0].before, updated);
+    assert_eq!(log[0].after, None);
+}
+
+fn records_nothing_for_missing_persons(mut db: DbConnection) {
+    let mut missing = person("Yorick");
+    missing.id = 7;
+    assert!(!db.update_person("susan", missing).unwrap());
+    assert!(!db.delete_by_id("susan", 7).unwrap());
+    assert!(entries(&db).is_empty());
+}
+
+/// The persons store must be empty.
+fn undoes_changes_that_cannot_be_recorded(mut persons: Box<dyn PersonsStore>) {
+    persons.insert_person(person("Ophelia")).unwrap();
+    let ophelia = persons.get_person_by_id(1).unwrap();
+    let mut db = DbConnection::new(
+        persons,
+        Box::new(MemoryUsers::new()),
+        Box::new(FailingAudit),
+    );
+    assert!(db.insert_person("susan", person("Hamlet")).is_err());
+    let mut changed = person("Ophelia, daughter of Polonius");
+    changed.id = 1;
+    assert!(db.update_person("susan", changed).is_err());
+    assert!(db.delete_by_id("susan", 1).is_err());
+    let found = db.get_persons_by_partial_name("").unwrap();
+    assert_eq!(Some(found), ophelia.map(|p| vec![p]));
+}
+
+#[test]
+fn records_each_change_once_in_memory() {
+    records_each_change_once(DbConnection::new(
+        Box::new(MemoryPersons::new()),
+        Box::new(MemoryUsers::new()),
+        Box::new(MemoryAudit::new()),
+    ));
+}
+
+#[test]
+fn records_nothing_for_missing_persons_in_memory() {
+    records_nothing_for_missing_persons(DbConnection::new(
+        Box::new(MemoryPersons::new()),
+        Box::new(MemoryUsers::new()),
+        Box::new(MemoryAudit::new()),
+    ));
+}
+
+#[test]
+fn undoes_changes_that_cannot_be_recorded_in_memory() {
+    undoes_changes_that_cannot_be_recorded(Box::new(MemoryPersons::new()));
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_db() -> DbConnection {
+    let conn = open_connection(":memory:").unwrap();
+    DbConnection::new(
+        Box::new(SqlitePersons::new(conn.clone()).unwrap()),
+        Box::new(SqliteUsers::new(conn.clone()).unwrap()),
+        Box::new(SqliteAudit::new(conn).unwrap()),
+    )
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn records_each_change_once_in_sqlite() {
+    records_each_change_once(sqlite_db());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn records_nothing_for_missing_persons_in_sqlite() {
+    records_nothing_for_missing_persons(sqlite_db());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn undoes_changes_that_cannot_be_recorded_in_sqlite() {
+    undoes_changes_that_cannot_be_recorded(Box::new(SqlitePersons::open(":memory:").unwrap()));
+}
+
+/// Known pairs of timestamps and dates, across leap days and centuries.
+const DATES: &[(u64, &str)] = &[
+    (0, "1970-01-01 00:00:00"),
+    (951_782_399, "2000-02-28 23:59:59"),
+    (951_782_400, "2000-02-29 00:00:00"),
+    (951_868_800, "2000-03-01 00:00:00"),
+    (1_709_251_199, "2024-02-29 23:59:59"),
+    (4_107_456_000, "2100-02-28 00:00:00"),
+    (4_107_542_400, "2100-03-01 00:00:00"),
+];
+
+#[test]
+fn formats_timestamps_in_utc() {
+    for (timestamp, date) in DATES {
+        assert_eq!(format_timestamp(*timestamp), *date, "{}", timestamp);
+    }
+}
+
+#[test]
+fn converts_dates_to_their_first_second() {
+    for (timestamp, date) in DATES {
+        let day_start = timestamp - timestamp % (24 * 60 * 60);
+        assert_eq!(date_to_secs(&date[..10]), Some(day_start), "{}", date);
+    }
+    assert_eq!(date_to_secs("2023-02-29"), None);
+    assert_eq!(date_to_secs("2100-02-29"), None);
+    assert_eq!(date_to_secs("1969-12-31"), None);
+}
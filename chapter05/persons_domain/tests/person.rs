@@ -0,0 +1,210 @@
+use persons_domain::{FieldError, Person};
+
+fn valid_person() -> Person {
+    Person {
+        name: "Hamlet".to_string(),
+        email: Some("hamlet@elsinore.dk".to_string()),
+        birth_date: Some("1964-02-29".to_string()),
+        phone: Some("+45 (33) 12-34.56".to_string()),
+        notes: Some("Prince of Denmark".to_string()),
+        ..Person::default()
+    }
+}
+
+/// The fields rejected by `validate`, in their order.
+fn invalid_fields(person: &Person) -> Vec<String> {
+    match person.validate() {
+        Ok(()) => vec![],
+        Err(errors) => errors.into_iter().map(|e| e.field).collect(),
+    }
+}
+
+fn with_email(email: &str) -> Person {
+    Person {
+        email: Some(email.to_string()),
+        ..valid_person()
+    }
+}
+
+fn with_birth_date(birth_date: &str) -> Person {
+    Person {
+        birth_date: Some(birth_date.to_string()),
+        ..valid_person()
+    }
+}
+
+fn with_phone(phone: &str) -> Person {
+    Person {
+        phone: Some(phone.to_string()),
+        ..valid_person()
+    }
+}
+
+#[test]
+fn accepts_a_valid_person() {
+    assert_eq!(valid_person().validate(), Ok(()));
+}
+
+#[test]
+fn accepts_a_person_with_only_a_name() {
+    let person = Person {
+        name: "Yorick".to_string(),
+        ..Person::default()
+    };
+    assert_eq!(person.validate(), Ok(()));
+}
+
+#[test]
+fn requires_a_short_enough_name() {
+    let person = Person {
+        name: String::new(),
+        ..valid_person()
+    };
+    assert_eq!(
+        person.validate(),
+        Err(vec![FieldError {
+            field: "name".to_string(),
+            message: "The name is required.".to_string(),
+        }])
+    );
+    let person = Person {
+        name: "é".repeat(100),
+        ..valid_person()
+    };
+    assert_eq!(invalid_fields(&person), Vec::<String>::new());
+    let person = Person {
+        name: "é".repeat(101),
+        ..valid_person()
+    };
+    assert_eq!(invalid_fields(&person), vec!["name"]);
+}
+
+#[test]
+fn rejects_invalid_emails() {
+    for email in &[
+        "hamlet.elsinore.dk",
+        "@elsinore.dk",
+        "hamlet@",
+        "hamlet@elsinore@dk",
+        "ham let@elsinore.dk",
+        "hamlet@localhost",
+        "hamlet@elsinore..dk",
+        "hamlet@.dk",
+        "hamlet@elsinore.",
+    ] {
+        assert_eq!(
+            invalid_fields(&with_email(email)),
+            vec!["email"],
+            "{}",
+            email
+        );
+    }
+    let long_email = format!("{}@elsinore.dk", "h".repeat(250));
+    let errors = with_email(&long_email).validate().unwrap_err();
+    assert_eq!(errors[0].message, "At most 254 characters are allowed.");
+}
+
+#[test]
+fn rejects_invalid_dates() {
+    for date in &[
+        "1964-2-29",
+        "1964/02/29",
+        "19640-02-29",
+        "1964-02-29 ",
+        "abcd-ef-gh",
+        "1964-00-10",
+        "1964-13-10",
+        "1964-04-00",
+        "1964-04-31",
+        "1700-02-29",
+        "1799-12-31",
+    ] {
+        assert_eq!(
+            invalid_fields(&with_birth_date(date)),
+            vec!["birth_date"],
+            "{}",
+            date
+        );
+    }
+}
+
+#[test]
+fn accepts_leap_days_only_in_leap_years() {
+    for date in &["2000-02-29", "2020-02-29", "1800-01-01", "2019-12-31"] {
+        assert_eq!(invalid_fields(&with_birth_date(date)), Vec::<String>::new());
+    }
+    for date in &["1900-02-29", "2019-02-29", "2021-02-30"] {
+        assert_eq!(invalid_fields(&with_birth_date(date)), vec!["birth_date"]);
+    }
+}
+
+#[test]
+fn rejects_invalid_phones() {
+    for phone in &["12", "555-CALL-NOW", "45+12345", "++4512345", "123_456"] {
+        assert_eq!(
+            invalid_fields(&with_phone(phone)),
+            vec!["phone"],
+            "{}",
+            phone
+        );
+    }
+    assert_eq!(
+        invalid_fields(&with_phone(&"1".repeat(30))),
+        Vec::<String>::new()
+    );
+    assert_eq!(invalid_fields(&with_phone(&"1".repeat(31))), vec!["phone"]);
+}
+
+#[test]
+fn rejects_too_long_notes() {
+    let person = Person {
+        notes: Some("x".repeat(2001)),
+        ..valid_person()
+    };
+    assert_eq!(invalid_fields(&person), vec!["notes"]);
+}
+
+#[test]
+fn reports_every_invalid_field() {
+    let person = Person {
+        name: String::new(),
+        email: Some("hamlet".to_string()),
+        birth_date: Some("yesterday".to_string()),
+        phone: Some("none".to_string()),
+        notes: Some("x".repeat(2001)),
+        ..Person::default()
+    };
+    assert_eq!(
+        invalid_fields(&person),
+        vec!["name", "email", "birth_date", "phone", "notes"]
+    );
+}
+
+#[test]
+fn normalize_trims_and_drops_blank_fields() {
+    let mut person = Person {
+        name: "  Hamlet \t".to_string(),
+        email: Some(" hamlet@elsinore.dk\n".to_string()),
+        birth_date: Some("   ".to_string()),
+        phone: Some(String::new()),
+        notes: Some("  To be,  or not to be  ".to_string()),
+        ..Person::default()
+    };
+    person.normalize();
+    assert_eq!(person.name, "Hamlet");
+    assert_eq!(person.email.as_deref(), Some("hamlet@elsinore.dk"));
+    assert_eq!(person.birth_date, None);
+    assert_eq!(person.phone, None);
+    assert_eq!(person.notes.as_deref(), Some("To be,  or not to be"));
+    assert_eq!(person.validate(), Ok(()));
+}
+
+#[test]
+fn normalize_lets_a_blank_name_be_rejected() {
+    let mut person = Person {
+        name: "   ".to_string(),
+        ..Person::default()
+    };
+    person.normalize();
+    assert_eq!(invalid_fields(&person), vec!["name"]);
+}
@@ -0,0 +1,190 @@
+use persons_domain::{
+    fold_text, Person, PersonsFilter, PersonsPage, PersonsQuery, SortColumn, MAX_PAGE_SIZE,
+};
+
+fn person(id: u32, name: &str, birth_date: Option<&str>, created_at: u64) -> Person {
+    Person {
+        id,
+        name: name.to_string(),
+        birth_date: birth_date.map(str::to_string),
+        created_at,
+        ..Person::default()
+    }
+}
+
+fn persons() -> Vec<Person> {
+    vec![
+        person(1, "emma", Some("1990-05-01"), 30),
+        person(2, "Élise", None, 10),
+        person(3, "Eddie", Some("1985-12-31"), 20),
+        person(4, "Elise", Some("1990-05-02"), 10),
+    ]
+}
+
+fn ids(page: &PersonsPage) -> Vec<u32> {
+    page.persons.iter().map(|p| p.id).collect()
+}
+
+fn apply(query: PersonsQuery) -> PersonsPage {
+    query.apply(persons().iter())
+}
+
+#[test]
+fn folds_case_and_accents() {
+    assert_eq!(fold_text("Élise ÇA"), "elise ca");
+}
+
+#[test]
+fn sorts_text_ignoring_case_and_accents_then_by_id() {
+    let page = apply(PersonsQuery {
+        sort_by: SortColumn::Name,
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![3, 2, 4, 1]);
+    let page = apply(PersonsQuery {
+        sort_by: SortColumn::Name,
+        descending: true,
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![1, 4, 2, 3]);
+}
+
+#[test]
+fn sorts_missing_values_first() {
+    let page = apply(PersonsQuery {
+        sort_by: SortColumn::BirthDate,
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![2, 3, 1, 4]);
+}
+
+#[test]
+fn sorts_numbers_then_by_id() {
+    let page = apply(PersonsQuery {
+        sort_by: SortColumn::CreatedAt,
+        descending: true,
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![1, 3, 4, 2]);
+}
+
+#[test]
+fn compare_agrees_with_apply() {
+    let query = PersonsQuery {
+        sort_by: SortColumn::Name,
+        descending: true,
+        ..PersonsQuery::default()
+    };
+    let mut sorted = persons();
+    sorted.sort_by(|a, b| query.compare(a, b));
+    assert_eq!(
+        sorted.iter().map(|p| p.id).collect::<Vec<_>>(),
+        ids(&query.apply(persons().iter()))
+    );
+}
+
+#[test]
+fn filters_by_partial_name_and_birth_dates() {
+    let page = apply(PersonsQuery {
+        partial_name: "ELIS".to_string(),
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![2, 4]);
+    let page = apply(PersonsQuery {
+        born_from: Some("1990-05-01".to_string()),
+        born_to: Some("1990-05-01".to_string()),
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![1]);
+    // A person without a birth date is outside any range.
+    let page = apply(PersonsQuery {
+        born_to: Some("2000-01-01".to_string()),
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![1, 3, 4]);
+}
+
+#[test]
+fn pages_the_sorted_persons() {
+    let page = apply(PersonsQuery {
+        page: 2,
+        page_size: 3,
+        ..PersonsQuery::default()
+    });
+    assert_eq!(ids(&page), vec![4]);
+    assert_eq!(page.total, 4);
+    assert_eq!(page.page_count(), 2);
+    let page = apply(PersonsQuery {
+        page: 3,
+        page_size: 3,
+        ..PersonsQuery::default()
+    });
+    assert!(page.persons.is_empty());
+}
+
+#[test]
+fn counts_at_least_one_page() {
+    let page_count = |total, page_size| {
+        PersonsPage {
+            persons: vec![],
+            total,
+            page: 1,
+            page_size,
+        }
+        .page_count()
+    };
+    assert_eq!(page_count(0, 20), 1);
+    assert_eq!(page_count(20, 20), 1);
+    assert_eq!(page_count(21, 20), 2);
+    assert_eq!(page_count(5, 0), 5);
+    assert_eq!(page_count(u32::MAX, u32::MAX), 1);
+}
+
+#[test]
+fn normalize_brings_the_page_into_range() {
+    let mut query = PersonsQuery {
+        page: 0,
+        page_size: 0,
+        ..PersonsQuery::default()
+    };
+    query.normalize();
+    assert_eq!((query.page, query.page_size), (1, 1));
+    query.page_size = MAX_PAGE_SIZE + 1;
+    query.normalize();
+    assert_eq!(query.page_size, MAX_PAGE_SIZE);
+}
+
+#[test]
+fn filter_defaults_the_missing_and_unknown_values() {
+    assert_eq!(PersonsFilter::default().to_query(), PersonsQuery::default());
+    let filter = PersonsFilter {
+        partial_name: Some("ham".to_string()),
+        born_from: Some(String::new()),
+        born_to: Some("1990-01-01".to_string()),
+        sort_by: Some("shoe_size".to_string()),
+        order: Some("up".to_string()),
+        page: Some(0),
+        page_size: Some(MAX_PAGE_SIZE * 2),
+        ..PersonsFilter::default()
+    };
+    assert_eq!(
+        filter.to_query(),
+        PersonsQuery {
+            partial_name: "ham".to_string(),
+            born_to: Some("1990-01-01".to_string()),
+            page: 1,
+            page_size: MAX_PAGE_SIZE,
+            ..PersonsQuery::default()
+        }
+    );
+    let filter = PersonsFilter {
+        sort_by: Some("birth_date".to_string()),
+        order: Some("desc".to_string()),
+        ..PersonsFilter::default()
+    };
+    let query = filter.to_query();
+    assert_eq!(
+        (query.sort_by, query.descending),
+        (SortColumn::BirthDate, true)
+    );
+}
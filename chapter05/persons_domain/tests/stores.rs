@@ -0,0 +1,263 @@
+//! The same checks run against every storage backend; the SQLite ones need
+//! the `sqlite` feature, as in `cargo test --all-features`.
+
+use persons_domain::{
+    MemoryPersons, MemoryUsers, Person, PersonsQuery, PersonsStore, Role, SortColumn, User,
+    UsersStore,
+};
+#[cfg(feature = "sqlite")]
+use persons_domain::{SqlitePersons, SqliteUsers};
+
+fn person(name: &str, email: Option<&str>) -> Person {
+    Person {
+        name: name.to_string(),
+        email: email.map(str::to_string),
+        created_at: 1000,
+        updated_at: 1000,
+        ..Person::default()
+    }
+}
+
+fn user(username: &str, role: Role) -> User {
+    User {
+        username: username.to_string(),
+        password_hash: format!("hash of {}", username),
+        role,
+        enabled: true,
+    }
+}
+
+fn names(persons: &[Person]) -> Vec<&str> {
+    persons.iter().map(|p| p.name.as_str()).collect()
+}
+
+/// Inserts the persons, returning their ids.
+fn insert_all(store: &mut impl PersonsStore, persons: &[Person]) -> Vec<u32> {
+    persons
+        .iter()
+        .map(|p| store.insert_person(p.clone()).unwrap())
+        .collect()
+}
+
+fn inserts_and_gets_by_id(mut store: impl PersonsStore) {
+    let ids = insert_all(
+        &mut store,
+        &[
+            person("Hamlet", Some("hamlet@elsinore.dk")),
+            person("Ophelia", None),
+        ],
+    );
+    assert_eq!(ids, vec![1, 2]);
+    let hamlet = store.get_person_by_id(1).unwrap().unwrap();
+    assert_eq!(hamlet.id, 1);
+    assert_eq!(hamlet.name, "Hamlet");
+    assert_eq!(hamlet.email.as_deref(), Some("hamlet@elsinore.dk"));
+    assert_eq!(hamlet.created_at, 1000);
+    assert_eq!(store.get_person_by_id(2).unwrap().unwrap().email, None);
+    assert_eq!(store.get_person_by_id(3).unwrap(), None);
+}
+
+fn never_reuses_ids(mut store: impl PersonsStore) {
+    insert_all(
+        &mut store,
+        &[person("Hamlet", None), person("Ophelia", None)],
+    );
+    assert!(store.delete_by_id(2).unwrap());
+    assert_eq!(store.insert_person(person("Laertes", None)).unwrap(), 3);
+}
+
+fn finds_by_partial_name_ignoring_case_and_accents(mut store: impl PersonsStore) {
+    insert_all(
+        &mut store,
+        &[
+            person("Élise", None),
+            person("Hamlet", None),
+            person("Annelise", None),
+        ],
+    );
+    let found = store.get_persons_by_partial_name("ELIS").unwrap();
+    assert_eq!(names(&found), vec!["Élise", "Annelise"]);
+    let found = store.get_persons_by_partial_name("élise").unwrap();
+    assert_eq!(names(&found), vec!["Élise", "Annelise"]);
+    assert!(store
+        .get_persons_by_partial_name("ophelia")
+        .unwrap()
+        .is_empty());
+    assert_eq!(store.get_persons_by_partial_name("").unwrap().len(), 3);
+}
+
+fn queries_filters_sorts_and_pages(mut store: impl PersonsStore) {
+    insert_all(
+        &mut store,
+        &[
+            person("Ophelia", Some("ophelia@elsinore.dk")),
+            person("Hamlet", Some("hamlet@elsinore.dk")),
+            person("Horatio", Some("horatio@wittenberg.de")),
+            person("Polonius", None),
+        ],
+    );
+    let query = PersonsQuery {
+        email: "ELSINORE".to_string(),
+        sort_by: SortColumn::Name,
+        ..PersonsQuery::default()
+    };
+    let page = store.query_persons(&query).unwrap();
+    assert_eq!(names(&page.persons), vec!["Hamlet", "Ophelia"]);
+    assert_eq!(page.total, 2);
+
+    let query = PersonsQuery {
+        sort_by: SortColumn::Name,
+        descending: true,
+        page: 2,
+        page_size: 3,
+        ..PersonsQuery::default()
+    };
+    let page = store.query_persons(&query).unwrap();
+    assert_eq!(names(&page.persons), vec!["Hamlet"]);
+    assert_eq!(page.total, 4);
+    assert_eq!(page.page, 2);
+    assert_eq!(page.page_count(), 2);
+}
+
+fn updates_all_fields_but_the_creation_time(mut store: impl PersonsStore) {
+    insert_all(&mut store, &[person("Hamlet", None)]);
+    let mut changed = person("Hamlet, Prince of Denmark", Some("hamlet@elsinore.dk"));
+    changed.id = 1;
+    changed.phone = Some("+45 1234".to_string());
+    changed.created_at = 5000;
+    changed.updated_at = 6000;
+    assert!(store.update_person(changed).unwrap());
+    let hamlet = store.get_person_by_id(1).unwrap().unwrap();
+    assert_eq!(hamlet.name, "Hamlet, Prince of Denmark");
+    assert_eq!(hamlet.email.as_deref(), Some("hamlet@elsinore.dk"));
+    assert_eq!(hamlet.phone.as_deref(), Some("+45 1234"));
+    assert_eq!(hamlet.created_at, 1000);
+    assert_eq!(hamlet.updated_at, 6000);
+
+    let mut missing = person("Yorick", None);
+    missing.id = 7;
+    assert!(!store.update_person(missing).unwrap());
+}
+
+fn deletes_by_id(mut store: impl PersonsStore) {
+    insert_all(
+        &mut store,
+        &[person("Hamlet", None), person("Ophelia", None)],
+    );
+    assert!(store.delete_by_id(1).unwrap());
+    assert!(!store.delete_by_id(1).unwrap());
+    assert_eq!(store.get_person_by_id(1).unwrap(), None);
+    assert!(store.get_person_by_id(2).unwrap().is_some());
+}
+
+fn rolls_back_transactions(mut store: impl PersonsStore) {
+    insert_all(&mut store, &[person("Hamlet", None)]);
+    store.begin().unwrap();
+    insert_all(&mut store, &[person("Ophelia", None)]);
+    assert!(store.delete_by_id(1).unwrap());
+    store.rollback().unwrap();
+    assert_eq!(
+        names(&store.get_persons_by_partial_name("").unwrap()),
+        vec!["Hamlet"]
+    );
+    // The ids given in the transaction can be given again.
+    assert_eq!(store.insert_person(person("Laertes", None)).unwrap(), 2);
+
+    store.begin().unwrap();
+    assert!(store.delete_by_id(2).unwrap());
+    store.commit().unwrap();
+    assert_eq!(store.get_person_by_id(2).unwrap(), None);
+}
+
+fn inserts_users_once(mut store: impl UsersStore) {
+    assert!(store.insert_user(user("susan", Role::Admin)).unwrap());
+    assert!(store.insert_user(user("joe", Role::Viewer)).unwrap());
+    assert!(!store.insert_user(user("susan", Role::Viewer)).unwrap());
+    assert_eq!(
+        store.get_user_by_username("susan").unwrap(),
+        Some(user("susan", Role::Admin))
+    );
+    assert_eq!(store.get_user_by_username("mary").unwrap(), None);
+    let mut usernames: Vec<String> = store
+        .get_all_users()
+        .unwrap()
+        .into_iter()
+        .map(|u| u.username)
+        .collect();
+    usernames.sort();
+    assert_eq!(usernames, vec!["joe", "susan"]);
+}
+
+fn updates_users(mut store: impl UsersStore) {
+    store.insert_user(user("joe", Role::Viewer)).unwrap();
+    let mut joe = user("joe", Role::Editor);
+    joe.enabled = false;
+    joe.password_hash = "another hash".to_string();
+    assert!(store.update_user(joe.clone()).unwrap());
+    assert_eq!(store.get_user_by_username("joe").unwrap(), Some(joe));
+    assert!(!store.update_user(user("mary", Role::Admin)).unwrap());
+}
+
+/// Runs every check against the stores made by the given expressions.
+macro_rules! store_tests {
+    ($backend:ident, $persons:expr, $users:expr) => {
+        mod $backend {
+            use super::*;
+
+            #[test]
+            fn inserts_and_gets_by_id() {
+                super::inserts_and_gets_by_id($persons);
+            }
+
+            #[test]
+            fn never_reuses_ids() {
+                super::never_reuses_ids($persons);
+            }
+
+            #[test]
+            fn finds_by_partial_name_ignoring_case_and_accents() {
+                super::finds_by_partial_name_ignoring_case_and_accents($persons);
+            }
+
+            #[test]
+            fn queries_filters_sorts_and_pages() {
+                super::queries_filters_sorts_and_pages($persons);
+            }
+
+            #[test]
+            fn updates_all_fields_but_the_creation_time() {
+                super::updates_all_fields_but_the_creation_time($persons);
+            }
+
+            #[test]
+            fn deletes_by_id() {
+                super::deletes_by_id($persons);
+            }
+
+            #[test]
+            fn rolls_back_transactions() {
+                super::rolls_back_transactions($persons);
+            }
+
+            #[test]
+            fn inserts_users_once() {
+                super::inserts_users_once($users);
+            }
+
+            #[test]
+            fn updates_users() {
+                super::updates_users($users);
+            }
+        }
+    };
+}
+
+store_tests!(memory, MemoryPersons::new(), MemoryUsers::new());
+
+// Every store opened on ":memory:" gets its own empty database.
+#[cfg(feature = "sqlite")]
+store_tests!(
+    sqlite,
+    SqlitePersons::open(":memory:").unwrap(),
+    SqliteUsers::open(":memory:").unwrap()
+);
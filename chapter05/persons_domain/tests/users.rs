@@ -0,0 +1,87 @@
+#![cfg(feature = "server")]
+
+use persons_domain::{AppState, DbConnection, MemoryAudit, MemoryPersons, MemoryUsers, Role, User};
+
+fn memory_db() -> DbConnection {
+    DbConnection::new(
+        Box::new(MemoryPersons::new()),
+        Box::new(MemoryUsers::new()),
+        Box::new(MemoryAudit::new()),
+    )
+}
+
+#[test]
+fn bootstraps_the_admin_only_without_users() {
+    let mut db = memory_db();
+    assert!(db.bootstrap_admin(None).is_err());
+    db.bootstrap_admin(Some("xadmin")).unwrap();
+    let admin = db.get_user_by_username("admin").unwrap().unwrap();
+    assert_eq!(admin.role, Role::Admin);
+    assert!(admin.check_password("xadmin"));
+
+    // Another password does not replace the one of the existing admin.
+    db.bootstrap_admin(Some("another")).unwrap();
+    db.bootstrap_admin(None).unwrap();
+    assert!(db.check_credentials("admin", Some("xadmin")).is_ok());
+    assert_eq!(db.get_all_users().unwrap().len(), 1);
+}
+
+#[test]
+fn accepts_only_the_right_credentials_of_enabled_users() {
+    let mut db = memory_db();
+    db.insert_user(User::new("joe", "xjoe", Role::Viewer).unwrap())
+        .unwrap();
+    assert_eq!(
+        db.check_credentials("joe", Some("xjoe")).unwrap().username,
+        "joe"
+    );
+    let invalid = Err("Invalid credentials".to_string());
+    assert_eq!(db.check_credentials("joe", Some("xjo")), invalid);
+    assert_eq!(db.check_credentials("joe", None), invalid);
+    assert_eq!(db.check_credentials("mary", Some("xjoe")), invalid);
+
+    let mut joe = db.get_user_by_username("joe").unwrap().unwrap();
+    joe.enabled = false;
+    db.update_user(joe).unwrap();
+    assert_eq!(db.check_credentials("joe", Some("xjoe")), invalid);
+}
+
+#[test]
+fn rehashes_at_login_the_passwords_hashed_with_fewer_rounds() {
+    let mut db = memory_db();
+    let mut joe = User::new("joe", "xjoe", Role::Viewer).unwrap();
+    joe.password_hash = pbkdf2::pbkdf2_simple("xjoe", 10_000).unwrap();
+    let old_hash = joe.password_hash.clone();
+    db.insert_user(joe).unwrap();
+
+    // A failed login keeps the old hash.
+    assert!(db.check_credentials("joe", Some("xjo")).is_err());
+    let joe = db.get_user_by_username("joe").unwrap().unwrap();
+    assert_eq!(joe.password_hash, old_hash);
+
+    db.check_credentials("joe", Some("xjoe")).unwrap();
+    let joe = db.get_user_by_username("joe").unwrap().unwrap();
+    assert_ne!(joe.password_hash, old_hash);
+    assert!(joe.password_hash.starts_with("$rpbkdf2$0$AAknwA==$"));
+    assert!(joe.check_password("xjoe"));
+}
+
+#[test]
+fn resolves_sessions_of_enabled_users() {
+    let mut state = AppState::new(memory_db());
+    state
+        .db
+        .insert_user(User::new("joe", "xjoe", Role::Viewer).unwrap())
+        .unwrap();
+    let token = state.sessions.issue("joe");
+    assert_eq!(state.logged_user(&token).unwrap().username, "joe");
+    assert!(state.logged_user("not a token").is_none());
+
+    let mut joe = state.db.get_user_by_username("joe").unwrap().unwrap();
+    joe.enabled = false;
+    state.db.update_user(joe).unwrap();
+    assert!(state.logged_user(&token).is_none());
+
+    let token = state.sessions.issue("mary");
+    assert!(state.logged_user(&token).is_none());
+}
@@ -0,0 +1,252 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use lazy_static::lazy_static;
+use persons_domain::FieldError;
+use serde_derive::Serialize;
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref TEMPLATES: RwLock<tera::Tera> = RwLock::new(tera::Tera::default());
+}
+
+/// Sets the templates of the pages, which each server loads from its own directory.
+pub fn set_templates(templates: tera::Tera) {
+    *TEMPLATES.write().unwrap() = templates;
+}
+
+fn render(template: &str, context: &tera::Context) -> tera::Result<String> {
+    TEMPLATES.read().unwrap().render(template, context)
+}
+
+/// Why a request failed; as a handler error it is answered with a JSON body.
+#[derive(Debug)]
+pub enum AppError {
+    /// A parameter is missing or invalid.
+    BadRequest(String),
+    /// Some fields of a submitted person are invalid.
+    InvalidFields(Vec<FieldError>),
+    NotFound(String),
+    /// No user is logged in, or the credentials are wrong.
+    Unauthorized(String),
+    /// The logged user lacks a permission.
+    Forbidden(String),
+    Storage(String),
+    Template(String),
+}
+
+impl AppError {
+    pub fn not_logged_in() -> Self {
+        AppError::Unauthorized("Not logged in".to_string())
+    }
+
+    pub fn insufficient_privileges(username: &str) -> Self {
+        AppError::Forbidden(format!("Insufficient privileges for user: {}", username))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) | AppError::InvalidFields(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Storage(_) | AppError::Template(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Answers with an HTML page for the page routes, and with JSON otherwise,
+    /// for the errors raised before the handler is chosen
+    /// and for the requests not matching any route.
+    pub fn for_request(self, req: &HttpRequest) -> actix_web::Error {
+        if is_page_path(req.path()) {
+            PageError(self).into()
+        } else {
+            self.into()
+        }
+    }
+}
+
+fn is_page_path(path: &str) -> bool {
+    path == "/" || path.starts_with("/page/")
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AppError::BadRequest(msg)
+            | AppError::NotFound(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg) => write!(f, "{}", msg),
+            AppError::InvalidFields(errors) => write!(
+                f,
+                "Invalid fields: {}",
+                errors
+                    .iter()
+                    .map(|e| e.field.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            AppError::Storage(msg) => write!(f, "Storage failure: {}", msg),
+            AppError::Template(msg) => write!(f, "Template failure: {}", msg),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl ResponseError for AppError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status();
+        if status.is_server_error() {
+            println!("{}", self);
+        }
+        match self {
+            // The form shows each message next to its field.
+            AppError::InvalidFields(errors) => HttpResponse::build(status).json(errors),
+            _ => HttpResponse::build(status).json(ErrorBody {
+                error: self.to_string(),
+            }),
+        }
+    }
+}
+
+/// An error of a page route, answered with an HTML page.
+#[derive(Debug)]
+pub struct PageError(pub AppError);
+
+impl From<AppError> for PageError {
+    fn from(error: AppError) -> Self {
+        PageError(error)
+    }
+}
+
+impl std::fmt::Display for PageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for PageError {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.0.status();
+        if status.is_server_error() {
+            println!("{}", self.0);
+        }
+        let mut context = tera::Context::new();
+        context.insert("error_message", &self.0.to_string());
+        // Whoever is not logged in is asked to log in.
+        let template = match self.0 {
+            AppError::Unauthorized(_) => "login.html",
+            _ => "error.html",
+        };
+        context.insert("status", &status.as_u16());
+        match render(template, &context) {
+            Ok(body) => HttpResponse::build(status)
+                .content_type("text/html")
+                .body(body),
+            Err(e) => {
+                println!("Template failure: {}", e);
+                HttpResponse::build(status)
+                    .content_type("text/plain")
+                    .body(self.0.to_string())
+            }
+        }
+    }
+}
+
+/// Renders a page template, failing with a `Template` error.
+pub fn render_page(template: &str, context: &tera::Context) -> Result<HttpResponse, AppError> {
+    let body = render(template, context).map_err(|e| AppError::Template(e.to_string()))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::Body;
+    use actix_web::http::header::CONTENT_TYPE;
+    use actix_web::test::TestRequest;
+
+    fn set_test_templates() {
+        let mut templates = tera::Tera::default();
+        templates
+            .add_raw_templates(vec![
+                ("error.html", "<p>{{ status }}: {{ error_message }}</p>"),
+                ("login.html", "<p>Log in: {{ error_message }}</p>"),
+            ])
+            .unwrap();
+        set_templates(templates);
+    }
+
+    /// The status, the content type and the body of the answer to the error.
+    fn answer(error: AppError, path: &str) -> (StatusCode, String, String) {
+        set_test_templates();
+        let req = TestRequest::with_uri(path).to_http_request();
+        let response = error.for_request(&req).as_response_error().error_response();
+        let content_type = response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = match response.body().as_ref() {
+            Some(Body::Bytes(bytes)) => String::from_utf8(bytes.to_vec()).unwrap(),
+            _ => String::new(),
+        };
+        (response.status(), content_type, body)
+    }
+
+    #[test]
+    fn tells_the_page_paths() {
+        assert!(is_page_path("/"));
+        assert!(is_page_path("/page/persons"));
+        assert!(!is_page_path("/persons"));
+        assert!(!is_page_path("/pages"));
+        assert!(!is_page_path("/one_person"));
+    }
+
+    #[test]
+    fn answers_the_page_paths_with_html() {
+        let (status, content_type, body) = answer(
+            AppError::NotFound("No such page".to_string()),
+            "/page/nothing",
+        );
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content_type, "text/html");
+        assert_eq!(body, "<p>404: No such page</p>");
+
+        let (status, content_type, body) = answer(AppError::not_logged_in(), "/");
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(content_type, "text/html");
+        assert_eq!(body, "<p>Log in: Not logged in</p>");
+    }
+
+    #[test]
+    fn answers_the_other_paths_with_json() {
+        let (status, content_type, body) =
+            answer(AppError::insufficient_privileges("joe"), "/persons");
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(content_type, "application/json");
+        assert_eq!(body, r#"{"error":"Insufficient privileges for user: joe"}"#);
+
+        let (status, content_type, _) =
+            answer(AppError::Storage("Disk full".to_string()), "/one_person");
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[test]
+    fn answers_invalid_fields_with_each_message() {
+        let error = AppError::InvalidFields(vec![FieldError {
+            field: "name".to_string(),
+            message: "The name is required.".to_string(),
+        }]);
+        let (status, content_type, body) = answer(error, "/one_person");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, "application/json");
+        assert_eq!(
+            body,
+            r#"[{"field":"name","message":"The name is required."}]"#
+        );
+    }
+}
@@ -0,0 +1,14 @@
+//! The parts of the persons web servers built on actix-web,
+//! kept apart from `persons_domain`, which also builds for WebAssembly.
+//! The `sessions` feature adds the extractors of the logged user.
+
+mod error;
+#[cfg(feature = "sessions")]
+mod session;
+
+pub use error::{render_page, set_templates, AppError, PageError};
+#[cfg(feature = "sessions")]
+pub use session::{
+    bearer_token, resolve_session, Authorized, CanDelete, CanExport, CanInsert, CanManageUsers,
+    CanRead, CanUpdate, CanViewAudit, LoggedUser, RequiredPermission,
+};
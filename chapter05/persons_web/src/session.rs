@@ -0,0 +1,107 @@
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest};
+use persons_domain::{AppState, Permission, User};
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use crate::AppError;
+
+/// The user of the current request, put in the request extensions
+/// by the session middleware when a valid token is received.
+#[derive(Clone)]
+pub struct LoggedUser {
+    pub user: User,
+    pub token: String,
+}
+
+impl FromRequest for LoggedUser {
+    type Config = ();
+    type Error = actix_web::Error;
+    type Future = Result<Self, Self::Error>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        req.extensions()
+            .get::<LoggedUser>()
+            .cloned()
+            .ok_or_else(|| AppError::not_logged_in().for_request(req))
+    }
+}
+
+/// A permission required by a handler, as a type parameter of `Authorized`.
+pub trait RequiredPermission: 'static {
+    const PERMISSION: Permission;
+}
+
+pub struct CanRead;
+pub struct CanInsert;
+pub struct CanUpdate;
+pub struct CanDelete;
+pub struct CanManageUsers;
+pub struct CanExport;
+pub struct CanViewAudit;
+
+impl RequiredPermission for CanRead {
+    const PERMISSION: Permission = Permission::Read;
+}
+impl RequiredPermission for CanInsert {
+    const PERMISSION: Permission = Permission::Insert;
+}
+impl RequiredPermission for CanUpdate {
+    const PERMISSION: Permission = Permission::Update;
+}
+impl RequiredPermission for CanDelete {
+    const PERMISSION: Permission = Permission::Delete;
+}
+impl RequiredPermission for CanManageUsers {
+    const PERMISSION: Permission = Permission::ManageUsers;
+}
+impl RequiredPermission for CanExport {
+    const PERMISSION: Permission = Permission::Export;
+}
+impl RequiredPermission for CanViewAudit {
+    const PERMISSION: Permission = Permission::ViewAudit;
+}
+
+/// Extracts the logged user, failing if they lack the permission `P`.
+pub struct Authorized<P: RequiredPermission> {
+    pub user: User,
+    pub token: String,
+    _permission: PhantomData<P>,
+}
+
+impl<P: RequiredPermission> FromRequest for Authorized<P> {
+    type Config = ();
+    type Error = actix_web::Error;
+    type Future = Result<Self, Self::Error>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let logged = LoggedUser::from_request(req, payload)?;
+        if logged.user.has_permission(P::PERMISSION) {
+            Ok(Authorized {
+                user: logged.user,
+                token: logged.token,
+                _permission: PhantomData,
+            })
+        } else {
+            Err(AppError::insufficient_privileges(&logged.user.username).for_request(req))
+        }
+    }
+}
+
+/// The token of an "Authorization: Bearer" header of the request, if any.
+pub fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    let authorization = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    if authorization.starts_with("Bearer ") {
+        Some(authorization["Bearer ".len()..].to_string())
+    } else {
+        None
+    }
+}
+
+/// Finds the user of the session of `token`, to be put in the request
+/// extensions by the session middleware.
+pub fn resolve_session(token: String, state: &Mutex<AppState>) -> Option<LoggedUser> {
+    let user = state.lock().unwrap().logged_user(&token)?;
+    Some(LoggedUser { user, token })
+}
@@ -0,0 +1,419 @@
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::str::FromStr;
+
+use crate::logger;
+
+/// The keys which can be overridden, with their command-line flag and the
+/// name of their environment variable, after the prefix of the server.
+const OVERRIDES: &[(&str, &str, &str)] = &[
+    ("server.bind_address", "--bind-address", "BIND_ADDRESS"),
+    ("server.workers", "--workers", "WORKERS"),
+    ("server.log_level", "--log-level", "LOG_LEVEL"),
+    (
+        "server.tls.certificate_file",
+        "--tls-certificate-file",
+        "TLS_CERTIFICATE_FILE",
+    ),
+    (
+        "server.tls.private_key_file",
+        "--tls-private-key-file",
+        "TLS_PRIVATE_KEY_FILE",
+    ),
+    ("storage.root", "--storage-root", "STORAGE_ROOT"),
+    ("storage.database_file", "--database-file", "DATABASE_FILE"),
+    ("storage.users_file", "--users-file", "USERS"),
+    ("storage.blob_dir", "--blob-dir", "BLOB_DIR"),
+    (
+        "storage.max_upload_size",
+        "--max-upload-size",
+        "MAX_UPLOAD_SIZE",
+    ),
+    ("templates.dir", "--templates-dir", "TEMPLATES_DIR"),
+];
+
+const LOG_LEVELS: &str = "off, error, warn, info, debug or trace";
+
+/// A configuration which cannot be used, naming the offending key
+/// and where its value comes from.
+#[derive(Debug)]
+pub struct ConfigError {
+    /// None if the whole file is invalid.
+    pub key: Option<String>,
+    /// The file, the environment variable or the flag.
+    pub source: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.key {
+            Some(key) => write!(
+                f,
+                "Invalid configuration key {} (from {}): {}",
+                key, self.source, self.message
+            ),
+            None => write!(
+                f,
+                "Invalid configuration (from {}): {}",
+                self.source, self.message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for std::io::Error {
+    fn from(error: ConfigError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, error.to_string())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct ServerSection {
+    /// As "host:port".
+    pub bind_address: String,
+    /// By default, as many as the CPU cores.
+    pub workers: Option<usize>,
+    pub log_level: String,
+    /// Without it, plain HTTP is served.
+    pub tls: Option<TlsSection>,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1:8080".to_string(),
+            workers: None,
+            log_level: "info".to_string(),
+            tls: None,
+        }
+    }
+}
+
+/// The PEM files of the certificate chain and of its private key.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct TlsSection {
+    pub certificate_file: String,
+    pub private_key_file: String,
+}
+
+/// The paths of the data, each used only by some servers.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct StorageSection {
+    /// The directory of the served files.
+    pub root: Option<String>,
+    pub database_file: Option<String>,
+    pub users_file: Option<String>,
+    /// The directory of the blobs shared by the identical files.
+    pub blob_dir: Option<String>,
+    /// In bytes.
+    pub max_upload_size: Option<u64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct TemplatesSection {
+    /// By default, "templates" in the current directory.
+    pub dir: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    pub server: ServerSection,
+    pub storage: StorageSection,
+    pub templates: TemplatesSection,
+    /// The command-line arguments which are not flags.
+    #[serde(skip)]
+    pub args: Vec<String>,
+    /// Where the value of each key comes from, if not from the file.
+    #[serde(skip)]
+    sources: HashMap<String, String>,
+    /// The configuration file, if any.
+    #[serde(skip)]
+    file: Option<String>,
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| format!("expected a number, found \"{}\"", value))
+}
+
+impl Config {
+    /// Loads the configuration of the server whose environment variables
+    /// start with `prefix`, as "FILE_TRANSFER": from the file given by the
+    /// --config flag or by the <prefix>_CONFIG variable, if any, then
+    /// overridden by the <prefix>_<NAME> variables and by the flags.
+    /// The defaults need no file.
+    pub fn load(prefix: &str) -> Result<Self, ConfigError> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--help") {
+            println!("{}", Self::usage(prefix));
+            std::process::exit(0);
+        }
+        Self::load_from(prefix, args, |name| std::env::var(name).ok())
+    }
+
+    /// As `load`, with the given arguments and environment.
+    pub fn load_from(
+        prefix: &str,
+        args: Vec<String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let args = split_args(args)?;
+        let config_var = format!("{}_CONFIG", prefix);
+        let mut config = match args.config_file.or_else(|| env(&config_var)) {
+            Some(file) => {
+                let invalid = |message: String| ConfigError {
+                    key: None,
+                    source: file.clone(),
+                    message,
+                };
+                let text = std::fs::read_to_string(&file).map_err(|e| invalid(e.to_string()))?;
+                let mut config: Config =
+                    toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+                config.file = Some(file);
+                config
+            }
+            None => Config::default(),
+        };
+        config.args = args.positional;
+
+        for (key, _, var_suffix) in OVERRIDES {
+            let var = format!("{}_{}", prefix, var_suffix);
+            if let Some(value) = env(&var) {
+                config.set(key, &value, format!("environment variable {}", var))?;
+            }
+        }
+        for (flag, value) in args.flags {
+            match OVERRIDES.iter().find(|(_, name, _)| *name == flag) {
+                Some((key, _, _)) => config.set(key, &value, format!("flag {}", flag))?,
+                None => {
+                    return Err(ConfigError {
+                        key: None,
+                        source: format!("flag {}", flag),
+                        message: format!("unknown flag; {}", Self::usage(prefix)),
+                    })
+                }
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The flags and the environment variables, for the server whose
+    /// environment variables start with `prefix`.
+    pub fn usage(prefix: &str) -> String {
+        let mut usage = format!(
+            "the accepted flags are:\n  --config <file>  ({}_CONFIG)\n",
+            prefix
+        );
+        for (key, flag, var_suffix) in OVERRIDES {
+            usage += &format!(
+                "  {} <{}>  ({}_{})\n",
+                flag,
+                key.rsplit('.').next().unwrap_or(key),
+                prefix,
+                var_suffix
+            );
+        }
+        usage
+    }
+
+    fn set(&mut self, key: &str, value: &str, source: String) -> Result<(), ConfigError> {
+        let invalid = |message: String| ConfigError {
+            key: Some(key.to_string()),
+            source: source.clone(),
+            message,
+        };
+        let text = value.to_string();
+        match key {
+            "server.bind_address" => self.server.bind_address = text,
+            "server.workers" => self.server.workers = Some(parse_number(value).map_err(invalid)?),
+            "server.log_level" => self.server.log_level = text,
+            "server.tls.certificate_file" => {
+                self.server
+                    .tls
+                    .get_or_insert_with(Default::default)
+                    .certificate_file = text
+            }
+            "server.tls.private_key_file" => {
+                self.server
+                    .tls
+                    .get_or_insert_with(Default::default)
+                    .private_key_file = text
+            }
+            "storage.root" => self.storage.root = Some(text),
+            "storage.database_file" => self.storage.database_file = Some(text),
+            "storage.users_file" => self.storage.users_file = Some(text),
+            "storage.blob_dir" => self.storage.blob_dir = Some(text),
+            "storage.max_upload_size" => {
+                self.storage.max_upload_size = Some(parse_number(value).map_err(invalid)?)
+            }
+            "templates.dir" => self.templates.dir = Some(text),
+            _ => return Err(invalid("unknown key".to_string())),
+        }
+        self.sources.insert(key.to_string(), source);
+        Ok(())
+    }
+
+    /// An error about a key, telling where its value comes from, also for
+    /// the checks made by a server.
+    pub fn invalid(&self, key: &str, message: impl Into<String>) -> ConfigError {
+        let source = self
+            .sources
+            .get(key)
+            .cloned()
+            .or_else(|| self.file.clone())
+            .unwrap_or_else(|| "the defaults".to_string());
+        ConfigError {
+            key: Some(key.to_string()),
+            source,
+            message: message.into(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let address = &self.server.bind_address;
+        let resolves = address
+            .to_socket_addrs()
+            .is_ok_and(|mut addrs| addrs.next().is_some());
+        if !resolves {
+            return Err(self.invalid(
+                "server.bind_address",
+                format!("expected \"host:port\", found \"{}\"", address),
+            ));
+        }
+        if self.server.workers == Some(0) {
+            return Err(self.invalid("server.workers", "must be at least 1".to_string()));
+        }
+        if logger::parse_level(&self.server.log_level).is_none() {
+            return Err(self.invalid(
+                "server.log_level",
+                format!(
+                    "expected {}, found \"{}\"",
+                    LOG_LEVELS, self.server.log_level
+                ),
+            ));
+        }
+        if let Some(tls) = &self.server.tls {
+            if !cfg!(feature = "tls") {
+                return Err(self.invalid(
+                    "server.tls",
+                    "this server was built without the tls feature".to_string(),
+                ));
+            }
+            for (key, file) in &[
+                ("server.tls.certificate_file", &tls.certificate_file),
+                ("server.tls.private_key_file", &tls.private_key_file),
+            ] {
+                if file.is_empty() {
+                    return Err(self.invalid(key, "missing, but required by TLS".to_string()));
+                }
+                check_readable(file).map_err(|message| self.invalid(key, message))?;
+            }
+        }
+        if let Some(file) = &self.storage.users_file {
+            check_readable(file).map_err(|message| self.invalid("storage.users_file", message))?;
+        }
+        if self.storage.max_upload_size == Some(0) {
+            return Err(self.invalid("storage.max_upload_size", "must be at least 1".to_string()));
+        }
+        if let Some(dir) = &self.templates.dir {
+            if !std::path::Path::new(dir).is_dir() {
+                return Err(self.invalid("templates.dir", format!("no directory \"{}\"", dir)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Installs a logger writing to the standard error the records up to
+    /// the configured level, such as those of the request logger of actix.
+    pub fn init_logger(&self) {
+        // The level was validated.
+        logger::init(logger::parse_level(&self.server.log_level).unwrap_or(log::LevelFilter::Info));
+    }
+
+    /// The pattern of the template files, for `tera::Tera::new`.
+    pub fn templates_glob(&self) -> String {
+        format!(
+            "{}/**/*",
+            self.templates
+                .dir
+                .as_deref()
+                .unwrap_or("templates")
+                .trim_end_matches('/')
+        )
+    }
+
+    /// The acceptor of the HTTPS connections, if TLS is configured.
+    #[cfg(feature = "tls")]
+    pub fn ssl_acceptor(&self) -> std::io::Result<Option<openssl::ssl::SslAcceptorBuilder>> {
+        match &self.server.tls {
+            Some(tls) => crate::tls::ssl_acceptor(tls).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// "http" or "https", to print the address of the server.
+    pub fn scheme(&self) -> &'static str {
+        if self.server.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        }
+    }
+}
+
+fn check_readable(file: &str) -> Result<(), String> {
+    std::fs::File::open(file)
+        .map(|_| ())
+        .map_err(|error| format!("cannot read \"{}\": {}", file, error))
+}
+
+/// The command-line arguments, sorted.
+#[derive(Default)]
+struct SplitArgs {
+    /// The value of the --config flag.
+    config_file: Option<String>,
+    /// The other flags with their values.
+    flags: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+/// Sorts the command-line arguments. A flag is followed by its value,
+/// or has it after an equal sign.
+fn split_args(args: Vec<String>) -> Result<SplitArgs, ConfigError> {
+    let mut split = SplitArgs::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            split.positional.push(arg);
+            continue;
+        }
+        let (flag, value) = match arg.find('=') {
+            Some(equal) => (arg[..equal].to_string(), Some(arg[equal + 1..].to_string())),
+            None => (arg.clone(), args.next()),
+        };
+        let value = value.ok_or_else(|| ConfigError {
+            key: None,
+            source: format!("flag {}", flag),
+            message: "missing value".to_string(),
+        })?;
+        if flag == "--config" {
+            split.config_file = Some(value);
+        } else {
+            split.flags.push((flag, value));
+        }
+    }
+    Ok(split)
+}
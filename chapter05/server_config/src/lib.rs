@@ -0,0 +1,15 @@
+//! The runtime configuration shared by the servers.
+//!
+//! It is read from a TOML file, then overridden by environment variables
+//! and by command-line flags. The `tls` feature adds the loading of the
+//! certificate, for the servers built with HTTPS support.
+
+mod config;
+mod logger;
+#[cfg(feature = "tls")]
+mod tls;
+
+pub use config::{
+    Config, ConfigError, ServerSection, StorageSection, TemplatesSection, TlsSection,
+};
+pub use logger::ACCESS_LOG_FORMAT;
@@ -0,0 +1,23 @@
+use log::{Level, LevelFilter};
+
+/// The format of the access log of the servers, for `middleware::Logger::new`.
+/// Unlike the default format, it has the path without the query string,
+/// which may carry passwords or tokens, and without the referer, which may too.
+pub const ACCESS_LOG_FORMAT: &str = "%a \"%U\" %s %b \"%{User-Agent}i\" %T";
+
+/// Installs `env_logger`, writing to the standard error the records up to
+/// `level`, unless another logger was installed before.
+pub fn init(level: LevelFilter) {
+    let _ = env_logger::Builder::new().filter_level(level).try_init();
+}
+
+/// The level named in the configuration, from "off" to "trace".
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    if name.eq_ignore_ascii_case("off") {
+        Some(LevelFilter::Off)
+    } else {
+        name.parse::<Level>()
+            .ok()
+            .map(|level| level.to_level_filter())
+    }
+}
@@ -0,0 +1,16 @@
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
+
+use crate::config::TlsSection;
+
+/// The acceptor of the HTTPS connections, with the configured certificate.
+pub fn ssl_acceptor(tls: &TlsSection) -> std::io::Result<SslAcceptorBuilder> {
+    let to_io_error = |error: openssl::error::ErrorStack| std::io::Error::other(error);
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).map_err(to_io_error)?;
+    builder
+        .set_private_key_file(&tls.private_key_file, SslFiletype::PEM)
+        .map_err(to_io_error)?;
+    builder
+        .set_certificate_chain_file(&tls.certificate_file)
+        .map_err(to_io_error)?;
+    Ok(builder)
+}
@@ -0,0 +1,181 @@
+use server_config::{Config, ConfigError};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("server_config-{}-{}", std::process::id(), name))
+}
+
+/// Writes the configuration file, returning its path.
+fn config_file(name: &str, text: &str) -> String {
+    let path = temp_path(name);
+    std::fs::write(&path, text).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn load(args: &[&str], env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let env: HashMap<String, String> = env
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Config::load_from(
+        "TEST",
+        args.iter().map(|arg| arg.to_string()).collect(),
+        |name| env.get(name).cloned(),
+    )
+}
+
+#[test]
+fn needs_no_file() {
+    let config = load(&["persons.db"], &[]).unwrap();
+    assert_eq!(config.server.bind_address, "127.0.0.1:8080");
+    assert_eq!(config.server.workers, None);
+    assert_eq!(config.server.log_level, "info");
+    assert!(config.server.tls.is_none());
+    assert_eq!(config.storage.database_file, None);
+    assert_eq!(config.args, vec!["persons.db"]);
+    assert_eq!(config.scheme(), "http");
+    assert_eq!(config.templates_glob(), "templates/**/*");
+}
+
+#[test]
+fn overrides_the_file_by_the_environment_and_the_environment_by_the_flags() {
+    let file = config_file(
+        "precedence.toml",
+        "[server]
+        bind_address = \"127.0.0.1:9000\"
+        workers = 2
+        log_level = \"debug\"
+
+        [storage]
+        database_file = \"file.db\"
+        max_upload_size = 100
+
+        [templates]
+        dir = \"/tmp/\"",
+    );
+    let env = [
+        ("TEST_CONFIG", file.as_str()),
+        ("TEST_WORKERS", "3"),
+        ("TEST_LOG_LEVEL", "warn"),
+        ("TEST_DATABASE_FILE", "env.db"),
+    ];
+    let config = load(
+        &["--workers", "4", "--database-file=flag.db", "extra"],
+        &env,
+    )
+    .unwrap();
+    assert_eq!(config.server.bind_address, "127.0.0.1:9000");
+    assert_eq!(config.server.workers, Some(4));
+    assert_eq!(config.server.log_level, "warn");
+    assert_eq!(config.storage.database_file.as_deref(), Some("flag.db"));
+    assert_eq!(config.storage.max_upload_size, Some(100));
+    assert_eq!(config.templates_glob(), "/tmp/**/*");
+    assert_eq!(config.args, vec!["extra"]);
+
+    // The --config flag wins over the variable.
+    let other = config_file("precedence_flag.toml", "[server]\nworkers = 7");
+    let config = load(&["--config", &other], &env[..1]).unwrap();
+    assert_eq!(config.server.workers, Some(7));
+    assert_eq!(config.server.bind_address, "127.0.0.1:8080");
+}
+
+fn error_of(result: Result<Config, ConfigError>) -> (Option<String>, String) {
+    let error = result.unwrap_err();
+    (error.key, error.source)
+}
+
+#[test]
+fn rejects_bad_values_naming_their_source() {
+    let key = |key: &str| Some(key.to_string());
+    assert_eq!(
+        error_of(load(&[], &[("TEST_WORKERS", "many")])),
+        (
+            key("server.workers"),
+            "environment variable TEST_WORKERS".to_string()
+        )
+    );
+    assert_eq!(
+        error_of(load(&["--workers=0"], &[])),
+        (key("server.workers"), "flag --workers".to_string())
+    );
+    assert_eq!(
+        error_of(load(&["--log-level", "loud"], &[])),
+        (key("server.log_level"), "flag --log-level".to_string())
+    );
+    assert_eq!(
+        error_of(load(&["--bind-address", "localhost"], &[])),
+        (
+            key("server.bind_address"),
+            "flag --bind-address".to_string()
+        )
+    );
+    assert_eq!(
+        error_of(load(&["--max-upload-size", "0"], &[])),
+        (
+            key("storage.max_upload_size"),
+            "flag --max-upload-size".to_string()
+        )
+    );
+    assert_eq!(
+        error_of(load(&["--shoe-size", "44"], &[])),
+        (None, "flag --shoe-size".to_string())
+    );
+    assert_eq!(
+        error_of(load(&["--workers"], &[])),
+        (None, "flag --workers".to_string())
+    );
+    let file = config_file("bad_value.toml", "[server]\nworkers = 0");
+    assert_eq!(
+        error_of(load(&["--config", &file], &[])),
+        (key("server.workers"), file)
+    );
+}
+
+#[test]
+fn rejects_invalid_files() {
+    let unknown_key = config_file("unknown_key.toml", "[server]\nshoe_size = 44");
+    assert_eq!(
+        error_of(load(&["--config", &unknown_key], &[])),
+        (None, unknown_key)
+    );
+    let missing = temp_path("missing.toml").to_str().unwrap().to_string();
+    assert_eq!(
+        error_of(load(&["--config", &missing], &[])),
+        (None, missing)
+    );
+}
+
+#[test]
+fn rejects_missing_tls_files() {
+    let missing = temp_path("missing.pem").to_str().unwrap().to_string();
+    let error = load(
+        &[
+            "--tls-certificate-file",
+            &missing,
+            "--tls-private-key-file",
+            &missing,
+        ],
+        &[],
+    )
+    .unwrap_err();
+    if cfg!(feature = "tls") {
+        assert_eq!(error.key.as_deref(), Some("server.tls.certificate_file"));
+        assert!(
+            error.message.starts_with("cannot read"),
+            "{}",
+            error.message
+        );
+    } else {
+        assert_eq!(error.key.as_deref(), Some("server.tls"));
+    }
+
+    let certificate = config_file("certificate.pem", "");
+    let error = load(&["--tls-certificate-file", &certificate], &[]).unwrap_err();
+    if cfg!(feature = "tls") {
+        assert_eq!(error.key.as_deref(), Some("server.tls.private_key_file"));
+        assert_eq!(error.message, "missing, but required by TLS");
+    } else {
+        assert_eq!(error.key.as_deref(), Some("server.tls"));
+    }
+}
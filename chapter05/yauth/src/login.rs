@@ -1,7 +1,8 @@
 use yew::services::DialogService;
 use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 
-use crate::db_access::{DbConnection, User};
+use crate::db_access::DbConnection;
+use persons_domain::User;
 
 pub struct LoginModel {
     dialog: DialogService,
@@ -55,21 +56,17 @@ impl Component for LoginModel {
             LoginMsg::UsernameChanged(username) => self.username = username,
             LoginMsg::PasswordChanged(password) => self.password = password,
             LoginMsg::LoginPressed => {
-                if let Some(user) = self
+                match self
                     .db_connection
                     .borrow()
-                    .get_user_by_username(&self.username)
+                    .check_credentials(&self.username, &self.password)
                 {
-                    if user.password == self.password {
+                    Ok(user) => {
                         if let Some(ref go_to_page) = self.when_logged_in {
-                            go_to_page.emit(user.clone());
+                            go_to_page.emit(user);
                         }
-                    } else {
-                        self.dialog
-                            .alert("Invalid password for the specified user.");
                     }
-                } else {
-                    self.dialog.alert("User not found.");
+                    Err(msg) => self.dialog.alert(msg),
                 }
             }
         }
@@ -8,7 +8,8 @@ mod persons_list;
 use crate::login::LoginModel;
 use crate::one_person::OnePersonModel;
 use crate::persons_list::PersonsListModel;
-use db_access::{DbConnection, DbPrivilege, Person, User};
+use db_access::DbConnection;
+use persons_domain::{Permission, Person, User};
 
 enum Page {
     Login,
@@ -19,7 +20,7 @@ enum Page {
 struct MainModel {
     page: Page,
     current_user: Option<String>,
-    can_write: bool,
+    permissions: Vec<Permission>,
     db_connection: std::rc::Rc<std::cell::RefCell<DbConnection>>,
 }
 
@@ -39,7 +40,7 @@ impl Component for MainModel {
         MainModel {
             page: Page::Login,
             current_user: None,
-            can_write: false,
+            permissions: vec![],
             db_connection: std::rc::Rc::new(std::cell::RefCell::new(DbConnection::new())),
         }
     }
@@ -49,7 +50,7 @@ impl Component for MainModel {
             MainMsg::LoggedIn(user) => {
                 self.page = Page::PersonsList;
                 self.current_user = Some(user.username);
-                self.can_write = user.privileges.contains(&DbPrivilege::CanWrite);
+                self.permissions = user.role.permissions().to_vec();
             }
             MainMsg::ChangeUserPressed => self.page = Page::Login,
             MainMsg::GoToOnePersonPage(person) => self.page = Page::OnePerson(person),
@@ -110,7 +111,7 @@ impl Renderable<MainModel> for MainModel {
                         },
                         Page::PersonsList => html! {
                             <PersonsListModel:
-                                can_write=self.can_write,
+                                permissions=self.permissions.clone(),
                                 go_to_one_person_page=MainMsg::GoToOnePersonPage,
                                 db_connection=Some(self.db_connection.clone()),
                             />
@@ -119,7 +120,7 @@ impl Renderable<MainModel> for MainModel {
                             <OnePersonModel:
                                 id=match person { Some(p) => Some(p.id), None => None },
                                 name=match person { Some(p) => p.name.clone(), None => "".to_string() },
-                                can_write=self.can_write,
+                                permissions=self.permissions.clone(),
                                 go_to_persons_list_page=|_| MainMsg::GoToPersonsListPage,
                                 db_connection=Some(self.db_connection.clone()),
                             />
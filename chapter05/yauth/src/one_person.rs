@@ -1,10 +1,11 @@
-use crate::db_access::{DbConnection, Person};
+use crate::db_access::DbConnection;
+use persons_domain::{Permission, Person, PersonsStore};
 use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 
 pub struct OnePersonModel {
     id: Option<u32>,
     name: String,
-    can_write: bool,
+    permissions: Vec<Permission>,
     is_inserting: bool,
     go_to_persons_list_page: Option<Callback<()>>,
     db_connection: std::rc::Rc<std::cell::RefCell<DbConnection>>,
@@ -21,7 +22,7 @@ pub enum OnePersonMsg {
 pub struct OnePersonProps {
     pub id: Option<u32>,
     pub name: String,
-    pub can_write: bool,
+    pub permissions: Vec<Permission>,
     pub go_to_persons_list_page: Option<Callback<()>>,
     pub db_connection: Option<std::rc::Rc<std::cell::RefCell<DbConnection>>>,
 }
@@ -31,7 +32,7 @@ impl Default for OnePersonProps {
         OnePersonProps {
             id: None,
             name: "".to_string(),
-            can_write: false,
+            permissions: vec![],
             go_to_persons_list_page: None,
             db_connection: None,
         }
@@ -47,7 +48,7 @@ impl Component for OnePersonModel {
         OnePersonModel {
             id: props.id,
             name: props.name,
-            can_write: props.can_write,
+            permissions: props.permissions,
             is_inserting: props.id.is_none(),
             go_to_persons_list_page: props.go_to_persons_list_page,
             db_connection: props.db_connection.unwrap(),
@@ -58,16 +59,22 @@ impl Component for OnePersonModel {
         match msg {
             OnePersonMsg::NameChanged(name) => self.name = name,
             OnePersonMsg::SavePressed => {
-                if self.is_inserting {
-                    self.db_connection.borrow_mut().insert_person(Person {
-                        id: 0,
-                        name: self.name.clone(),
-                    });
-                } else {
-                    self.db_connection.borrow_mut().update_person(Person {
-                        id: self.id.unwrap(),
-                        name: self.name.clone(),
-                    });
+                {
+                    let mut db = self.db_connection.borrow_mut();
+                    if self.is_inserting {
+                        let _ = db.persons.insert_person(Person {
+                            id: 0,
+                            name: self.name.clone(),
+                            ..Person::default()
+                        });
+                    } else {
+                        // Only the name is edited here, so the other fields are kept.
+                        if let Ok(Some(mut person)) = db.persons.get_person_by_id(self.id.unwrap())
+                        {
+                            person.name = self.name.clone();
+                            let _ = db.persons.update_person(person);
+                        }
+                    }
                 }
 
                 if let Some(ref go_to_page) = self.go_to_persons_list_page {
@@ -86,7 +93,7 @@ impl Component for OnePersonModel {
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.id = props.id;
         self.name = props.name;
-        self.can_write = props.can_write;
+        self.permissions = props.permissions;
         self.is_inserting = props.id.is_none();
         self.go_to_persons_list_page = props.go_to_persons_list_page;
         self.db_connection = props.db_connection.unwrap();
@@ -94,6 +101,16 @@ impl Component for OnePersonModel {
     }
 }
 
+impl OnePersonModel {
+    fn can_save(&self) -> bool {
+        self.permissions.contains(if self.is_inserting {
+            &Permission::Insert
+        } else {
+            &Permission::Update
+        })
+    }
+}
+
 impl Renderable<OnePersonModel> for OnePersonModel {
     fn view(&self) -> Html<Self> {
         html! {
@@ -111,21 +128,20 @@ impl Renderable<OnePersonModel> for OnePersonModel {
                     <input
                         type="text",
                         value=&self.name,
-                        disabled=!self.can_write,
+                        disabled=!self.can_save(),
                         oninput=|e| OnePersonMsg::NameChanged(e.value),
                     />
                 </div>
                 <div>
                     <button
                         onclick=|_| OnePersonMsg::SavePressed,
-                        disabled=!self.can_write,
+                        disabled=!self.can_save(),
                     >
                         { if self.is_inserting { "Insert" } else { "Update" } }
                     </button>
                     { " " }
                     <button
                         onclick=|_| OnePersonMsg::CancelPressed,
-                        disabled=!self.can_write,
                     >
                         { "Cancel" }
                     </button>
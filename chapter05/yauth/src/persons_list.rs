@@ -1,7 +1,9 @@
 use yew::services::DialogService;
 use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 
-use crate::db_access::{DbConnection, Person};
+use persons_domain::{Permission, Person, PersonsStore};
+
+use crate::db_access::DbConnection;
 
 pub struct PersonsListModel {
     dialog: DialogService,
@@ -9,7 +11,7 @@ pub struct PersonsListModel {
     name_portion: String,
     filtered_persons: Vec<Person>,
     selected_ids: std::collections::HashSet<u32>,
-    can_write: bool,
+    permissions: Vec<Permission>,
     go_to_one_person_page: Option<Callback<Option<Person>>>,
     db_connection: std::rc::Rc<std::cell::RefCell<DbConnection>>,
 }
@@ -28,7 +30,7 @@ pub enum PersonsListMsg {
 
 #[derive(PartialEq, Clone)]
 pub struct PersonsListProps {
-    pub can_write: bool,
+    pub permissions: Vec<Permission>,
     pub go_to_one_person_page: Option<Callback<Option<Person>>>,
     pub db_connection: Option<std::rc::Rc<std::cell::RefCell<DbConnection>>>,
 }
@@ -36,7 +38,7 @@ pub struct PersonsListProps {
 impl Default for PersonsListProps {
     fn default() -> Self {
         PersonsListProps {
-            can_write: false,
+            permissions: vec![],
             go_to_one_person_page: None,
             db_connection: None,
         }
@@ -55,11 +57,16 @@ impl Component for PersonsListModel {
             name_portion: "".to_string(),
             filtered_persons: Vec::<Person>::new(),
             selected_ids: std::collections::HashSet::<u32>::new(),
-            can_write: props.can_write,
+            permissions: props.permissions,
             go_to_one_person_page: props.go_to_one_person_page,
             db_connection: props.db_connection.unwrap(),
         };
-        model.filtered_persons = model.db_connection.borrow().get_persons_by_partial_name("");
+        model.filtered_persons = model
+            .db_connection
+            .borrow()
+            .persons
+            .get_persons_by_partial_name("")
+            .unwrap_or_default();
         model
     }
 
@@ -79,7 +86,9 @@ impl Component for PersonsListModel {
                 self.filtered_persons = self
                     .db_connection
                     .borrow()
-                    .get_persons_by_partial_name(&self.name_portion);
+                    .persons
+                    .get_persons_by_partial_name(&self.name_portion)
+                    .unwrap_or_default();
             }
             PersonsListMsg::DeletePressed => {
                 if self
@@ -89,7 +98,7 @@ impl Component for PersonsListModel {
                     {
                         let mut db = self.db_connection.borrow_mut();
                         for id in &self.selected_ids {
-                            db.delete_by_id(*id);
+                            let _ = db.persons.delete_by_id(*id);
                         }
                     }
                     self.update(PersonsListMsg::FilterPressed);
@@ -109,13 +118,13 @@ impl Component for PersonsListModel {
                 }
             }
             PersonsListMsg::EditPressed(id) => {
-                match self.db_connection.borrow().get_person_by_id(id) {
-                    Some(person) => {
+                match self.db_connection.borrow().persons.get_person_by_id(id) {
+                    Ok(Some(person)) => {
                         if let Some(ref go_to_page) = self.go_to_one_person_page {
-                            go_to_page.emit(Some(person.clone()))
+                            go_to_page.emit(Some(person))
                         }
                     }
-                    None => self.dialog.alert("No person found with the indicated id."),
+                    _ => self.dialog.alert("No person found with the indicated id."),
                 }
             }
         }
@@ -123,14 +132,27 @@ impl Component for PersonsListModel {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.can_write = props.can_write;
+        self.permissions = props.permissions;
         self.go_to_one_person_page = props.go_to_one_person_page;
         self.db_connection = props.db_connection.unwrap();
-        self.filtered_persons = self.db_connection.borrow().get_persons_by_partial_name("");
+        self.filtered_persons = self
+            .db_connection
+            .borrow()
+            .persons
+            .get_persons_by_partial_name("")
+            .unwrap_or_default();
         true
     }
 }
 
+impl PersonsListModel {
+    /// Deleting more than one person at once requires a further permission.
+    fn can_delete_selection(&self) -> bool {
+        self.permissions.contains(&Permission::Delete)
+            && (self.selected_ids.len() <= 1 || self.permissions.contains(&Permission::BulkDelete))
+    }
+}
+
 impl Renderable<PersonsListModel> for PersonsListModel {
     fn view(&self) -> Html<Self> {
         html! {
@@ -161,14 +183,14 @@ impl Renderable<PersonsListModel> for PersonsListModel {
                 </div>
                 <button
                     onclick=|_| PersonsListMsg::DeletePressed,
-                    disabled=!self.can_write,
+                    disabled=!self.can_delete_selection(),
                 >
                     { "Delete Selected Persons" }
                     </button>
                 { " " }
                 <button
                     onclick=|_| PersonsListMsg::AddPressed,
-                    disabled=!self.can_write,
+                    disabled=!self.permissions.contains(&Permission::Insert),
                 >
                     { "Add New Person" }
                     </button>
@@ -1,28 +1,23 @@
+use persons_domain::User;
 use serde_derive::Deserialize;
 use yew::services::fetch::Request;
 
-pub const BACKEND_SITE: &str = "http://localhost:8080/";
+/// The persons_db server; one configured at another address is reached by
+/// building with the YCLIENT_BACKEND_SITE variable, ending with "/".
+pub const BACKEND_SITE: &str = match option_env!("YCLIENT_BACKEND_SITE") {
+    Some(site) => site,
+    None => "http://localhost:8080/",
+};
 
+/// The logged user, with the session token the backend has issued for them.
 #[derive(Clone, Debug, PartialEq, Deserialize)]
-pub struct Person {
-    pub id: u32,
-    pub name: String,
+pub struct LoginSession {
+    pub user: User,
+    pub token: String,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
-pub enum DbPrivilege {
-    CanRead,
-    CanWrite,
-}
-
-#[derive(Clone, Debug, PartialEq, Deserialize)]
-pub struct User {
-    pub username: String,
-    pub password: String,
-    pub privileges: Vec<DbPrivilege>,
-}
-
-pub fn add_auth<T>(username: &str, password: &str, request: &mut Request<T>) {
+/// Used only to log in, as the other requests send the session token.
+pub fn add_basic_auth<T>(username: &str, password: &str, request: &mut Request<T>) {
     let mut auth_string = "Basic ".to_string();
     base64::encode_config_buf(
         format!("{}:{}", username, password).as_bytes(),
@@ -33,3 +28,10 @@ pub fn add_auth<T>(username: &str, password: &str, request: &mut Request<T>) {
         .headers_mut()
         .append("authorization", auth_string.parse().unwrap());
 }
+
+pub fn add_auth<T>(token: &str, request: &mut Request<T>) {
+    request.headers_mut().append(
+        "authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+}
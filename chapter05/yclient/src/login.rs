@@ -5,7 +5,7 @@ use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 use yew::services::{ConsoleService, DialogService};
 use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 
-use crate::common::{add_auth, User, BACKEND_SITE};
+use crate::common::{add_basic_auth, LoginSession, BACKEND_SITE};
 
 pub struct LoginModel {
     fetching: bool,
@@ -15,7 +15,7 @@ pub struct LoginModel {
     dialog: DialogService,
     username: String,
     password: String,
-    when_logged_in: Option<Callback<User>>,
+    when_logged_in: Option<Callback<LoginSession>>,
     console: ConsoleService,
 }
 
@@ -24,22 +24,20 @@ pub enum LoginMsg {
     UsernameChanged(String),
     PasswordChanged(String),
     LoginPressed,
-    ReadyLogin(User),
+    ReadyLogin(LoginSession),
     Failure(String),
 }
 
 #[derive(PartialEq, Clone)]
 pub struct LoginProps {
     pub username: String,
-    pub password: String,
-    pub when_logged_in: Option<Callback<User>>,
+    pub when_logged_in: Option<Callback<LoginSession>>,
 }
 
 impl Default for LoginProps {
     fn default() -> Self {
         LoginProps {
             username: "".to_string(),
-            password: "".to_string(),
             when_logged_in: None,
         }
     }
@@ -47,7 +45,7 @@ impl Default for LoginProps {
 
 #[derive(Deserialize)]
 enum AuthenticationResult {
-    LoggedUser(User),
+    LoggedUser(LoginSession),
     ErrorMessage(String),
 }
 
@@ -64,7 +62,7 @@ impl Component for LoginModel {
             link,
             dialog: DialogService::new(),
             username: props.username,
-            password: props.password,
+            password: "".to_string(),
             when_logged_in: props.when_logged_in,
             console: ConsoleService::new(),
         }
@@ -85,8 +83,8 @@ impl Component for LoginModel {
                         let (_, Json(data)) = response.into_parts();
                         match data {
                             Ok(auth_res) => match auth_res {
-                                AuthenticationResult::LoggedUser(user) => {
-                                    LoginMsg::ReadyLogin(user)
+                                AuthenticationResult::LoggedUser(session) => {
+                                    LoginMsg::ReadyLogin(session)
                                 }
                                 AuthenticationResult::ErrorMessage(msg) => LoginMsg::Failure(msg),
                             },
@@ -99,14 +97,16 @@ impl Component for LoginModel {
                 let mut request = Request::get(format!("{}authenticate", BACKEND_SITE))
                     .body(Nothing)
                     .unwrap();
-                add_auth(&self.username, &self.password, &mut request);
+                add_basic_auth(&self.username, &self.password, &mut request);
                 self.ft = Some(self.fetch_service.fetch(request, callback));
             }
-            LoginMsg::ReadyLogin(user) => {
+            LoginMsg::ReadyLogin(session) => {
                 self.fetching = false;
-                self.console.log(&format!("User: {:?}", user));
+                // The password is no more needed, as the token will be sent.
+                self.password = "".to_string();
+                self.console.log(&format!("User: {:?}", session.user));
                 if let Some(ref go_to_page) = self.when_logged_in {
-                    go_to_page.emit(user.clone());
+                    go_to_page.emit(session);
                 }
             }
             LoginMsg::Failure(msg) => {
@@ -121,7 +121,6 @@ impl Component for LoginModel {
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.username = props.username;
-        self.password = props.password;
         self.when_logged_in = props.when_logged_in;
         true
     }
@@ -143,6 +142,7 @@ impl Renderable<LoginModel> for LoginModel {
                     <label>{ "Password: " }</label>
                     <input
                         type="password",
+                        value=&self.password,
                         oninput=|e| LoginMsg::PasswordChanged(e.value),
                     />
                 </div>
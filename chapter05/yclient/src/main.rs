@@ -1,3 +1,6 @@
+use failure::Error;
+use yew::format::{Json, Nothing};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
 
 mod common;
@@ -5,7 +8,9 @@ mod login;
 mod one_person;
 mod persons_list;
 
-use crate::common::{DbPrivilege, Person, User};
+use persons_domain::{Permission, Person};
+
+use crate::common::{add_auth, LoginSession, BACKEND_SITE};
 use crate::login::LoginModel;
 use crate::one_person::OnePersonModel;
 use crate::persons_list::PersonsListModel;
@@ -19,13 +24,17 @@ enum Page {
 struct MainModel {
     page: Page,
     username: String,
-    password: String,
-    can_write: bool,
+    token: String,
+    permissions: Vec<Permission>,
+    fetch_service: FetchService,
+    ft: Option<FetchTask>,
+    link: ComponentLink<MainModel>,
 }
 
 enum MainMsg {
-    LoggedIn(User),
+    LoggedIn(LoginSession),
     ChangeUserPressed,
+    LoggedOut,
     GoToOnePersonPage(Option<Person>),
     GoToPersonsListPage,
 }
@@ -35,24 +44,45 @@ impl Component for MainModel {
 
     type Properties = ();
 
-    fn create(_props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
         MainModel {
             page: Page::Login,
             username: "".to_string(),
-            password: "".to_string(),
-            can_write: false,
+            token: "".to_string(),
+            permissions: vec![],
+            fetch_service: FetchService::new(),
+            ft: None,
+            link,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            MainMsg::LoggedIn(user) => {
+            MainMsg::LoggedIn(session) => {
                 self.page = Page::PersonsList;
-                self.username = user.username;
-                self.password = user.password;
-                self.can_write = user.privileges.contains(&DbPrivilege::CanWrite);
+                self.username = session.user.username;
+                self.token = session.token;
+                self.permissions = session.user.role.permissions().to_vec();
+            }
+            MainMsg::ChangeUserPressed => {
+                // Revokes the token on the backend; the login page is shown
+                // even if the backend cannot be reached.
+                let callback = self
+                    .link
+                    .send_back(|_: Response<Json<Result<bool, Error>>>| MainMsg::LoggedOut);
+                let mut request = Request::post(format!("{}logout", BACKEND_SITE))
+                    .body(Nothing)
+                    .unwrap();
+                add_auth(&self.token, &mut request);
+                self.ft = Some(self.fetch_service.fetch(request, callback));
+                return false;
+            }
+            MainMsg::LoggedOut => {
+                self.ft = None;
+                self.token = "".to_string();
+                self.permissions.clear();
+                self.page = Page::Login;
             }
-            MainMsg::ChangeUserPressed => self.page = Page::Login,
             MainMsg::GoToOnePersonPage(person) => self.page = Page::OnePerson(person),
             MainMsg::GoToPersonsListPage => self.page = Page::PersonsList,
         }
@@ -66,6 +96,7 @@ impl Renderable<MainModel> for MainModel {
                 <style>
                 { "
                     .current-user { color: #0000C0}
+                    .field-error { color: #C00000}
                 " }
                 </style>
                 <header>
@@ -105,25 +136,21 @@ impl Renderable<MainModel> for MainModel {
                             <LoginModel:
                                 when_logged_in=MainMsg::LoggedIn,
                                 username=self.username.clone(),
-                                password=self.password.clone(),
                             />
                         },
                         Page::PersonsList => html! {
                             <PersonsListModel:
-                                can_write=self.can_write,
+                                permissions=self.permissions.clone(),
                                 go_to_one_person_page=MainMsg::GoToOnePersonPage,
-                                username=self.username.clone(),
-                                password=self.password.clone(),
+                                token=self.token.clone(),
                             />
                         },
                         Page::OnePerson(person) => html! {
                             <OnePersonModel:
-                                id=match person { Some(p) => Some(p.id), None => None },
-                                name=match person { Some(p) => p.name.clone(), None => "".to_string() },
-                                can_write=self.can_write,
+                                person=person.clone(),
+                                permissions=self.permissions.clone(),
                                 go_to_persons_list_page=|_| MainMsg::GoToPersonsListPage,
-                                username=self.username.clone(),
-                                password=self.password.clone(),
+                                token=self.token.clone(),
                             />
                         },
                     }
@@ -1,9 +1,11 @@
 use failure::Error;
-use yew::format::{Json, Nothing};
+use yew::format::Json;
 use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 use yew::services::{ConsoleService, DialogService};
 use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 
+use persons_domain::{FieldError, Permission, Person};
+
 use crate::common::{add_auth, BACKEND_SITE};
 
 pub struct OnePersonModel {
@@ -12,48 +14,58 @@ pub struct OnePersonModel {
     ft: Option<FetchTask>,
     link: ComponentLink<OnePersonModel>,
     dialog: DialogService,
-    id: Option<u32>,
-    name: String,
-    can_write: bool,
+    person: Person,
+    errors: Vec<FieldError>,
+    permissions: Vec<Permission>,
     is_inserting: bool,
     go_to_persons_list_page: Option<Callback<()>>,
     console: ConsoleService,
-    username: String,
-    password: String,
+    token: String,
 }
 
 #[derive(Debug)]
 pub enum OnePersonMsg {
     NameChanged(String),
+    EmailChanged(String),
+    BirthDateChanged(String),
+    PhoneChanged(String),
+    NotesChanged(String),
     SavePressed,
     CancelPressed,
     SavedPerson,
+    InvalidFields(Vec<FieldError>),
     Failure(String),
 }
 
 #[derive(PartialEq, Clone)]
 pub struct OnePersonProps {
-    pub id: Option<u32>,
-    pub name: String,
-    pub can_write: bool,
+    /// The person to edit, or `None` to insert a new one.
+    pub person: Option<Person>,
+    pub permissions: Vec<Permission>,
     pub go_to_persons_list_page: Option<Callback<()>>,
-    pub username: String,
-    pub password: String,
+    pub token: String,
 }
 
 impl Default for OnePersonProps {
     fn default() -> Self {
         OnePersonProps {
-            id: None,
-            name: "".to_string(),
-            can_write: false,
+            person: None,
+            permissions: vec![],
             go_to_persons_list_page: None,
-            username: String::new(),
-            password: String::new(),
+            token: String::new(),
         }
     }
 }
 
+/// The value of an optional text field, with an empty input meaning `None`.
+fn optional_text(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
 impl Component for OnePersonModel {
     type Message = OnePersonMsg;
     type Properties = OnePersonProps;
@@ -65,49 +77,53 @@ impl Component for OnePersonModel {
             ft: None,
             link,
             dialog: DialogService::new(),
-            id: props.id,
-            name: props.name,
-            can_write: props.can_write,
-            is_inserting: props.id.is_none(),
+            is_inserting: props.person.is_none(),
+            person: props.person.unwrap_or_default(),
+            errors: vec![],
+            permissions: props.permissions,
             go_to_persons_list_page: props.go_to_persons_list_page,
             console: ConsoleService::new(),
-            username: props.username,
-            password: props.password,
+            token: props.token,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            OnePersonMsg::NameChanged(name) => self.name = name,
+            OnePersonMsg::NameChanged(name) => self.person.name = name,
+            OnePersonMsg::EmailChanged(email) => self.person.email = optional_text(email),
+            OnePersonMsg::BirthDateChanged(birth_date) => {
+                self.person.birth_date = optional_text(birth_date)
+            }
+            OnePersonMsg::PhoneChanged(phone) => self.person.phone = optional_text(phone),
+            OnePersonMsg::NotesChanged(notes) => self.person.notes = optional_text(notes),
             OnePersonMsg::SavePressed => {
                 self.fetching = true;
-                let callback =
-                    self.link
-                        .send_back(move |response: Response<Json<Result<bool, Error>>>| {
-                            let (meta, Json(_)) = response.into_parts();
-                            if meta.status.is_success() {
-                                OnePersonMsg::SavedPerson
-                            } else {
-                                OnePersonMsg::Failure("Cannot save the person.".to_string())
-                            }
-                        });
-
-                let encoded_name =
-                    url::form_urlencoded::byte_serialize(self.name.as_bytes()).collect::<String>();
+                // The body of a successful response is ignored,
+                // while a rejected person comes back with its field errors.
+                let callback = self.link.send_back(
+                    move |response: Response<Json<Result<Vec<FieldError>, Error>>>| {
+                        let (meta, Json(data)) = response.into_parts();
+                        if meta.status.is_success() {
+                            OnePersonMsg::SavedPerson
+                        } else if let (400, Ok(errors)) = (meta.status.as_u16(), data) {
+                            OnePersonMsg::InvalidFields(errors)
+                        } else {
+                            OnePersonMsg::Failure("Cannot save the person.".to_string())
+                        }
+                    },
+                );
+
+                let url = format!("{}one_person", BACKEND_SITE);
                 let mut request = if self.is_inserting {
-                    Request::post(format!("{}one_person?name={}", BACKEND_SITE, encoded_name))
+                    Request::post(url)
                 } else {
-                    Request::put(format!(
-                        "{}one_person?id={}&name={}",
-                        BACKEND_SITE,
-                        self.id.unwrap(),
-                        encoded_name
-                    ))
+                    Request::put(url)
                 }
-                .body(Nothing)
+                .header("Content-Type", "application/json")
+                .body(Json(&self.person))
                 .unwrap();
 
-                add_auth(&self.username, &self.password, &mut request);
+                add_auth(&self.token, &mut request);
                 self.ft = Some(self.fetch_service.fetch(request, callback));
             }
             OnePersonMsg::CancelPressed => {
@@ -121,6 +137,11 @@ impl Component for OnePersonModel {
                     go_to_page.emit(());
                 }
             }
+            OnePersonMsg::InvalidFields(errors) => {
+                self.fetching = false;
+                self.console.log(&format!("InvalidFields: {:?}.", errors));
+                self.errors = errors;
+            }
             OnePersonMsg::Failure(msg) => {
                 self.fetching = false;
                 self.console.log(&format!("Failure: {:?}.", msg));
@@ -132,26 +153,46 @@ impl Component for OnePersonModel {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.id = props.id;
-        self.name = props.name;
-        self.can_write = props.can_write;
-        self.is_inserting = props.id.is_none();
+        self.is_inserting = props.person.is_none();
+        self.person = props.person.unwrap_or_default();
+        self.errors.clear();
+        self.permissions = props.permissions;
         self.go_to_persons_list_page = props.go_to_persons_list_page;
-        self.username = props.username;
-        self.password = props.password;
+        self.token = props.token;
         true
     }
 }
 
+impl OnePersonModel {
+    fn can_save(&self) -> bool {
+        self.permissions.contains(if self.is_inserting {
+            &Permission::Insert
+        } else {
+            &Permission::Update
+        })
+    }
+
+    /// The message explaining why the last save rejected the given field, if any.
+    fn view_error(&self, field: &str) -> Html<Self> {
+        match self.errors.iter().find(|e| e.field == field) {
+            Some(error) => html! {
+                <span class="field-error",>{ format!(" {}", error.message) }</span>
+            },
+            None => html! { <span/> },
+        }
+    }
+}
+
 impl Renderable<OnePersonModel> for OnePersonModel {
     fn view(&self) -> Html<Self> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
         html! {
             <div>
                 <div>
                     <label>{ "Id: " }</label>
                     <input
                         type="number",
-                        value=match self.id { Some(id) => format!("{}", id), _ => "".to_string() },
+                        value=if self.is_inserting { "".to_string() } else { format!("{}", self.person.id) },
                         disabled=true,
                     />
                 </div>
@@ -159,22 +200,63 @@ impl Renderable<OnePersonModel> for OnePersonModel {
                     <label>{ "Name: " }</label>
                     <input
                         type="text",
-                        value=&self.name,
-                        disabled=!self.can_write,
+                        value=&self.person.name,
+                        disabled=!self.can_save(),
                         oninput=|e| OnePersonMsg::NameChanged(e.value),
                     />
+                    { self.view_error("name") }
+                </div>
+                <div>
+                    <label>{ "E-mail: " }</label>
+                    <input
+                        type="email",
+                        value=text(&self.person.email),
+                        disabled=!self.can_save(),
+                        oninput=|e| OnePersonMsg::EmailChanged(e.value),
+                    />
+                    { self.view_error("email") }
+                </div>
+                <div>
+                    <label>{ "Birth date: " }</label>
+                    <input
+                        type="date",
+                        value=text(&self.person.birth_date),
+                        disabled=!self.can_save(),
+                        oninput=|e| OnePersonMsg::BirthDateChanged(e.value),
+                    />
+                    { self.view_error("birth_date") }
+                </div>
+                <div>
+                    <label>{ "Phone: " }</label>
+                    <input
+                        type="tel",
+                        value=text(&self.person.phone),
+                        disabled=!self.can_save(),
+                        oninput=|e| OnePersonMsg::PhoneChanged(e.value),
+                    />
+                    { self.view_error("phone") }
+                </div>
+                <div>
+                    <label>{ "Notes: " }</label>
+                    <textarea
+                        rows=4,
+                        cols=40,
+                        value=text(&self.person.notes),
+                        disabled=!self.can_save(),
+                        oninput=|e| OnePersonMsg::NotesChanged(e.value),
+                    />
+                    { self.view_error("notes") }
                 </div>
                 <div>
                     <button
                         onclick=|_| OnePersonMsg::SavePressed,
-                        disabled=!self.can_write,
+                        disabled=!self.can_save(),
                     >
                         { if self.is_inserting { "Insert" } else { "Update" } }
                     </button>
                     { " " }
                     <button
                         onclick=|_| OnePersonMsg::CancelPressed,
-                        disabled=!self.can_write,
                     >
                         { "Cancel" }
                     </button>
@@ -4,7 +4,9 @@ use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 use yew::services::{ConsoleService, DialogService};
 use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 
-use crate::common::{add_auth, Person, BACKEND_SITE};
+use persons_domain::{Permission, Person, PersonsPage, PersonsQuery, SortColumn};
+
+use crate::common::{add_auth, BACKEND_SITE};
 
 pub struct PersonsListModel {
     fetching: bool,
@@ -13,14 +15,15 @@ pub struct PersonsListModel {
     link: ComponentLink<PersonsListModel>,
     dialog: DialogService,
     id_to_find: Option<u32>,
-    name_portion: String,
+    query: PersonsQuery,
     filtered_persons: Vec<Person>,
+    total: u32,
+    page_count: u32,
     selected_ids: std::collections::HashSet<u32>,
-    can_write: bool,
+    permissions: Vec<Permission>,
     go_to_one_person_page: Option<Callback<Option<Person>>>,
     console: ConsoleService,
-    username: String,
-    password: String,
+    token: String,
 }
 
 #[derive(Debug)]
@@ -28,12 +31,19 @@ pub enum PersonsListMsg {
     IdChanged(String),
     FindPressed,
     PartialNameChanged(String),
+    EmailChanged(String),
+    PhoneChanged(String),
+    BornFromChanged(String),
+    BornToChanged(String),
     FilterPressed,
+    SortPressed(SortColumn),
+    PageChanged(u32),
+    FetchPersons,
     DeletePressed,
     AddPressed,
     SelectionToggled(u32),
     EditPressed(u32),
-    ReadyFilteredPersons(Result<Vec<Person>, Error>),
+    ReadyFilteredPersons(Result<PersonsPage, Error>),
     ReadyDeletedPersons(Result<u32, Error>),
     ReadyPersonToEdit(Result<Person, Error>),
     Failure(String),
@@ -41,19 +51,17 @@ pub enum PersonsListMsg {
 
 #[derive(PartialEq, Clone)]
 pub struct PersonsListProps {
-    pub can_write: bool,
+    pub permissions: Vec<Permission>,
     pub go_to_one_person_page: Option<Callback<Option<Person>>>,
-    pub username: String,
-    pub password: String,
+    pub token: String,
 }
 
 impl Default for PersonsListProps {
     fn default() -> Self {
         PersonsListProps {
-            can_write: false,
+            permissions: vec![],
             go_to_one_person_page: None,
-            username: "".to_string(),
-            password: "".to_string(),
+            token: "".to_string(),
         }
     }
 }
@@ -70,16 +78,17 @@ impl Component for PersonsListModel {
             link,
             dialog: DialogService::new(),
             id_to_find: None,
-            name_portion: "".to_string(),
+            query: PersonsQuery::default(),
             filtered_persons: Vec::<Person>::new(),
+            total: 0,
+            page_count: 1,
             selected_ids: std::collections::HashSet::<u32>::new(),
-            can_write: props.can_write,
+            permissions: props.permissions,
             go_to_one_person_page: props.go_to_one_person_page,
             console: ConsoleService::new(),
-            username: props.username,
-            password: props.password,
+            token: props.token,
         };
-        model.update(PersonsListMsg::FilterPressed);
+        model.update(PersonsListMsg::FetchPersons);
         model
     }
 
@@ -94,7 +103,11 @@ impl Component for PersonsListModel {
                     self.dialog.alert("No id specified.");
                 }
             },
-            PersonsListMsg::PartialNameChanged(s) => self.name_portion = s,
+            PersonsListMsg::PartialNameChanged(s) => self.query.partial_name = s,
+            PersonsListMsg::EmailChanged(s) => self.query.email = s,
+            PersonsListMsg::PhoneChanged(s) => self.query.phone = s,
+            PersonsListMsg::BornFromChanged(s) => self.query.born_from = optional_date(s),
+            PersonsListMsg::BornToChanged(s) => self.query.born_to = optional_date(s),
             PersonsListMsg::DeletePressed => {
                 if self
                     .dialog
@@ -124,7 +137,7 @@ impl Component for PersonsListModel {
                     .body(Nothing)
                     .unwrap();
 
-                    add_auth(&self.username, &self.password, &mut request);
+                    add_auth(&self.token, &mut request);
                     self.ft = Some(self.fetch_service.fetch(request, callback));
                 }
             }
@@ -134,7 +147,7 @@ impl Component for PersonsListModel {
                 self.console
                     .log(&format!("ReadyDeletedPersons: {}.", num_deleted));
 
-                self.update(PersonsListMsg::FilterPressed);
+                self.update(PersonsListMsg::FetchPersons);
                 self.dialog.alert("Deleted.");
             }
             PersonsListMsg::AddPressed => {
@@ -169,15 +182,12 @@ impl Component for PersonsListModel {
                     .body(Nothing)
                     .unwrap();
 
-                add_auth(&self.username, &self.password, &mut request);
+                add_auth(&self.token, &mut request);
                 self.ft = Some(self.fetch_service.fetch(request, callback));
             }
             PersonsListMsg::ReadyPersonToEdit(person) => {
                 self.fetching = false;
-                let person = person.unwrap_or(Person {
-                    id: 0,
-                    name: "".to_string(),
-                });
+                let person = person.unwrap_or_default();
                 if let Some(ref go_to_page) = self.go_to_one_person_page {
                     self.console
                         .log(&format!("ReadyPersonToEdit: {:?}.", person));
@@ -185,9 +195,24 @@ impl Component for PersonsListModel {
                 }
             }
             PersonsListMsg::FilterPressed => {
+                self.query.page = 1;
+                self.update(PersonsListMsg::FetchPersons);
+            }
+            PersonsListMsg::SortPressed(column) => {
+                // Sorting again by the same column reverses the order.
+                self.query.descending = self.query.sort_by == column && !self.query.descending;
+                self.query.sort_by = column;
+                self.query.page = 1;
+                self.update(PersonsListMsg::FetchPersons);
+            }
+            PersonsListMsg::PageChanged(page) => {
+                self.query.page = page;
+                self.update(PersonsListMsg::FetchPersons);
+            }
+            PersonsListMsg::FetchPersons => {
                 self.fetching = true;
                 let callback = self.link.send_back(
-                    move |response: Response<Json<Result<Vec<Person>, Error>>>| {
+                    move |response: Response<Json<Result<PersonsPage, Error>>>| {
                         let (meta, Json(data)) = response.into_parts();
                         if meta.status.is_success() {
                             PersonsListMsg::ReadyFilteredPersons(data)
@@ -197,21 +222,32 @@ impl Component for PersonsListModel {
                     },
                 );
 
-                let mut request = Request::get(format!(
-                    "{}persons?partial_name={}",
-                    BACKEND_SITE,
-                    url::form_urlencoded::byte_serialize(self.name_portion.as_bytes())
-                        .collect::<String>()
-                ))
-                .body(Nothing)
-                .unwrap();
+                let mut request =
+                    Request::get(format!("{}persons?{}", BACKEND_SITE, self.query_string()))
+                        .body(Nothing)
+                        .unwrap();
 
-                add_auth(&self.username, &self.password, &mut request);
+                add_auth(&self.token, &mut request);
                 self.ft = Some(self.fetch_service.fetch(request, callback));
             }
             PersonsListMsg::ReadyFilteredPersons(response) => {
                 self.fetching = false;
-                self.filtered_persons = response.unwrap_or_else(|_| vec![]);
+                match response {
+                    Ok(page) => {
+                        // After a deletion the current page may no longer exist.
+                        if page.persons.is_empty() && page.page > page.page_count() {
+                            return self.update(PersonsListMsg::PageChanged(page.page_count()));
+                        }
+                        self.total = page.total;
+                        self.page_count = page.page_count();
+                        self.filtered_persons = page.persons;
+                    }
+                    Err(_) => {
+                        self.total = 0;
+                        self.page_count = 1;
+                        self.filtered_persons.clear();
+                    }
+                }
                 self.console.log(&format!(
                     "ReadyFilteredPersons: {:?}.",
                     self.filtered_persons
@@ -228,17 +264,67 @@ impl Component for PersonsListModel {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.can_write = props.can_write;
+        self.permissions = props.permissions;
         self.go_to_one_person_page = props.go_to_one_person_page;
-        self.username = props.username;
-        self.password = props.password;
-        self.update(PersonsListMsg::FilterPressed);
+        self.token = props.token;
+        self.update(PersonsListMsg::FetchPersons);
         true
     }
 }
 
+/// The value of an optional date field, with an empty input meaning `None`.
+fn optional_date(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+impl PersonsListModel {
+    /// The query string of the current filters, sort order and page.
+    fn query_string(&self) -> String {
+        let encode = |value: &str| {
+            url::form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+        };
+        let q = &self.query;
+        format!(
+            "partial_name={}&email={}&phone={}&born_from={}&born_to={}&sort_by={}&order={}&page={}&page_size={}",
+            encode(&q.partial_name),
+            encode(&q.email),
+            encode(&q.phone),
+            encode(q.born_from.as_deref().unwrap_or("")),
+            encode(q.born_to.as_deref().unwrap_or("")),
+            q.sort_by.name(),
+            if q.descending { "desc" } else { "asc" },
+            q.page,
+            q.page_size
+        )
+    }
+
+    /// The title of a column header, marked if the persons are sorted by it.
+    fn sort_title(&self, column: SortColumn, title: &str) -> String {
+        if self.query.sort_by != column {
+            title.to_string()
+        } else if self.query.descending {
+            format!("{} \u{25BC}", title)
+        } else {
+            format!("{} \u{25B2}", title)
+        }
+    }
+
+    /// Deleting more than one person at once requires a further permission.
+    fn can_delete_selection(&self) -> bool {
+        self.permissions.contains(&Permission::Delete)
+            && (self.selected_ids.len() <= 1 || self.permissions.contains(&Permission::BulkDelete))
+    }
+}
+
 impl Renderable<PersonsListModel> for PersonsListModel {
     fn view(&self) -> Html<Self> {
+        let page = self.query.page;
+        let is_first_page = page <= 1;
+        let is_last_page = page >= self.page_count;
         html! {
             <div>
                 <div>
@@ -256,8 +342,35 @@ impl Renderable<PersonsListModel> for PersonsListModel {
                     <label>{ "Name portion: " }</label>
                     <input
                         type="text",
+                        value=&self.query.partial_name,
                         oninput=|e| PersonsListMsg::PartialNameChanged(e.value),
                     />
+                    <label>{ " E-mail: " }</label>
+                    <input
+                        type="text",
+                        value=&self.query.email,
+                        oninput=|e| PersonsListMsg::EmailChanged(e.value),
+                    />
+                    <label>{ " Phone: " }</label>
+                    <input
+                        type="text",
+                        value=&self.query.phone,
+                        oninput=|e| PersonsListMsg::PhoneChanged(e.value),
+                    />
+                </div>
+                <div>
+                    <label>{ "Born from: " }</label>
+                    <input
+                        type="date",
+                        value=self.query.born_from.clone().unwrap_or_default(),
+                        oninput=|e| PersonsListMsg::BornFromChanged(e.value),
+                    />
+                    <label>{ " to: " }</label>
+                    <input
+                        type="date",
+                        value=self.query.born_to.clone().unwrap_or_default(),
+                        oninput=|e| PersonsListMsg::BornToChanged(e.value),
+                    />
                     { " " }
                     <button
                         onclick=|_| PersonsListMsg::FilterPressed,
@@ -267,14 +380,14 @@ impl Renderable<PersonsListModel> for PersonsListModel {
                 </div>
                 <button
                     onclick=|_| PersonsListMsg::DeletePressed,
-                    disabled=!self.can_write,
+                    disabled=!self.can_delete_selection(),
                 >
                     { "Delete Selected Persons" }
                     </button>
                 { " " }
                 <button
                     onclick=|_| PersonsListMsg::AddPressed,
-                    disabled=!self.can_write,
+                    disabled=!self.permissions.contains(&Permission::Insert),
                 >
                     { "Add New Person" }
                     </button>
@@ -286,14 +399,26 @@ impl Renderable<PersonsListModel> for PersonsListModel {
                                 <thead>
                                     <th></th>
                                     <th></th>
-                                    <th>{ "Id" }</th>
-                                    <th>{ "Name" }</th>
+                                    <th onclick=|_| PersonsListMsg::SortPressed(SortColumn::Id),>
+                                        { self.sort_title(SortColumn::Id, "Id") }
+                                    </th>
+                                    <th onclick=|_| PersonsListMsg::SortPressed(SortColumn::Name),>
+                                        { self.sort_title(SortColumn::Name, "Name") }
+                                    </th>
+                                    <th onclick=|_| PersonsListMsg::SortPressed(SortColumn::Email),>
+                                        { self.sort_title(SortColumn::Email, "E-mail") }
+                                    </th>
+                                    <th onclick=|_| PersonsListMsg::SortPressed(SortColumn::Phone),>
+                                        { self.sort_title(SortColumn::Phone, "Phone") }
+                                    </th>
                                 </thead>
                                 <tbody>
                                     {
                                         for self.filtered_persons.iter().map(|p| {
                                             let id = p.id;
                                             let name = p.name.clone();
+                                            let email = p.email.clone().unwrap_or_default();
+                                            let phone = p.phone.clone().unwrap_or_default();
                                             html! {
                                                 <tr>
                                                     <td><input
@@ -305,6 +430,8 @@ impl Renderable<PersonsListModel> for PersonsListModel {
                                                         onclick=|_| PersonsListMsg::EditPressed(id),>{ "Edit" }</button></td>
                                                     <td>{ id }</td>
                                                     <td>{ name }</td>
+                                                    <td>{ email }</td>
+                                                    <td>{ phone }</td>
                                                 </tr>
                                             }
                                         })
@@ -319,6 +446,21 @@ impl Renderable<PersonsListModel> for PersonsListModel {
                         }
                     }
                 }
+                <div>
+                    <button
+                        onclick=|_| PersonsListMsg::PageChanged(page - 1),
+                        disabled=is_first_page,
+                    >
+                        { "Previous" }
+                    </button>
+                    { format!(" Page {} of {} ({} persons) ", page, self.page_count, self.total) }
+                    <button
+                        onclick=|_| PersonsListMsg::PageChanged(page + 1),
+                        disabled=is_last_page,
+                    >
+                        { "Next" }
+                    </button>
+                </div>
             </div>
         }
     }